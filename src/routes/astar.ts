@@ -1,15 +1,11 @@
-import type { Layer, WasmAstar, WasmModuleAstar } from '../types';
+import type { AstarWorld, Layer, WasmAstar, WasmModuleAstar } from '../types';
 import { loadWasmModule, validateWasmModule } from '../wasm/loader';
 import { WasmLoadError, WasmInitError } from '../wasm/types';
 
 // Lazy WASM import - only load when init() is called
 let wasmModuleExports: {
   default: () => Promise<unknown>;
-  wasm_init: (debug: number, renderIntervalMs: number, windowWidth: number, windowHeight: number) => void;
-  tick: (elapsedTime: number) => void;
-  key_down: (keyCode: number) => void;
-  key_up: (keyCode: number) => void;
-  mouse_move: (x: number, y: number) => void;
+  World: new (layerBase: number) => AstarWorld;
 } | null = null;
 
 const getInitWasm = async (): Promise<unknown> => {
@@ -18,11 +14,7 @@ const getInitWasm = async (): Promise<unknown> => {
     const module = await import('../../pkg/wasm_astar/wasm_astar.js');
     wasmModuleExports = {
       default: module.default,
-      wasm_init: module.wasm_init,
-      tick: module.tick,
-      key_down: module.key_down,
-      key_up: module.key_up,
-      mouse_move: module.mouse_move,
+      World: module.World,
     };
   }
   if (!wasmModuleExports) {
@@ -41,6 +33,7 @@ const getLayerWrapper = (): HTMLElement => {
 
 const WASM_ASTAR: WasmAstar = {
   wasmModule: null,
+  world: null,
   wasmModulePath: '../pkg/wasm_astar',
   debug: false,
   renderIntervalMs: 1000,
@@ -78,20 +71,8 @@ function validateAstarModule(exports: unknown): WasmModuleAstar | null {
   if (!wasmModuleExports) {
     missingExports.push('module exports (wasmModuleExports is null)');
   } else {
-    if (typeof wasmModuleExports.wasm_init !== 'function') {
-      missingExports.push('wasm_init (function)');
-    }
-    if (typeof wasmModuleExports.tick !== 'function') {
-      missingExports.push('tick (function)');
-    }
-    if (typeof wasmModuleExports.key_down !== 'function') {
-      missingExports.push('key_down (function)');
-    }
-    if (typeof wasmModuleExports.key_up !== 'function') {
-      missingExports.push('key_up (function)');
-    }
-    if (typeof wasmModuleExports.mouse_move !== 'function') {
-      missingExports.push('mouse_move (function)');
+    if (typeof wasmModuleExports.World !== 'function') {
+      missingExports.push('World (class)');
     }
   }
   
@@ -113,11 +94,7 @@ function validateAstarModule(exports: unknown): WasmModuleAstar | null {
   
   return {
     memory,
-    wasm_init: wasmModuleExports.wasm_init,
-    tick: wasmModuleExports.tick,
-    key_down: wasmModuleExports.key_down,
-    key_up: wasmModuleExports.key_up,
-    mouse_move: wasmModuleExports.mouse_move,
+    World: wasmModuleExports.World,
   };
 }
 
@@ -136,19 +113,18 @@ export const init = async (): Promise<void> => {
   // Make functions available globally for wasm-bindgen
   const globalObj: { [key: string]: unknown } = globalThis;
   globalObj.js_random = (): number => wasmImports.js_random();
-  globalObj.js_random_range = (min: number, max: number): number => wasmImports.js_random_range(min, max);
   globalObj.js_log = (): void => wasmImports.js_log();
-  globalObj.js_request_tick = (): void => wasmImports.js_request_tick();
-  globalObj.js_start_interval_tick = (ms: number): void => wasmImports.js_start_interval_tick(ms);
+  globalObj.js_now = (): number => wasmImports.js_now();
   globalObj.js_create_layer = (id: string, key: number): void => wasmImports.js_create_layer(id, key);
   globalObj.js_set_screen_size = (width: number, height: number, quality: number): void => wasmImports.js_set_screen_size(width, height, quality);
   globalObj.js_set_layer_size = (layerId: number, width: number, height: number, quality: number): void => wasmImports.js_set_layer_size(layerId, width, height, quality);
-  globalObj.js_clear_screen = (layerId: number): void => wasmImports.js_clear_screen(layerId);
   globalObj.js_update = (): void => wasmImports.js_update();
-  globalObj.js_draw_tile = (layerId: number, px: number, py: number, size: number, ch: number, cs: number, cl: number, ca: number): void => wasmImports.js_draw_tile(layerId, px, py, size, ch, cs, cl, ca);
-  globalObj.js_draw_circle = (layerId: number, px: number, py: number, r: number, ch: number, cs: number, cl: number, ca: number): void => wasmImports.js_draw_circle(layerId, px, py, r, ch, cs, cl, ca);
-  globalObj.js_draw_fps = (layerId: number, fps: number): void => wasmImports.js_draw_fps(layerId, fps);
+  globalObj.js_draw_batch = (commands: Float32Array): void => wasmImports.js_draw_batch(commands);
+  globalObj.js_draw_line = (layerId: number, points: Float32Array, width: number, h: number, s: number, l: number, a: number): void => wasmImports.js_draw_line(layerId, points, width, h, s, l, a);
+  globalObj.js_draw_frame_stats = (layerId: number, fps: number, minMs: number, avgMs: number, maxMs: number, p99Ms: number): void => wasmImports.js_draw_frame_stats(layerId, fps, minMs, avgMs, maxMs, p99Ms);
   globalObj.js_path_count = (layerId: number, count: number): void => wasmImports.js_path_count(layerId, count);
+  globalObj.js_search_stats = (layerId: number, nodesExpanded: number, elapsedUs: number): void => wasmImports.js_search_stats(layerId, nodesExpanded, elapsedUs);
+  globalObj.js_draw_node_scores = (layerId: number, px: number, py: number, size: number, g: number, h: number, f: number): void => wasmImports.js_draw_node_scores(layerId, px, py, size, g, h, f);
   
   // Initialize WASM module using loadWasmModule helper
   try {
@@ -163,12 +139,14 @@ export const init = async (): Promise<void> => {
     
     WASM_ASTAR.wasmModule = wasmModule;
     
-    wasmModule.wasm_init(
-      debug ? 1 : 0,
-      renderIntervalMs,
-      window.innerWidth,
-      window.innerHeight
-    );
+    const world = new wasmModule.World(0);
+    WASM_ASTAR.world = world;
+    world.init(debug ? 1 : 0, window.innerWidth, window.innerHeight);
+    if (debug) {
+      startIntervalTick(world, renderIntervalMs);
+    } else {
+      startAnimationTick(world);
+    }
   } catch (error) {
     // Show detailed error
     if (errorEl) {
@@ -214,96 +192,141 @@ export const init = async (): Promise<void> => {
   // Initial cache
   updateCachedRect();
   
-  window.addEventListener('mousemove', (e: MouseEvent) => {
+  // Mouse position relative to the layers
+  const getMousePos = (e: MouseEvent): { x: number; y: number } | null => {
     // Use cached rect, only recalculate if null (safety check)
     if (!cachedRect) {
       updateCachedRect();
     }
     const rect = cachedRect;
     if (!rect) {
+      return null;
+    }
+    return { x: e.clientX - rect.left, y: e.clientY - rect.top };
+  };
+  
+  // Right or middle button drags, or shift with the left button, pan the
+  // camera instead of editing the map
+  let panFrom: { x: number; y: number } | null = null;
+  
+  window.addEventListener('mousemove', (e: MouseEvent) => {
+    const pos = getMousePos(e);
+    if (!pos || !WASM_ASTAR.world) {
+      return;
+    }
+    if (panFrom) {
+      WASM_ASTAR.world.pan_camera(pos.x - panFrom.x, pos.y - panFrom.y);
+      panFrom = pos;
+    } else {
+      WASM_ASTAR.world.mouse_move(pos.x, pos.y);
+    }
+  });
+  
+  layerWrapperEl.addEventListener('mousedown', (e: MouseEvent) => {
+    const pos = getMousePos(e);
+    if (!pos || !WASM_ASTAR.world) {
       return;
     }
-    const x = e.clientX - rect.left;
-    const y = e.clientY - rect.top;
-    if (WASM_ASTAR.wasmModule) {
-      WASM_ASTAR.wasmModule.mouse_move(x, y);
+    if (e.button !== 0 || e.shiftKey) {
+      e.preventDefault();
+      panFrom = pos;
+    } else {
+      WASM_ASTAR.world.mouse_down(pos.x, pos.y);
     }
   });
   
+  layerWrapperEl.addEventListener('contextmenu', (e: MouseEvent) => {
+    e.preventDefault();
+  });
+  
+  // On the window so releasing outside the map still ends the drag
+  window.addEventListener('mouseup', () => {
+    panFrom = null;
+    if (WASM_ASTAR.world) {
+      WASM_ASTAR.world.mouse_up();
+    }
+  });
+  
+  layerWrapperEl.addEventListener('wheel', (e: WheelEvent) => {
+    const pos = getMousePos(e);
+    if (pos && WASM_ASTAR.world) {
+      e.preventDefault();
+      WASM_ASTAR.world.zoom_camera(Math.exp(-e.deltaY * 0.002), pos.x, pos.y);
+    }
+  }, { passive: false });
+  
   window.addEventListener('keydown', (e: KeyboardEvent) => {
-    if (WASM_ASTAR.wasmModule) {
-      WASM_ASTAR.wasmModule.key_down(e.keyCode);
+    if (WASM_ASTAR.world) {
+      WASM_ASTAR.world.key_down(e.keyCode);
     }
   });
   
   window.addEventListener('keyup', (e: KeyboardEvent) => {
-    if (WASM_ASTAR.wasmModule) {
-      WASM_ASTAR.wasmModule.key_up(e.keyCode);
+    if (WASM_ASTAR.world) {
+      WASM_ASTAR.world.key_up(e.keyCode);
     }
   });
   
   layerWrapperEl.addEventListener('touchend', () => {
     // Simulating spacebar for mobile support
-    if (WASM_ASTAR.wasmModule) {
-      WASM_ASTAR.wasmModule.key_down(32);
+    if (WASM_ASTAR.world) {
+      WASM_ASTAR.world.key_down(32);
       requestAnimationFrame(() => {
-        if (WASM_ASTAR.wasmModule) {
-          WASM_ASTAR.wasmModule.key_up(32);
+        if (WASM_ASTAR.world) {
+          WASM_ASTAR.world.key_up(32);
         }
       });
     }
   });
 };
 
-const getWasmImports = () => {
-  let isIntervalTick = false;
+// Left stick of the first connected gamepad drives the move axis
+const pollGamepad = (world: AstarWorld): void => {
+  for (const pad of navigator.getGamepads()) {
+    if (pad && pad.axes.length >= 2) {
+      world.set_move_axis(pad.axes[0], pad.axes[1]);
+      return;
+    }
+  }
+  world.set_move_axis(0, 0);
+};
+
+// Ticks a World on every animation frame
+const startAnimationTick = (world: AstarWorld): void => {
+  const loop = (): void => {
+    pollGamepad(world);
+    world.tick(performance.now());
+    requestAnimationFrame(loop);
+  };
+  requestAnimationFrame(loop);
+};
+
+// Debug mode ticks every ms milliseconds, slow enough to watch the search
+const startIntervalTick = (world: AstarWorld, ms: number): void => {
+  let lastTick = 0;
+  const loop = (now: number): void => {
+    if (now - lastTick >= ms) {
+      lastTick = now;
+      pollGamepad(world);
+      world.tick(performance.now());
+    }
+    requestAnimationFrame(loop);
+  };
+  requestAnimationFrame(loop);
+};
 
+const getWasmImports = () => {
   return {
     js_random(): number {
       return Math.random();
     },
 
-    js_random_range(min: number, max: number): number {
-      return Math.floor(Math.random() * (max + 1 - min)) + min;
-    },
-
     js_log(): void {
       // Logging disabled per code requirements
     },
 
-    js_request_tick(): void {
-      if (isIntervalTick) return;
-      requestAnimationFrame(() => {
-        if (WASM_ASTAR.wasmModule) {
-          WASM_ASTAR.wasmModule.tick(performance.now());
-        }
-      });
-    },
-
-    js_start_interval_tick(ms: number): void {
-      isIntervalTick = true;
-      requestAnimationFrame(() => {
-        if (WASM_ASTAR.wasmModule) {
-          WASM_ASTAR.wasmModule.tick(performance.now());
-        }
-      });
-      const scheduleNext = (): void => {
-        if (WASM_ASTAR.wasmModule && isIntervalTick) {
-          const startTime = performance.now();
-          requestAnimationFrame(() => {
-            if (WASM_ASTAR.wasmModule && isIntervalTick) {
-              const elapsed = performance.now() - startTime;
-              if (elapsed >= ms) {
-                WASM_ASTAR.wasmModule.tick(performance.now());
-                scheduleNext();
-              } else {
-                scheduleNext();
-              }
-            }
-          });
-        }
-      };
-      scheduleNext();
+    js_now(): number {
+      return performance.now();
     },
 
         js_create_layer(id: string, key: number): void {
@@ -345,6 +368,18 @@ const getWasmImports = () => {
           ctx.closePath();
           ctx.fill();
         },
+        drawLine(points: Float32Array, width: number, ch: number, cs: number, cl: number, ca: number): void {
+          ctx.strokeStyle = `hsla(${ch}, ${cs}%, ${cl}%, ${ca})`;
+          ctx.lineWidth = width;
+          ctx.lineJoin = 'round';
+          ctx.lineCap = 'round';
+          ctx.beginPath();
+          ctx.moveTo(points[0], points[1]);
+          for (let i = 2; i + 1 < points.length; i += 2) {
+            ctx.lineTo(points[i], points[i + 1]);
+          }
+          ctx.stroke();
+        },
         drawText(text: string, fontSize: number, px: number, py: number): void {
           ctx.fillStyle = '#fff';
           ctx.font = `${fontSize}px Monaco, Consolas, Courier, monospace`;
@@ -370,42 +405,69 @@ const getWasmImports = () => {
       }
     },
 
-    js_clear_screen(layerId: number): void {
-      const layer = WASM_ASTAR.layers.get(layerId);
-      if (layer) {
-        layer.clearScreen();
+    js_update(): void {
+      // For minimal necessary client updates
+    },
+
+    // Commands queued by CanvasRenderer (wasm-astar/src/render/canvas.rs), 9 floats
+    // each: op (0 clear, 1 tile, 2 circle), layer, x, y, size or radius, h, s, l, a.
+    // The array is a view into wasm memory, only valid during this call.
+    js_draw_batch(commands: Float32Array): void {
+      for (let i = 0; i + 9 <= commands.length; i += 9) {
+        const layer = WASM_ASTAR.layers.get(commands[i + 1]);
+        if (!layer) {
+          continue;
+        }
+        const op = commands[i];
+        if (op === 0) {
+          layer.clearScreen();
+        } else if (op === 1) {
+          const size = commands[i + 4];
+          layer.drawRect(commands[i + 2], commands[i + 3], size, size, commands[i + 5], commands[i + 6], commands[i + 7], commands[i + 8]);
+        } else if (op === 2) {
+          layer.drawCircle(commands[i + 2], commands[i + 3], commands[i + 4], commands[i + 5], commands[i + 6], commands[i + 7], commands[i + 8]);
+        }
       }
     },
 
-    js_update(): void {
-      // For minimal necessary client updates
+    // Polyline as x, y pairs, a view into wasm memory like js_draw_batch
+    js_draw_line(layerId: number, points: Float32Array, width: number, h: number, s: number, l: number, a: number): void {
+      const layer = WASM_ASTAR.layers.get(layerId);
+      if (layer && points.length >= 4) {
+        layer.drawLine(points, width, h, s, l, a);
+      }
     },
 
-    js_draw_tile(layerId: number, px: number, py: number, size: number, ch: number, cs: number, cl: number, ca: number): void {
+    js_draw_frame_stats(layerId: number, fps: number, minMs: number, avgMs: number, maxMs: number, p99Ms: number): void {
       const layer = WASM_ASTAR.layers.get(layerId);
       if (layer) {
-        layer.drawRect(px, py, size, size, ch, cs, cl, ca);
+        layer.drawText(`fps: ${Math.round(fps)}`, 24, 5, 30);
+        layer.drawText(`avg ${avgMs.toFixed(1)} p99 ${p99Ms.toFixed(1)}`, 24, 5, 60);
+        layer.drawText(`min ${minMs.toFixed(1)} max ${maxMs.toFixed(1)}`, 24, 5, 90);
       }
     },
 
-    js_draw_circle(layerId: number, px: number, py: number, r: number, ch: number, cs: number, cl: number, ca: number): void {
+    js_path_count(layerId: number, count: number): void {
       const layer = WASM_ASTAR.layers.get(layerId);
       if (layer) {
-        layer.drawCircle(px, py, r, ch, cs, cl, ca);
+        layer.drawText(`path: ${count}`, 35, 5, 95);
       }
     },
 
-    js_draw_fps(layerId: number, fps: number): void {
+    js_search_stats(layerId: number, nodesExpanded: number, elapsedUs: number): void {
       const layer = WASM_ASTAR.layers.get(layerId);
       if (layer) {
-        layer.drawText(`fps: ${Math.round(fps)}`, 35, 5, 45);
+        layer.drawText(`nodes: ${nodesExpanded} (${Math.round(elapsedUs)}µs)`, 35, 5, 145);
       }
     },
 
-    js_path_count(layerId: number, count: number): void {
+    js_draw_node_scores(layerId: number, px: number, py: number, size: number, g: number, h: number, f: number): void {
       const layer = WASM_ASTAR.layers.get(layerId);
       if (layer) {
-        layer.drawText(`path: ${count}`, 35, 5, 95);
+        const fontSize = Math.floor(size / 4);
+        layer.drawText(`g ${g}`, fontSize, px + 2, py + fontSize);
+        layer.drawText(`h ${h}`, fontSize, px + 2, py + fontSize * 2);
+        layer.drawText(`f ${f}`, fontSize, px + 2, py + fontSize * 3);
       }
     },
   };
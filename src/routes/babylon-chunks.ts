@@ -62,6 +62,7 @@ interface NearestNeighborResult {
  * @param currentTileHex - Hex coordinate of current tile
  * @param rings - Number of rings per chunk (needed for chunk spacing calculation)
  * @param wasmModule - WASM module instance
+ * @param logFn - Optional logging function
  * @returns Nearest neighbor chunk info, or null if no neighbor found
  */
 function findNearestNeighborChunk(
@@ -69,7 +70,8 @@ function findNearestNeighborChunk(
   worldMap: WorldMap,
   currentTileHex: HexUtils.HexCoord,
   rings: number,
-  wasmModule: { find_nearest_neighbor_chunk: (current_chunk_q: number, current_chunk_r: number, current_tile_q: number, current_tile_r: number, rings: number, existing_chunks_json: string) => string }
+  wasmModule: { find_nearest_neighbor_chunk: (current_chunk_q: number, current_chunk_r: number, current_tile_q: number, current_tile_r: number, rings: number, existing_chunks_json: string) => string },
+  logFn?: (message: string, type?: 'info' | 'success' | 'warning' | 'error') => void
 ): NearestNeighborResult | null {
   // Build existing chunks JSON - only include chunks that are fully in the map
   // (Placeholder chunks added by the queue are already in the map, so they'll be included)
@@ -82,15 +84,24 @@ function findNearestNeighborChunk(
   
   const existingChunksJson = JSON.stringify(existingChunks);
   
-  // Call WASM function
-  const resultJson = wasmModule.find_nearest_neighbor_chunk(
-    currentChunkHex.q,
-    currentChunkHex.r,
-    currentTileHex.q,
-    currentTileHex.r,
-    rings,
-    existingChunksJson
-  );
+  // Call WASM function, it throws if the chunk list is malformed
+  let resultJson: string;
+  try {
+    resultJson = wasmModule.find_nearest_neighbor_chunk(
+      currentChunkHex.q,
+      currentChunkHex.r,
+      currentTileHex.q,
+      currentTileHex.r,
+      rings,
+      existingChunksJson
+    );
+  } catch (error) {
+    const errorMsg = error instanceof Error ? error.message : 'Unknown error';
+    if (logFn) {
+      logFn(`Error calling find_nearest_neighbor_chunk: ${errorMsg}`, 'error');
+    }
+    return null;
+  }
   
   if (resultJson === 'null' || resultJson === '') {
     return null;
@@ -212,13 +223,22 @@ function disableDistantChunks(
   }
   const allChunksJson = JSON.stringify(chunksJson);
   
-  // Call WASM function
-  const resultJson = wasmModule.disable_distant_chunks(
-    currentChunkHex.q,
-    currentChunkHex.r,
-    allChunksJson,
-    maxDistance
-  );
+  // Call WASM function, it throws if the chunk list is malformed
+  let resultJson: string;
+  try {
+    resultJson = wasmModule.disable_distant_chunks(
+      currentChunkHex.q,
+      currentChunkHex.r,
+      allChunksJson,
+      maxDistance
+    );
+  } catch (error) {
+    const errorMsg = error instanceof Error ? error.message : 'Unknown error';
+    if (logFn) {
+      logFn(`Error calling disable_distant_chunks: ${errorMsg}`, 'error');
+    }
+    return false;
+  }
   
   let disabledCount = 0;
   let reEnabledCount = 0;
@@ -341,7 +361,8 @@ function ensureNearestNeighborChunkIsVisible(
     worldMap,
     currentTileHex,
     rings,
-    wasmModule
+    wasmModule,
+    logFn
   );
   
   if (!nearestNeighbor || nearestNeighbor.distance > thresholdWorld) {
@@ -825,7 +846,8 @@ export const init = async (): Promise<void> => {
             worldMap,
             currentTileHex,
             canvasManager.getCurrentRings(),
-            wasmModule
+            wasmModule,
+            addLogEntry ?? undefined
           );
           
           // Log nearest neighbor stats when tile changes
@@ -1135,7 +1157,8 @@ export const init = async (): Promise<void> => {
                 newWorldMap,
                 currentTileHex,
                 newCanvasManager.getCurrentRings(),
-                wasmModuleForReinit
+                wasmModuleForReinit,
+                addLogEntry ?? undefined
               );
               
               // Log nearest neighbor stats when tile changes
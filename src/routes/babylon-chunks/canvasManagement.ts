@@ -857,6 +857,10 @@ export class CanvasManager {
           }
           
           // Generate layout - this recomputes tile types based on pre-constraints
+          // The region covers every chunk tile so cells without a pre-constraint are filled too
+          if (!wasmModule.set_layout_region(0, 0, requiredRings)) {
+            this.log(`Layout region of ${requiredRings} rings is out of range, keeping the previous region`, 'warning');
+          }
           wasmModule.generate_layout();
           
           if (this.logFn) {
@@ -920,6 +924,9 @@ export class CanvasManager {
     // Note: generate_layout() is now called conditionally above for chunk-based rendering
     // For non-chunk rendering, we still need to generate layout
     if (!this.worldMap) {
+      if (!wasmModule.set_layout_region(0, 0, this.currentRings)) {
+        this.log(`Layout region of ${this.currentRings} rings is out of range, keeping the previous region`, 'warning');
+      }
       wasmModule.generate_layout();
     }
     
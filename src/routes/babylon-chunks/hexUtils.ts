@@ -472,20 +472,25 @@ export function hexAStar(
     }
     
     const validTerrainJson = JSON.stringify(validTerrain);
-    const result = wasmModule.hex_astar(
-      start.q,
-      start.r,
-      goal.q,
-      goal.r,
-      validTerrainJson
-    );
-    
-    if (result === 'null' || result === null) {
-      return null;
+    // Throws on malformed input, the TypeScript implementation below takes over then
+    try {
+      const result = wasmModule.hex_astar(
+        start.q,
+        start.r,
+        goal.q,
+        goal.r,
+        validTerrainJson
+      );
+      
+      if (result === 'null' || result === null) {
+        return null;
+      }
+      
+      // Parse and validate result using utility function
+      return parseHexCoordArray(result);
+    } catch {
+      // Fall through to the TypeScript implementation
     }
-    
-    // Parse and validate result using utility function
-    return parseHexCoordArray(result);
   }
   
   // Fallback to TypeScript implementation
@@ -647,20 +652,25 @@ export function buildPathBetweenRoads(
     }
     
     const validTerrainJson = JSON.stringify(validTerrain);
-    const result = wasmModule.build_path_between_roads(
-      start.q,
-      start.r,
-      end.q,
-      end.r,
-      validTerrainJson
-    );
-    
-    if (result === 'null' || result === null) {
-      return null;
+    // Throws on malformed input, the TypeScript implementation below takes over then
+    try {
+      const result = wasmModule.build_path_between_roads(
+        start.q,
+        start.r,
+        end.q,
+        end.r,
+        validTerrainJson
+      );
+      
+      if (result === 'null' || result === null) {
+        return null;
+      }
+      
+      // Parse and validate result using utility function
+      return parseHexCoordArray(result);
+    } catch {
+      // Fall through to the TypeScript implementation
     }
-    
-    // Parse and validate result using utility function
-    return parseHexCoordArray(result);
   }
   
   // Fallback to TypeScript implementation
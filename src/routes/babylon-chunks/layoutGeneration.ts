@@ -558,12 +558,21 @@ export async function constraintsToPreConstraints(
   }
   const occupiedJson = JSON.stringify(occupiedArray);
   
-  const result = wasmModule.generate_road_network_growing_tree(
-    seedsJson,
-    validTerrainJson,
-    occupiedJson,
-    targetRoadCount
-  );
+  // Throws on malformed input, the TypeScript growth below takes over then
+  let result = '[]';
+  try {
+    result = wasmModule.generate_road_network_growing_tree(
+      seedsJson,
+      validTerrainJson,
+      occupiedJson,
+      targetRoadCount
+    );
+  } catch (error) {
+    const errorMsg = error instanceof Error ? error.message : 'Unknown error';
+    if (logFn) {
+      logFn(`Error calling generate_road_network_growing_tree: ${errorMsg}`, 'error');
+    }
+  }
   
   // Yield control after WASM road generation
   await new Promise<void>((resolve) => {
@@ -659,7 +668,15 @@ export async function constraintsToPreConstraints(
   }
 
   const roadsJson = JSON.stringify(roadConstraints.map((rc) => ({ q: rc.q, r: rc.r })));
-  const roadsConnected = wasmModule.validate_road_connectivity(roadsJson);
+  let roadsConnected = false;
+  try {
+    roadsConnected = wasmModule.validate_road_connectivity(roadsJson);
+  } catch (error) {
+    const errorMsg = error instanceof Error ? error.message : 'Unknown error';
+    if (logFn) {
+      logFn(`Error calling validate_road_connectivity: ${errorMsg}`, 'error');
+    }
+  }
   if (!roadsConnected && logFn) {
     logFn('Road connectivity validation failed', 'error');
   }
@@ -849,6 +866,10 @@ export async function generateLayoutFromText(
       modelStatusEl.textContent = 'Generating layout...';
     }
 
+    const rings = canvasManager.getCurrentRings();
+    if (!wasmModule.set_layout_region(0, 0, rings) && logFn) {
+      logFn(`Layout region of ${rings} rings is out of range, keeping the previous region`, 'warning');
+    }
     wasmModule.generate_layout();
 
     if (modelStatusEl) {
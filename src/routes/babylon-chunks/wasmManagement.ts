@@ -153,6 +153,7 @@ export class WasmManager {
     const getAdjacentValidTerrainValue = this.wasmModuleRecord ? getProperty(this.wasmModuleRecord, 'get_adjacent_valid_terrain') : getProperty(exports, 'get_adjacent_valid_terrain');
     const generateBuildingPlacementValue = this.wasmModuleRecord ? getProperty(this.wasmModuleRecord, 'generate_building_placement') : getProperty(exports, 'generate_building_placement');
     const batchHexToWorldValue = this.wasmModuleRecord ? getProperty(this.wasmModuleRecord, 'batch_hex_to_world') : getProperty(exports, 'batch_hex_to_world');
+    const setLayoutRegionValue = this.wasmModuleRecord ? getProperty(this.wasmModuleRecord, 'set_layout_region') : getProperty(exports, 'set_layout_region');
     
     if (typeof generateLayoutValue !== 'function') {
       missingExports.push('generate_layout (function)');
@@ -220,6 +221,9 @@ export class WasmManager {
     if (typeof batchHexToWorldValue !== 'function') {
       missingExports.push('batch_hex_to_world (function)');
     }
+    if (typeof setLayoutRegionValue !== 'function') {
+      missingExports.push('set_layout_region (function)');
+    }
     
     if (missingExports.length > 0) {
       throw new Error(`WASM module missing required exports: ${missingExports.join(', ')}. Available exports from init result: ${exportKeys.join(', ')}`);
@@ -254,6 +258,7 @@ export class WasmManager {
     const getAdjacentValidTerrainFunc = getAdjacentValidTerrainValue;
     const generateBuildingPlacementFunc = generateBuildingPlacementValue;
     const batchHexToWorldFunc = batchHexToWorldValue;
+    const setLayoutRegionFunc = setLayoutRegionValue;
     
     if (
       typeof generateLayoutFunc !== 'function' ||
@@ -278,7 +283,8 @@ export class WasmManager {
       typeof countAdjacentRoadsFunc !== 'function' ||
       typeof getAdjacentValidTerrainFunc !== 'function' ||
       typeof generateBuildingPlacementFunc !== 'function' ||
-      typeof batchHexToWorldFunc !== 'function'
+      typeof batchHexToWorldFunc !== 'function' ||
+      typeof setLayoutRegionFunc !== 'function'
     ) {
       return null;
     }
@@ -458,6 +464,11 @@ export class WasmManager {
         const result = batchHexToWorldFunc(hex_coords_json, hex_size);
         return typeof result === 'string' ? result : '[]';
       },
+      set_layout_region: (center_q: number, center_r: number, rings: number): boolean => {
+        // eslint-disable-next-line @typescript-eslint/no-unsafe-call, @typescript-eslint/no-unsafe-assignment
+        const result = setLayoutRegionFunc(center_q, center_r, rings);
+        return typeof result === 'boolean' ? result : false;
+      },
     };
   }
 
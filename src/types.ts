@@ -1,13 +1,99 @@
 // Type definitions for WASM modules
 
 // A* Pathfinding module types
-export interface WasmModuleAstar {
-  memory: WebAssembly.Memory;
-  wasm_init(debug: number, renderIntervalMs: number, windowWidth: number, windowHeight: number): void;
+// One simulation, see World in wasm-astar/src/lib.rs
+export interface AstarWorld {
+  init(debug: number, windowWidth: number, windowHeight: number): void;
   tick(elapsedTime: number): void;
   key_down(keyCode: number): void;
   key_up(keyCode: number): void;
   mouse_move(x: number, y: number): void;
+  mouse_down(x: number, y: number): void;
+  mouse_up(): void;
+  pan_camera(dx: number, dy: number): void;
+  zoom_camera(factor: number, x: number, y: number): void;
+  reset_camera(): void;
+  // 0 A*, 1 Dijkstra, 2 greedy best-first, 3 breadth-first, 4 jump point search,
+  // 5 bidirectional A*, 6 D* Lite, 7 HPA*. Jump point search runs as A* on maps with
+  // mixed terrain costs.
+  set_search_algorithm(algorithmId: number): void;
+  get_search_algorithm_name(): string;
+  // What the most recent search actually ran, A* when jump point search fell back
+  get_searched_algorithm_name(): string;
+  // 0 cardinal, 1 diagonal, 2 diagonal without corner cutting
+  set_movement(movementId: number): void;
+  // 0 Manhattan, 1 octile, 2 Chebyshev, 3 Euclidean, 4 zero
+  set_heuristic(heuristicId: number): void;
+  // Regenerates the map from the seed
+  set_map_seed(seed: number): void;
+  get_map_seed(): number;
+  // 0 noise, 1 recursive backtracker, 2 Prim, 3 cellular caves, 4 rooms and corridors
+  set_map_generator(generatorId: number): void;
+  get_map_generator_name(): string;
+  // Text format in wasm-astar/src/world/map.rs, throws on an invalid map and keeps the old one
+  import_map(text: string): void;
+  export_map(): string;
+  set_agent_count(count: number): void;
+  // 1 on, anything else off
+  set_follow_path(enabled: number): void;
+  set_player_speed(tilesPerSecond: number): void;
+  // stepsPerTick 0 only advances on step_search
+  set_search_stepping(enabled: number, stepsPerTick: number): void;
+  step_search(steps: number): void;
+  // From the most recent search
+  get_search_nodes_expanded(): number;
+  get_search_time_us(): number;
+  // Running totals since startup
+  get_full_replans(): number;
+  get_incremental_replans(): number;
+  get_skipped_replans(): number;
+  // 0 by 0 fills the canvas again
+  set_map_size(numXTiles: number, numYTiles: number): void;
+  // Throws on an unknown action name
+  bind_key(actionName: string, keyCode: number): void;
+  unbind_key(keyCode: number): void;
+  reset_key_bindings(): void;
+  set_move_axis(x: number, y: number): void;
+  is_action_pressed(actionName: string): boolean;
+  is_action_released(actionName: string): boolean;
+  is_action_held(actionName: string): boolean;
+  get_frame_stats(): AstarFrameStats;
+  get_cluster_stats(): AstarClusterStats;
+  // 0 off, 1 string pulling, 2 string pulling then a Catmull-Rom curve
+  set_path_smoothing(smoothingId: number): void;
+  // x, y pairs in world pixels, start first
+  get_path_waypoints(): Float64Array;
+  get_smoothed_path(): Float64Array;
+  free(): void;
+}
+
+// See ClusterStats in wasm-astar/src/world/search/hpa.rs
+export interface AstarClusterStats {
+  clusters: number;
+  entrances: number;
+  rebuilt_clusters: number;
+  // Most a HPA* path costs over the cheapest per cluster border the cheapest crosses
+  max_detour_per_border: number;
+  // Cost of the most recent HPA* path, and max_detour_per_border times the borders it crosses
+  path_cost: number;
+  max_path_detour: number;
+  free(): void;
+}
+
+// Frame times in ms over the last 120 frames, see FrameStats in wasm-astar/src/engine/timing.rs
+export interface AstarFrameStats {
+  frames: number;
+  min_ms: number;
+  avg_ms: number;
+  max_ms: number;
+  p99_ms: number;
+  fps: number;
+  free(): void;
+}
+
+export interface WasmModuleAstar {
+  memory: WebAssembly.Memory;
+  World: new (layerBase: number) => AstarWorld;
 }
 
 export interface Layer {
@@ -17,11 +103,13 @@ export interface Layer {
   clearScreen(): void;
   drawRect(px: number, py: number, sx: number, sy: number, ch: number, cs: number, cl: number, ca: number): void;
   drawCircle(px: number, py: number, r: number, ch: number, cs: number, cl: number, ca: number): void;
+  drawLine(points: Float32Array, width: number, ch: number, cs: number, cl: number, ca: number): void;
   drawText(text: string, fontSize: number, px: number, py: number): void;
 }
 
 export interface WasmAstar {
   wasmModule: WasmModuleAstar | null;
+  world: AstarWorld | null;
   wasmModulePath: string;
   debug: boolean;
   renderIntervalMs: number;
@@ -314,6 +402,7 @@ export interface WasmModuleBabylonWfc {
 
 /**
  * WASM module interface for babylon-chunks (extends WasmModuleBabylonWfc with version info)
+ * Exports taking a *_json argument throw an Error naming the argument when it is malformed
  */
 export interface WasmModuleBabylonChunks extends WasmModuleBabylonWfc {
   get_wasm_version(): string;
@@ -355,6 +444,127 @@ export interface WasmModuleBabylonChunks extends WasmModuleBabylonWfc {
     target_count: number
   ): string;
   batch_hex_to_world(hex_coords_json: string, hex_size: number): string;
+  /**
+   * Cheapest path over the generated layout, tile_costs_json gives the cost of entering
+   * each tile type ({"grass":2,"road":1,"forest":5,"water":null}); a missing or null type is impassable
+   */
+  hex_astar_weighted?(
+    start_q: number,
+    start_r: number,
+    goal_q: number,
+    goal_r: number,
+    tile_costs_json: string
+  ): string;
+  /**
+   * Hexagon generate_layout fills around the pre-constraints with wave function collapse;
+   * returns false when rings is outside 0-100
+   */
+  set_layout_region(center_q: number, center_r: number, rings: number): boolean;
+  clear_layout_region?(): void;
+  /** Makes the sequence of generated layouts reproducible */
+  set_layout_seed?(seed: number): void;
+}
+
+/**
+ * Packed typed-array variants of the babylon-chunks coordinate exports
+ * Hexes are Int32Array q,r pairs; undefined stands for the JSON exports' "null"
+ * They throw an Error naming the argument when a buffer isn't whole entries
+ */
+export interface WasmModuleBabylonChunksPacked {
+  hex_astar_packed(
+    start_q: number,
+    start_r: number,
+    goal_q: number,
+    goal_r: number,
+    valid_terrain: Int32Array
+  ): Int32Array | undefined;
+  build_path_between_roads_packed(
+    start_q: number,
+    start_r: number,
+    end_q: number,
+    end_r: number,
+    valid_terrain: Int32Array
+  ): Int32Array | undefined;
+  validate_road_connectivity_packed(roads: Int32Array): boolean;
+  /** tile_costs holds one cost per tile type id, -1 where that type is impassable; throws on 0 or other negatives */
+  hex_astar_weighted_packed(
+    start_q: number,
+    start_r: number,
+    goal_q: number,
+    goal_r: number,
+    tile_costs: Int32Array
+  ): Int32Array | undefined;
+  generate_road_network_growing_tree_packed(
+    seeds: Int32Array,
+    valid_terrain: Int32Array,
+    occupied: Int32Array,
+    target_count: number
+  ): Int32Array;
+  calculate_chunk_neighbors_packed(center_q: number, center_r: number, rings: number): Int32Array;
+  /** Returns [q, r, distance, isInstantiated (0 or 1)] */
+  find_nearest_neighbor_chunk_packed(
+    current_chunk_q: number,
+    current_chunk_r: number,
+    current_tile_q: number,
+    current_tile_r: number,
+    rings: number,
+    existing_chunks: Int32Array
+  ): Int32Array | undefined;
+  /** all_chunks and the result are q,r,enabled triples, the result holds the new state of chunks to change */
+  disable_distant_chunks_packed(
+    current_chunk_q: number,
+    current_chunk_r: number,
+    all_chunks: Int32Array,
+    max_distance: number
+  ): Int32Array;
+  /** Returns q,r,tileType triples */
+  batch_get_tile_types_packed(hex_coords: Int32Array): Int32Array;
+  calculate_chunk_for_tile_packed(
+    tile_q: number,
+    tile_r: number,
+    rings: number,
+    chunk_positions: Int32Array
+  ): Int32Array | undefined;
+  shuffle_array_packed(array: Int32Array): Int32Array;
+  count_adjacent_roads_packed(hex_q: number, hex_r: number, road_network: Int32Array): number;
+  get_adjacent_valid_terrain_packed(
+    road_network: Int32Array,
+    valid_terrain: Int32Array,
+    occupied: Int32Array
+  ): Int32Array;
+  generate_building_placement_packed(
+    valid_terrain: Int32Array,
+    road_network: Int32Array,
+    occupied: Int32Array,
+    min_adjacent_roads: number,
+    target_count: number
+  ): Int32Array;
+  /** Returns q,r,x,z tuples */
+  batch_hex_to_world_packed(hex_coords: Int32Array, hex_size: number): Float32Array;
+}
+
+/**
+ * Persistent terrain handle exported by babylon-chunks as the TerrainGraph class
+ * Built once (TerrainGraph.from_packed / from_json), edited with set_walkable / set_road,
+ * then queried many times. Call free() when done with it.
+ */
+export interface TerrainGraph {
+  walkable_count(): number;
+  road_count(): number;
+  is_walkable(q: number, r: number): boolean;
+  is_road(q: number, r: number): boolean;
+  set_walkable(q: number, r: number, walkable: boolean): boolean;
+  set_walkable_packed(hexes: Int32Array, walkable: boolean): number;
+  set_road(q: number, r: number, road: boolean): boolean;
+  set_roads_packed(hexes: Int32Array, road: boolean): number;
+  find_path(start_q: number, start_r: number, goal_q: number, goal_r: number): Int32Array | undefined;
+  find_path_json(start_q: number, start_r: number, goal_q: number, goal_r: number): string;
+  is_connected(a_q: number, a_r: number, b_q: number, b_r: number): boolean;
+  component_count(): number;
+  /** Returns [q, r, distance] */
+  nearest_road(q: number, r: number): Int32Array | undefined;
+  roads_connected(): boolean;
+  free(): void;
 }
 
 /**
@@ -0,0 +1,278 @@
+use std::ops::Range;
+
+use crate::engine::{Camera, Color, FrameStats};
+use crate::render::Renderer;
+use crate::world::{NodeState, Tile, WorldState};
+
+// Offsets from World::layer_base into WASM_ASTAR.layers on the client side.
+// The client keeps every layer in one map, so Worlds sharing a page need
+// layer bases at least 3 apart.
+#[derive(Clone, Copy)]
+pub enum Layer {
+    TileBg = 0,
+    Main = 1,
+    Fps = 2,
+}
+
+impl Layer {
+    pub fn id(self, layer_base: i32) -> i32 {
+        layer_base + self as i32
+    }
+}
+
+// Draws a WorldState through whichever renderer the World was given. The
+// TileBg and Main layers are drawn through the camera and anything outside
+// the canvas is skipped, the Fps layer and text overlays stay put.
+pub struct Painter<'a> {
+    pub world: &'a WorldState,
+    pub renderer: &'a mut dyn Renderer,
+    pub layer_base: i32,
+    pub camera: Camera,
+}
+
+impl Painter<'_> {
+    pub fn draw_frame(&mut self, elapsed_time: f64) {
+        let world = self.world;
+        if world.stepping {
+            self.draw_search_nodes();
+        }
+        self.draw_path(world.end_id);
+        self.draw_smoothed_path();
+        self.draw_agents(elapsed_time);
+        self.draw_tile_with_color(
+            Layer::Main,
+            &world.tiles[world.start_id as usize],
+            &Color::new(32, 100, 60, 0.3),
+        );
+        self.draw_tile_with_color(
+            Layer::Main,
+            &world.tiles[world.end_id as usize],
+            &Color::new(112, 89, 61, 1.0),
+        );
+        let path_count = get_path_count(world);
+        let main = Layer::Main.id(self.layer_base);
+        self.renderer.draw_path_count(main, path_count);
+        self.renderer.draw_search_stats(
+            main,
+            world.search_stats.nodes_expanded,
+            world.search_stats.elapsed_us,
+        );
+        if world.follow_path {
+            self.draw_player();
+        }
+    }
+
+    pub fn draw_background(&mut self) {
+        let world = self.world;
+        for id in self.visible_tile_ids() {
+            self.draw_tile(Layer::TileBg, &world.tiles[id]);
+        }
+    }
+
+    pub fn draw_frame_stats(&mut self, stats: &FrameStats) {
+        let layer_id = Layer::Fps.id(self.layer_base);
+        self.renderer.clear(layer_id);
+        self.renderer.draw_frame_stats(layer_id, stats);
+    }
+
+    // Open and closed sets from the last (partial) search, with G/H/F in debug
+    // mode when the tiles are big enough to fit the text.
+    fn draw_search_nodes(&mut self) {
+        let world = self.world;
+        let nodes = world.search_nodes();
+        let open_color = Color::new(120, 70, 50, 0.4);
+        let closed_color = Color::new(200, 70, 50, 0.4);
+        let show_scores = world.debug && world.tile_size as f64 * self.camera.zoom >= 50_f64;
+        for id in self.visible_tile_ids() {
+            let t = &world.tiles[id];
+            let color = match nodes.state[id] {
+                NodeState::Unvisited => continue,
+                NodeState::Open => &open_color,
+                NodeState::Closed => &closed_color,
+            };
+            self.draw_tile_with_color(Layer::Main, t, color);
+            if show_scores {
+                let (px, py) = self.camera.to_canvas(t.transform.pos_x, t.transform.pos_y);
+                self.renderer.draw_node_scores(
+                    Layer::Main.id(self.layer_base),
+                    px,
+                    py,
+                    t.transform.scale_x * self.camera.zoom,
+                    [nodes.g[id], nodes.h[id], nodes.f[id]],
+                );
+            }
+        }
+    }
+
+    // Each agent in its own hue: its planned steps as dots, its goal as a faded
+    // circle and the agent itself slid between its last tile and the current one.
+    fn draw_agents(&mut self, elapsed_time: f64) {
+        let world = self.world;
+        let half_tile = (world.tile_size / 2) as f64;
+        let progress = world.agents.step_progress(elapsed_time);
+        for agent in world.agents.agents.iter() {
+            let hue = agent.hue;
+            for id in agent.path.iter() {
+                let t = &world.tiles[*id].transform;
+                self.draw_circle(
+                    t.pos_x + half_tile,
+                    t.pos_y + half_tile,
+                    t.scale_x / 10_f64,
+                    &Color::new(hue, 80, 60, 0.6),
+                );
+            }
+            let goal = &world.tiles[agent.goal_id].transform;
+            self.draw_circle(
+                goal.pos_x + half_tile,
+                goal.pos_y + half_tile,
+                goal.scale_x / 3_f64,
+                &Color::new(hue, 80, 60, 0.3),
+            );
+            let from = &world.tiles[agent.prev_tile_id].transform;
+            let to = &world.tiles[agent.tile_id].transform;
+            self.draw_circle(
+                from.pos_x + (to.pos_x - from.pos_x) * progress + half_tile,
+                from.pos_y + (to.pos_y - from.pos_y) * progress + half_tile,
+                to.scale_x / 3_f64,
+                &Color::new(hue, 80, 60, 1_f32),
+            );
+        }
+    }
+
+    fn draw_player(&mut self) {
+        let world = self.world;
+        let half_tile = (world.tile_size / 2) as f64;
+        self.draw_circle(
+            world.player.pos_x + half_tile,
+            world.player.pos_y + half_tile,
+            world.tile_size as f64 / 3_f64,
+            &Color::new(32, 100, 45, 1_f32),
+        );
+    }
+
+    // Follows the parent_id chain back from end_id
+    fn draw_path(&mut self, end_id: i32) {
+        let world = self.world;
+        let half_tile = (world.tile_size / 2) as f64;
+        let color = Color::new(280, 100, 73, 1_f32);
+        let mut id = end_id;
+        while id >= 0 {
+            let t = &world.tiles[id as usize];
+            self.draw_circle(
+                t.transform.pos_x + half_tile,
+                t.transform.pos_y + half_tile,
+                t.transform.scale_x / 5_f64,
+                &color,
+            );
+            id = t.parent_id;
+        }
+    }
+
+    // String pulled waypoints as a thin line, the Catmull-Rom curve through
+    // them as a thicker one
+    fn draw_smoothed_path(&mut self) {
+        let world = self.world;
+        let size = world.tile_size as f64;
+        self.draw_line(&world.path_waypoints, size / 12_f64, &Color::new(280, 100, 85, 1_f32));
+        self.draw_line(&world.smoothed_path, size / 6_f64, &Color::new(190, 90, 60, 1_f32));
+    }
+
+    // Points and width in world pixels
+    fn draw_line(&mut self, points: &[(f64, f64)], width: f64, color: &Color) {
+        if points.len() < 2 {
+            return;
+        }
+        let camera = self.camera;
+        let points: Vec<(f64, f64)> = points.iter().map(|(x, y)| camera.to_canvas(*x, *y)).collect();
+        let width = width * camera.zoom;
+        let (mut left, mut top) = (f64::MAX, f64::MAX);
+        let (mut right, mut bottom) = (f64::MIN, f64::MIN);
+        for (px, py) in points.iter() {
+            left = left.min(*px);
+            top = top.min(*py);
+            right = right.max(*px);
+            bottom = bottom.max(*py);
+        }
+        let half_width = width / 2_f64;
+        if right + half_width <= 0_f64
+            || bottom + half_width <= 0_f64
+            || left - half_width >= self.world.width as f64
+            || top - half_width >= self.world.height as f64
+        {
+            return;
+        }
+        let layer_id = Layer::Main.id(self.layer_base);
+        self.renderer.draw_line(layer_id, &points, width, color);
+    }
+
+    // Center and radius in world pixels
+    fn draw_circle(&mut self, x: f64, y: f64, radius: f64, color: &Color) {
+        let (px, py) = self.camera.to_canvas(x, y);
+        let radius = radius * self.camera.zoom;
+        if !self.is_on_canvas(px - radius, py - radius, radius * 2_f64) {
+            return;
+        }
+        let layer_id = Layer::Main.id(self.layer_base);
+        self.renderer.draw_circle(layer_id, px, py, radius, color);
+    }
+
+    pub fn draw_tile(&mut self, layer: Layer, t: &Tile) {
+        self.draw_tile_with_color(layer, t, &t.color);
+    }
+
+    // Snapped to whole canvas pixels, so neighboring tiles leave no seams
+    // between them at fractional zoom levels.
+    fn draw_tile_with_color(&mut self, layer: Layer, t: &Tile, c: &Color) {
+        let (left, top) = self.camera.to_canvas(t.transform.pos_x, t.transform.pos_y);
+        let size = t.transform.scale_x * self.camera.zoom;
+        if !self.is_on_canvas(left, top, size) {
+            return;
+        }
+        let px = left.floor();
+        let py = top.floor();
+        self.renderer.draw_tile(
+            layer.id(self.layer_base),
+            px,
+            py,
+            (left + size).floor() - px,
+            c,
+        );
+    }
+
+    // Whether a square at canvas position px/py overlaps the canvas
+    fn is_on_canvas(&self, px: f64, py: f64, size: f64) -> bool {
+        px + size > 0_f64
+            && py + size > 0_f64
+            && px < self.world.width as f64
+            && py < self.world.height as f64
+    }
+
+    // Ids of the tiles the camera can see, row by row
+    fn visible_tile_ids(&self) -> impl Iterator<Item = usize> {
+        let world = self.world;
+        let view = (world.width as f64, world.height as f64);
+        let (left, top, right, bottom) = self.camera.visible_rect(view);
+        let size = world.tile_size as f64;
+        let x_ids = tile_range(left, right, size, world.num_x_tiles);
+        let y_ids = tile_range(top, bottom, size, world.num_y_tiles);
+        let num_x_tiles = world.num_x_tiles as usize;
+        y_ids.flat_map(move |y| x_ids.clone().map(move |x| y * num_x_tiles + x))
+    }
+}
+
+// Tile x or y ids overlapping the world pixels from start to end
+fn tile_range(start: f64, end: f64, tile_size: f64, num_tiles: u32) -> Range<usize> {
+    let first = (start / tile_size).floor().max(0_f64) as usize;
+    let last = ((end / tile_size).ceil().max(0_f64) as usize).min(num_tiles as usize);
+    first.min(last)..last
+}
+
+fn get_path_count(world: &WorldState) -> i32 {
+    let mut count = 0;
+    let mut id = world.tiles[world.end_id as usize].parent_id;
+    while id >= 0 {
+        count += 1;
+        id = world.tiles[id as usize].parent_id;
+    }
+    count
+}
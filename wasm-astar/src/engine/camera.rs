@@ -0,0 +1,71 @@
+// Zoom limits as the size of a tile on the canvas. Fully zoomed out a big
+// map still only has a few hundred thousand tiles on screen to draw.
+const MIN_TILE_PX: f64 = 4_f64;
+const MAX_TILE_PX: f64 = 200_f64;
+
+// Which part of the world the TileBg and Main layers show. x/y is the world
+// pixel at the top left corner of the canvas and zoom is canvas pixels per
+// world pixel, the default camera draws the world 1:1.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Camera {
+    pub x: f64,
+    pub y: f64,
+    pub zoom: f64,
+}
+
+impl Camera {
+    pub fn new() -> Camera {
+        Camera {
+            x: 0_f64,
+            y: 0_f64,
+            zoom: 1_f64,
+        }
+    }
+
+    pub fn to_world(self, canvas_x: f64, canvas_y: f64) -> (f64, f64) {
+        (canvas_x / self.zoom + self.x, canvas_y / self.zoom + self.y)
+    }
+
+    pub fn to_canvas(self, world_x: f64, world_y: f64) -> (f64, f64) {
+        ((world_x - self.x) * self.zoom, (world_y - self.y) * self.zoom)
+    }
+
+    // Moves the world along with a drag of dx/dy canvas pixels
+    pub fn pan(&mut self, dx: f64, dy: f64) {
+        self.x -= dx / self.zoom;
+        self.y -= dy / self.zoom;
+    }
+
+    // Scales the zoom by factor, keeping the world pixel under the canvas
+    // position x/y where it is.
+    pub fn zoom_at(&mut self, factor: f64, x: f64, y: f64) {
+        let (world_x, world_y) = self.to_world(x, y);
+        self.zoom *= factor;
+        self.x = world_x - x / self.zoom;
+        self.y = world_y - y / self.zoom;
+    }
+
+    // Keeps the zoom within the tile size limits and the map on the canvas.
+    // Along an axis where the whole map fits it sits at the top/left edge.
+    pub fn clamp(&mut self, view: (f64, f64), map: (f64, f64), tile_size: f64) {
+        let min_zoom = MIN_TILE_PX / tile_size;
+        let max_zoom = (MAX_TILE_PX / tile_size).max(min_zoom);
+        self.zoom = self.zoom.clamp(min_zoom, max_zoom);
+        self.x = clamp_axis(self.x, view.0 / self.zoom, map.0);
+        self.y = clamp_axis(self.y, view.1 / self.zoom, map.1);
+    }
+
+    // World pixel rectangle on the canvas as (left, top, right, bottom)
+    pub fn visible_rect(self, view: (f64, f64)) -> (f64, f64, f64, f64) {
+        let (right, bottom) = self.to_world(view.0, view.1);
+        (self.x, self.y, right, bottom)
+    }
+}
+
+fn clamp_axis(pos: f64, view_size: f64, map_size: f64) -> f64 {
+    if view_size >= map_size {
+        0_f64
+    } else {
+        pos.clamp(0_f64, map_size - view_size)
+    }
+}
@@ -0,0 +1,170 @@
+use std::collections::{HashMap, HashSet};
+
+// What keys are bound to. Names are what bind_key takes from the client.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Action {
+    MoveUp,
+    MoveDown,
+    MoveLeft,
+    MoveRight,
+    Regenerate,
+}
+
+impl Action {
+    pub fn from_name(name: &str) -> Option<Action> {
+        match name {
+            "move_up" => Some(Action::MoveUp),
+            "move_down" => Some(Action::MoveDown),
+            "move_left" => Some(Action::MoveLeft),
+            "move_right" => Some(Action::MoveRight),
+            "regenerate" => Some(Action::Regenerate),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Action::MoveUp => "move_up",
+            Action::MoveDown => "move_down",
+            Action::MoveLeft => "move_left",
+            Action::MoveRight => "move_right",
+            Action::Regenerate => "regenerate",
+        }
+    }
+}
+
+// KeyboardEvent.keyCode values
+const DEFAULT_BINDINGS: [(u32, Action); 9] = [
+    (38, Action::MoveUp),     // ArrowUp
+    (87, Action::MoveUp),     // W
+    (40, Action::MoveDown),   // ArrowDown
+    (83, Action::MoveDown),   // S
+    (37, Action::MoveLeft),   // ArrowLeft
+    (65, Action::MoveLeft),   // A
+    (39, Action::MoveRight),  // ArrowRight
+    (68, Action::MoveRight),  // D
+    (32, Action::Regenerate), // Spacebar
+];
+
+// Stick values closer to 0 than this count as centered
+const AXIS_DEAD_ZONE: f64 = 0.15;
+
+// Key events arrive between ticks. They are collected as they come and turned
+// into per-frame edges by begin_frame, so a key tapped and released between
+// two ticks still shows up as pressed (and released) for one frame.
+pub struct Input {
+    bindings: HashMap<u32, Action>,
+    keys_down: HashSet<u32>,
+    pending_pressed: HashSet<Action>,
+    pending_released: HashSet<Action>,
+    pressed: HashSet<Action>,
+    released: HashSet<Action>,
+    // Analog movement from the client, e.g. a gamepad stick, -1 to 1
+    axis: (f64, f64),
+}
+
+impl Input {
+    pub fn new() -> Input {
+        Input {
+            bindings: DEFAULT_BINDINGS.iter().copied().collect(),
+            keys_down: HashSet::new(),
+            pending_pressed: HashSet::new(),
+            pending_released: HashSet::new(),
+            pressed: HashSet::new(),
+            released: HashSet::new(),
+            axis: (0_f64, 0_f64),
+        }
+    }
+
+    // A key has one action, binding it again replaces the old one.
+    // An action can have any number of keys.
+    pub fn bind(&mut self, key_code: u32, action: Action) {
+        self.release_key(key_code);
+        self.bindings.insert(key_code, action);
+    }
+
+    pub fn unbind(&mut self, key_code: u32) {
+        self.release_key(key_code);
+        self.bindings.remove(&key_code);
+    }
+
+    pub fn reset_bindings(&mut self) {
+        let keys: Vec<u32> = self.keys_down.iter().copied().collect();
+        for key_code in keys {
+            self.release_key(key_code);
+        }
+        self.bindings = DEFAULT_BINDINGS.iter().copied().collect();
+    }
+
+    // Repeats from a held key are ignored, as are keys for an action that
+    // another key already holds down.
+    pub fn key_down(&mut self, key_code: u32) {
+        if self.keys_down.contains(&key_code) {
+            return;
+        }
+        if let Some(action) = self.bindings.get(&key_code).copied() {
+            if !self.is_held(action) {
+                self.pending_pressed.insert(action);
+            }
+        }
+        self.keys_down.insert(key_code);
+    }
+
+    pub fn key_up(&mut self, key_code: u32) {
+        self.release_key(key_code);
+    }
+
+    pub fn set_axis(&mut self, x: f64, y: f64) {
+        let clean = |v: f64| {
+            if v.is_finite() && v.abs() >= AXIS_DEAD_ZONE {
+                v.clamp(-1_f64, 1_f64)
+            } else {
+                0_f64
+            }
+        };
+        self.axis = (clean(x), clean(y));
+    }
+
+    pub fn begin_frame(&mut self) {
+        self.pressed = std::mem::take(&mut self.pending_pressed);
+        self.released = std::mem::take(&mut self.pending_released);
+    }
+
+    // First frame the action is down
+    pub fn is_pressed(&self, action: Action) -> bool {
+        self.pressed.contains(&action)
+    }
+
+    // Frame the last key for the action went up
+    pub fn is_released(&self, action: Action) -> bool {
+        self.released.contains(&action)
+    }
+
+    pub fn is_held(&self, action: Action) -> bool {
+        self.keys_down
+            .iter()
+            .any(|key_code| self.bindings.get(key_code) == Some(&action))
+    }
+
+    // Move keys and the analog axis combined, each direction -1 to 1.
+    // Opposite keys cancel out.
+    pub fn move_axis(&self) -> (f64, f64) {
+        let key_axis = |negative: Action, positive: Action| {
+            self.is_held(positive) as i32 as f64 - self.is_held(negative) as i32 as f64
+        };
+        let x = key_axis(Action::MoveLeft, Action::MoveRight) + self.axis.0;
+        let y = key_axis(Action::MoveUp, Action::MoveDown) + self.axis.1;
+        (x.clamp(-1_f64, 1_f64), y.clamp(-1_f64, 1_f64))
+    }
+
+    fn release_key(&mut self, key_code: u32) {
+        if !self.keys_down.remove(&key_code) {
+            return;
+        }
+        if let Some(action) = self.bindings.get(&key_code) {
+            if !self.is_held(*action) {
+                self.pending_released.insert(*action);
+            }
+        }
+    }
+}
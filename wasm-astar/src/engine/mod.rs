@@ -1,21 +1,30 @@
-use std::collections::HashMap;
-
-pub enum KeyCode {
-    ArrowUp = 38,
-    ArrowDown = 40,
-    ArrowLeft = 37,
-    ArrowRight = 39,
-    Spacebar = 32,
-}
+mod camera;
+mod input;
+mod timing;
+pub use self::camera::Camera;
+pub use self::input::{Action, Input};
+pub use self::timing::FrameStats;
+use self::timing::FrameTimes;
+
+// Longest frame step in seconds, so the first frame after the tab was in
+// the background doesn't move things across the whole map
+const MAX_DELTA: f64 = 0.1;
+// Movement advances in steps of this many seconds however long the frames
+// are, so it runs at the same speed at 30fps, 60fps or 144fps
+pub const FIXED_STEP: f64 = 1_f64 / 60_f64;
 
 pub struct EngineState {
     pub last_timestamp: f64,
     pub last_fps_render_timestamp: f64,
-    pub fps: f64,
+    // Seconds since the previous update, at most MAX_DELTA
+    pub delta: f64,
+    // Time not yet used up by fixed steps, less than FIXED_STEP after take_steps
+    accumulator: f64,
+    frame_times: FrameTimes,
     pub mouse_x: i32,
     pub mouse_y: i32,
-    key_state: HashMap<u32, bool>,
-    was_down: HashMap<u32, bool>,
+    pub input: Input,
+    pub camera: Camera,
 }
 
 impl EngineState {
@@ -23,20 +32,36 @@ impl EngineState {
         EngineState {
             last_timestamp: 0_f64,
             last_fps_render_timestamp: 0_f64,
-            fps: 0_f64,
+            delta: 0_f64,
+            accumulator: 0_f64,
+            frame_times: FrameTimes::new(),
             mouse_x: 0,
             mouse_y: 0,
-            key_state: HashMap::new(),
-            was_down: HashMap::new(),
+            input: Input::new(),
+            camera: Camera::new(),
         }
     }
 
     pub fn update(&mut self, elapsed_time: f64) {
         if self.last_timestamp != 0_f64 {
-            let delta: f64 = (elapsed_time - self.last_timestamp) / 1000_f64;
-            self.fps = 1_f64 / delta;
+            let frame_ms = elapsed_time - self.last_timestamp;
+            self.frame_times.push(frame_ms);
+            self.delta = (frame_ms / 1000_f64).min(MAX_DELTA);
+            self.accumulator += self.delta;
         }
         self.last_timestamp = elapsed_time;
+        self.input.begin_frame();
+    }
+
+    // Number of FIXED_STEPs to run this frame, the remainder carries over.
+    pub fn take_steps(&mut self) -> u32 {
+        let steps = (self.accumulator / FIXED_STEP).floor();
+        self.accumulator -= steps * FIXED_STEP;
+        steps as u32
+    }
+
+    pub fn frame_stats(&self) -> FrameStats {
+        self.frame_times.stats()
     }
 
     pub fn render_fps<F>(&mut self, elapsed_time: f64, render_delay_ms: i32, render_cb: F)
@@ -49,39 +74,6 @@ impl EngineState {
         }
     }
 
-    pub fn set_key_down(&mut self, raw_key_code: u32) {
-        let was_down = self.is_raw_key_down(raw_key_code);
-        self.was_down.insert(raw_key_code, was_down);
-        if !was_down {
-            self.key_state.insert(raw_key_code, true);
-        } else {
-            self.was_down.insert(raw_key_code, true);
-        }
-    }
-
-    pub fn set_key_up(&mut self, raw_key_code: u32) {
-        self.was_down.insert(raw_key_code, false);
-        self.key_state.insert(raw_key_code, false);
-    }
-
-    pub fn is_key_down(&self, key_code: KeyCode) -> bool {
-        self.is_raw_key_down(key_code as u32)
-    }
-
-    fn is_raw_key_down(&self, raw_key_code: u32) -> bool {
-        match self.key_state.get(&raw_key_code) {
-            Some(is_down) => *is_down,
-            None => false,
-        }
-    }
-
-    pub fn was_key_down(&self, key_code: KeyCode) -> bool {
-        match self.was_down.get(&(key_code as u32)) {
-            Some(was_down) => *was_down,
-            None => false,
-        }
-    }
-
     pub fn mouse_move(&mut self, x: i32, y: i32) {
         self.mouse_x = x;
         self.mouse_y = y;
@@ -112,7 +104,7 @@ impl Transform {
     }
 }
 
-#[derive(Clone)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct Color {
     pub h: u16,
     pub s: u16,
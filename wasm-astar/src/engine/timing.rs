@@ -0,0 +1,59 @@
+use std::collections::VecDeque;
+
+use wasm_bindgen::prelude::*;
+
+// Frames the stats are taken over, about two seconds at 60fps
+const FRAME_WINDOW: usize = 120;
+
+// Frame times in milliseconds over the last FRAME_WINDOW frames, all 0
+// before the second tick.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct FrameStats {
+    pub frames: u32,
+    pub min_ms: f64,
+    pub avg_ms: f64,
+    pub max_ms: f64,
+    pub p99_ms: f64,
+    // From avg_ms, steadier than one frame's delta
+    pub fps: f64,
+}
+
+pub struct FrameTimes {
+    times_ms: VecDeque<f64>,
+}
+
+impl FrameTimes {
+    pub fn new() -> FrameTimes {
+        FrameTimes {
+            times_ms: VecDeque::with_capacity(FRAME_WINDOW),
+        }
+    }
+
+    pub fn push(&mut self, frame_ms: f64) {
+        if self.times_ms.len() == FRAME_WINDOW {
+            self.times_ms.pop_front();
+        }
+        self.times_ms.push_back(frame_ms);
+    }
+
+    pub fn stats(&self) -> FrameStats {
+        if self.times_ms.is_empty() {
+            return FrameStats::default();
+        }
+        let mut sorted: Vec<f64> = self.times_ms.iter().copied().collect();
+        sorted.sort_by(f64::total_cmp);
+        let count = sorted.len();
+        let avg_ms = sorted.iter().sum::<f64>() / count as f64;
+        // Nearest rank, the slowest frame until there are 100 of them
+        let p99_index = ((count as f64 * 0.99).ceil() as usize).clamp(1, count) - 1;
+        FrameStats {
+            frames: count as u32,
+            min_ms: sorted[0],
+            avg_ms,
+            max_ms: sorted[count - 1],
+            p99_ms: sorted[p99_index],
+            fps: if avg_ms > 0_f64 { 1000_f64 / avg_ms } else { 0_f64 },
+        }
+    }
+}
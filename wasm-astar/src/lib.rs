@@ -1,260 +1,523 @@
 use wasm_bindgen::prelude::*;
-use std::sync::{LazyLock, Mutex};
 
-mod browser;
+mod draw;
 mod engine;
+pub mod render;
 mod utils;
 mod world;
-use engine::EngineState;
-use world::{Tile, WorldState};
+use draw::{Layer, Painter};
+use engine::{Action, Camera, EngineState, FIXED_STEP};
+pub use engine::FrameStats;
+use render::{CanvasRenderer, Renderer};
+pub use world::ClusterStats;
+use world::{Algorithm, Generator, Heuristic, Movement, PathSmoothing, WorldState};
 
-// Imported js functions. Note, some are used in other modules (browser, utils).
-#[wasm_bindgen]
-extern "C" {
-    #[wasm_bindgen(js_name = "js_update")]
-    fn js_update();
-    
-    #[wasm_bindgen(js_name = "js_draw_fps")]
-    fn js_draw_fps(layer_id: i32, fps: f64);
-    
-    #[wasm_bindgen(js_name = "js_path_count")]
-    fn js_path_count(layer_id: i32, count: i32);
-    
-    #[wasm_bindgen(js_name = "js_draw_circle")]
-    fn js_draw_circle(
-        layer_id: i32,
-        px: f64,
-        py: f64,
-        radius: f64,
-        ch: i32,
-        cs: i32,
-        cl: i32,
-        ca: f32,
-    );
-    
-    #[wasm_bindgen(js_name = "js_draw_tile")]
-    fn js_draw_tile(
-        layer_id: i32,
-        px: f64,
-        py: f64,
-        size: f64,
-        ch: i32,
-        cs: i32,
-        cl: i32,
-        ca: f32,
-    );
-}
-
-// Learned about this pattern from rocket_wasm on github
-// https://github.com/aochagavia/rocket_wasm/blob/d0ca51beb9c7c351a1f0266206edfd553bf078d3/src/lib.rs
-// QUESTION: is there a better way/place to store state???
-static WORLD_STATE: LazyLock<Mutex<WorldState>> = LazyLock::new(|| Mutex::new(WorldState::new()));
-static ENGINE_STATE: LazyLock<Mutex<EngineState>> = LazyLock::new(|| Mutex::new(EngineState::new()));
-
-// Maps to WASM_ASTAR.layers on the client side
-enum Layer {
-    TileBg = 0,
-    Main = 1,
-    Fps = 2,
-}
+// Largest generated map each way, see World::set_map_size
+const MAX_MAP_TILES: u32 = 2000;
 
 #[wasm_bindgen(start)]
 pub fn init() {
     console_error_panic_hook::set_once();
 }
 
+// One simulation: its map, search and input state. The client owns the tick
+// loop and calls tick on every World it hosts, so several can run on a page.
 #[wasm_bindgen]
-pub fn wasm_init(debug: i32, render_interval_ms: i32, window_width: u32, window_height: u32) {
-    utils::log("Initializing Rust/WASM");
-    // Requires block curlies so lifetime of world ends which causes unlock
-    // and allows initial_draw() to gain control of the lock.
-    // Otherwise, this generic client error occurs: "RuntimeError: unreachable executed"
-    // QUESTION: is there a better way to do this?
-    browser::create_layer("TileBg", Layer::TileBg as i32);
-    browser::create_layer("Main", Layer::Main as i32);
-    browser::create_layer("Fps", Layer::Fps as i32);
-    {
-        let world = &mut WORLD_STATE.lock().unwrap();
+pub struct World {
+    world: WorldState,
+    engine: EngineState,
+    renderer: Box<dyn Renderer>,
+    layer_base: i32,
+    // Camera the TileBg layer was last drawn with, it is redrawn when they differ
+    drawn_camera: Option<Camera>,
+}
+
+#[wasm_bindgen]
+impl World {
+    // Draws onto the client's canvases through the js_* callbacks
+    #[wasm_bindgen(constructor)]
+    pub fn new(layer_base: i32) -> World {
+        World::with_renderer(layer_base, Box::new(CanvasRenderer::new()))
+    }
+
+    pub fn init(&mut self, debug: i32, window_width: u32, window_height: u32) {
+        utils::log("Initializing Rust/WASM");
+        for (name, layer) in [
+            ("TileBg", Layer::TileBg),
+            ("Main", Layer::Main),
+            ("Fps", Layer::Fps),
+        ] {
+            let layer_id = self.layer_id(layer);
+            self.renderer
+                .create_layer(&format!("{}{}", name, self.layer_base), layer_id);
+        }
+        let world = &mut self.world;
         world.window_width = window_width;
         world.window_height = window_height;
-        world.debug = if debug == 1 { true } else { false };
+        world.debug = debug == 1;
         utils::log_fmt(format!("Debug Mode: {}", world.debug));
+        // Debug mode ticks on an interval, slow enough to watch the search one expansion at a time
         if world.debug {
-            browser::start_interval_tick(render_interval_ms);
+            world.set_stepping(true, 1);
+        }
+        world.load_seeded_map(utils::random_seed());
+        utils::log_fmt(format!("Map seed: {}", world.seed));
+        self.initial_draw();
+    }
+
+    pub fn tick(&mut self, elapsed_time: f64) {
+        self.clear(Layer::Main);
+        self.update(elapsed_time);
+        self.draw(elapsed_time);
+    }
+
+    pub fn key_down(&mut self, key_code: u32) {
+        self.engine.input.key_down(key_code);
+    }
+
+    pub fn key_up(&mut self, key_code: u32) {
+        self.engine.input.key_up(key_code);
+    }
+
+    // Actions: move_up, move_down, move_left, move_right, regenerate.
+    // Arrows, WASD and space are bound by default.
+    pub fn bind_key(&mut self, action_name: &str, key_code: u32) -> Result<(), String> {
+        let action = parse_action(action_name)?;
+        self.engine.input.bind(key_code, action);
+        utils::log_fmt(format!("Bound key {} to {}", key_code, action.name()));
+        Ok(())
+    }
+
+    pub fn unbind_key(&mut self, key_code: u32) {
+        self.engine.input.unbind(key_code);
+    }
+
+    pub fn reset_key_bindings(&mut self) {
+        self.engine.input.reset_bindings();
+    }
+
+    // Edges are per tick: pressed and released are true for the one tick
+    // after the first key for the action went down or the last one came up.
+    pub fn is_action_pressed(&self, action_name: &str) -> Result<bool, String> {
+        Ok(self.engine.input.is_pressed(parse_action(action_name)?))
+    }
+
+    pub fn is_action_released(&self, action_name: &str) -> Result<bool, String> {
+        Ok(self.engine.input.is_released(parse_action(action_name)?))
+    }
+
+    pub fn is_action_held(&self, action_name: &str) -> Result<bool, String> {
+        Ok(self.engine.input.is_held(parse_action(action_name)?))
+    }
+
+    // Analog movement, e.g. a gamepad stick, each axis -1 to 1. Adds to the
+    // move keys and stays until set again.
+    pub fn set_move_axis(&mut self, x: f64, y: f64) {
+        self.engine.input.set_axis(x, y);
+    }
+
+    // Mouse positions are CSS pixels from the top left of the canvas
+    pub fn mouse_move(&mut self, x: i32, y: i32) {
+        self.engine.mouse_move(x, y);
+        let (world_x, world_y) = self.to_world(x as f64, y as f64);
+        // The player stays put while painting or dragging the goal
+        if self.world.is_editing() {
+            if let Some(id) = self.world.continue_edit(world_x, world_y) {
+                self.draw_tile(id);
+            }
+        } else if !self.world.follow_path {
+            self.world.set_player_pos(world_x, world_y);
+        }
+    }
+
+    // Pressing on a wall erases walls while dragging, anywhere else paints them,
+    // and pressing on the goal drags it instead.
+    pub fn mouse_down(&mut self, x: i32, y: i32) {
+        let (world_x, world_y) = self.to_world(x as f64, y as f64);
+        if let Some(id) = self.world.begin_edit(world_x, world_y) {
+            self.draw_tile(id);
+        }
+    }
+
+    pub fn mouse_up(&mut self) {
+        self.world.end_edit();
+    }
+
+    // Drags the map by dx/dy CSS pixels. Which drag pans and which one
+    // edits is up to the client.
+    pub fn pan_camera(&mut self, dx: f64, dy: f64) {
+        let quality = self.world.quality as f64;
+        self.engine.camera.pan(dx * quality, dy * quality);
+        self.clamp_camera();
+    }
+
+    // Zooms in for factors above 1 and out below, around the CSS pixel x/y.
+    // Tiles stay between 4 and 200 canvas pixels.
+    pub fn zoom_camera(&mut self, factor: f64, x: f64, y: f64) {
+        if !factor.is_finite() || factor <= 0_f64 {
+            utils::log_fmt(format!("Invalid zoom factor: {}", factor));
+            return;
+        }
+        let quality = self.world.quality as f64;
+        self.engine.camera.zoom_at(factor, x * quality, y * quality);
+        self.clamp_camera();
+    }
+
+    // Back to the top left of the map at 1:1
+    pub fn reset_camera(&mut self) {
+        self.engine.camera = Camera::new();
+        self.clamp_camera();
+    }
+
+    // Ids map to world::Algorithm: 0 A*, 1 Dijkstra, 2 greedy best-first,
+    // 3 breadth-first, 4 jump point search, 5 bidirectional A*, 6 D* Lite,
+    // 7 HPA*. Jump point search runs as A* on maps with mixed terrain costs,
+    // get_searched_algorithm_name tells when it did.
+    pub fn set_search_algorithm(&mut self, algorithm_id: u32) {
+        match Algorithm::from_id(algorithm_id) {
+            Some(algorithm) => {
+                self.world.algorithm = algorithm;
+                utils::log_fmt(format!("Search algorithm: {}", algorithm.searcher().name()));
+            }
+            None => utils::log_fmt(format!("Unknown search algorithm id: {}", algorithm_id)),
+        }
+    }
+
+    pub fn get_search_algorithm_name(&self) -> String {
+        self.world.algorithm.searcher().name().to_string()
+    }
+
+    // The algorithm the most recent calc_path actually ran, empty before the first
+    pub fn get_searched_algorithm_name(&self) -> String {
+        self.world.search_stats.searched_with.to_string()
+    }
+
+    // Ids map to world::Movement: 0 cardinal, 1 diagonal, 2 diagonal without corner cutting
+    pub fn set_movement(&mut self, movement_id: u32) {
+        match Movement::from_id(movement_id) {
+            Some(movement) => {
+                self.world.set_movement(movement);
+                utils::log_fmt(format!("Movement: {}", movement.name()));
+            }
+            None => utils::log_fmt(format!("Unknown movement id: {}", movement_id)),
+        }
+    }
+
+    // Ids map to world::Heuristic: 0 Manhattan, 1 octile, 2 Chebyshev, 3 Euclidean, 4 zero
+    pub fn set_heuristic(&mut self, heuristic_id: u32) {
+        match Heuristic::from_id(heuristic_id) {
+            Some(heuristic) => {
+                self.world.heuristic = heuristic;
+                utils::log_fmt(format!("Heuristic: {}", heuristic.name()));
+            }
+            None => utils::log_fmt(format!("Unknown heuristic id: {}", heuristic_id)),
+        }
+    }
+
+    // Ids map to world::PathSmoothing: 0 off, 1 string pulling, 2 string
+    // pulling then a Catmull-Rom curve
+    pub fn set_path_smoothing(&mut self, smoothing_id: u32) {
+        match PathSmoothing::from_id(smoothing_id) {
+            Some(path_smoothing) => {
+                self.world.set_path_smoothing(path_smoothing);
+                utils::log_fmt(format!("Path smoothing: {}", path_smoothing.name()));
+            }
+            None => utils::log_fmt(format!("Unknown path smoothing id: {}", smoothing_id)),
+        }
+    }
+
+    // String pulled waypoints of the current path as x, y pairs in world
+    // pixels (canvas pixels at the default camera), start first. Empty while
+    // path smoothing is off or there is no path.
+    pub fn get_path_waypoints(&self) -> Vec<f64> {
+        flatten_points(&self.world.path_waypoints)
+    }
+
+    // The Catmull-Rom curve through the waypoints, laid out the same way.
+    // Only filled with path smoothing 2.
+    pub fn get_smoothed_path(&self) -> Vec<f64> {
+        flatten_points(&self.world.smoothed_path)
+    }
+
+    // Stats from the most recent calc_path, for profiling alongside the path count.
+    pub fn get_search_nodes_expanded(&self) -> u32 {
+        self.world.search_stats.nodes_expanded
+    }
+
+    pub fn get_search_time_us(&self) -> f64 {
+        self.world.search_stats.elapsed_us
+    }
+
+    // Size of the HPA* cluster graph, how many clusters the last search had
+    // to rebuild, its bound on how far off the cheapest path it can be per
+    // border, and the cost of its current path with that bound for the
+    // borders the path crosses. All 0 until HPA* has run.
+    pub fn get_cluster_stats(&self) -> ClusterStats {
+        self.world.cluster_stats()
+    }
+
+    // Frame times over the last couple of seconds, the same numbers the fps
+    // layer shows
+    pub fn get_frame_stats(&self) -> FrameStats {
+        self.engine.frame_stats()
+    }
+
+    // Running totals since startup. Incremental replans only happen with D* Lite,
+    // skipped ones are frames where neither the start, end, map nor settings changed.
+    pub fn get_full_replans(&self) -> u32 {
+        self.world.replan_stats.full
+    }
+
+    pub fn get_incremental_replans(&self) -> u32 {
+        self.world.replan_stats.incremental
+    }
+
+    pub fn get_skipped_replans(&self) -> u32 {
+        self.world.replan_stats.skipped
+    }
+
+    // Agents plan around each other (WHCA*) and wander between random goals,
+    // they are respawned with every new map.
+    pub fn set_agent_count(&mut self, count: u32) {
+        self.world.set_agent_count(count as usize);
+        utils::log_fmt(format!("Agents: {}", self.world.agents.agents.len()));
+    }
+
+    // Follow mode: the player walks the path on its own instead of sticking to
+    // the mouse, drag the goal to send it somewhere else.
+    pub fn set_follow_path(&mut self, enabled: i32) {
+        self.world.set_follow_path(enabled == 1);
+        utils::log_fmt(format!("Follow path: {}", self.world.follow_path));
+    }
+
+    pub fn set_player_speed(&mut self, tiles_per_second: f64) {
+        if tiles_per_second.is_finite() && tiles_per_second > 0_f64 {
+            self.world.player_speed = tiles_per_second;
         } else {
-            browser::request_next_tick();
+            utils::log_fmt(format!("Invalid player speed: {}", tiles_per_second));
         }
     }
-    initial_draw();
-}
 
-#[wasm_bindgen]
-pub fn tick(elapsed_time: f64) {
-    browser::clear_screen(Layer::Main as i32);
-    update(elapsed_time);
-    draw(elapsed_time);
-    browser::request_next_tick();
-}
+    // Step mode: each tick expands steps_per_tick more nodes and the open/closed
+    // sets are drawn. steps_per_tick 0 only advances on step_search.
+    pub fn set_search_stepping(&mut self, enabled: i32, steps_per_tick: u32) {
+        self.world.set_stepping(enabled == 1, steps_per_tick);
+        utils::log_fmt(format!(
+            "Search stepping: {} ({} per tick)",
+            self.world.stepping, steps_per_tick
+        ));
+    }
 
-#[wasm_bindgen]
-pub fn key_down(key_code: u32) {
-    let engine = &mut ENGINE_STATE.lock().unwrap();
-    engine.set_key_down(key_code);
-}
+    pub fn step_search(&mut self, steps: u32) {
+        self.world.step_search(steps);
+    }
 
-#[wasm_bindgen]
-pub fn key_up(key_code: u32) {
-    let engine = &mut ENGINE_STATE.lock().unwrap();
-    engine.set_key_up(key_code);
-}
+    // Regenerates the map from a seed so a layout can be shared and reproduced.
+    pub fn set_map_seed(&mut self, seed: u32) {
+        self.world.load_seeded_map(seed);
+        utils::log_fmt(format!("Map seed: {}", self.world.seed));
+        self.show_new_map();
+    }
 
-#[wasm_bindgen]
-pub fn mouse_move(x: i32, y: i32) {
-    let engine = &mut ENGINE_STATE.lock().unwrap();
-    let world = &mut WORLD_STATE.lock().unwrap();
-    engine.mouse_move(x, y);
-    world.set_player_pos(x as f64, y as f64);
-}
+    pub fn get_map_seed(&self) -> u32 {
+        self.world.seed
+    }
 
-fn update(elapsed_time: f64) {
-    handle_input();
-    let engine = &mut ENGINE_STATE.lock().unwrap();
-    engine.update(elapsed_time);
-    let world = &mut WORLD_STATE.lock().unwrap();
-    world.set_start_node();
-    world.calc_astar();
-    js_update();
-}
+    // Ids map to world::Generator: 0 noise, 1 recursive backtracker, 2 Prim,
+    // 3 cellular caves, 4 rooms and corridors. The map is regenerated from the
+    // current seed so switching back and forth shows the same layout.
+    pub fn set_map_generator(&mut self, generator_id: u32) {
+        match Generator::from_id(generator_id) {
+            Some(generator) => {
+                self.world.generator = generator;
+                utils::log_fmt(format!("Map generator: {}", generator.name()));
+                let seed = self.world.seed;
+                self.world.load_seeded_map(seed);
+                self.show_new_map();
+            }
+            None => utils::log_fmt(format!("Unknown map generator id: {}", generator_id)),
+        }
+    }
 
-fn handle_input() {
-    let world = &mut WORLD_STATE.lock().unwrap();
-    let engine = &mut ENGINE_STATE.lock().unwrap();
-    if !engine.was_key_down(engine::KeyCode::Spacebar)
-        && engine.is_key_down(engine::KeyCode::Spacebar)
-        && !world.recent_regen
-    {
-        world.reset();
-        browser::clear_screen(Layer::Main as i32);
-        // Horrible check until i implement event callbacks for key presses
-        world.recent_regen = true;
-    } else if !engine.is_key_down(engine::KeyCode::Spacebar) {
-        world.recent_regen = false;
-    }
-
-    let mut x_dir = 0;
-    let mut y_dir = 0;
-    if engine.is_key_down(engine::KeyCode::ArrowUp) {
-        y_dir = -1;
-    } else if engine.is_key_down(engine::KeyCode::ArrowDown) {
-        y_dir = 1;
-    }
-    if engine.is_key_down(engine::KeyCode::ArrowLeft) {
-        x_dir = -1;
-    } else if engine.is_key_down(engine::KeyCode::ArrowRight) {
-        x_dir = 1;
-    }
-    world.update_player(x_dir, y_dir);
-}
+    pub fn get_map_generator_name(&self) -> String {
+        self.world.generator.name().to_string()
+    }
 
-fn initial_draw() {
-    let world = &mut WORLD_STATE.lock().unwrap();
-    if world.window_width < 600 {
-        world.width = 350 * world.quality;
-        world.height = 450 * world.quality;
-        world.reset();
-    }
-    browser::set_screen_size(world.width, world.height, world.quality);
-    browser::set_layer_size(
-        Layer::TileBg as i32,
-        world.width,
-        world.height,
-        world.quality,
-    );
-    browser::set_layer_size(Layer::Main as i32, world.width, world.height, world.quality);
-    browser::set_layer_size(Layer::Fps as i32, 200, 70, world.quality);
-    draw_background(world);
-}
+    // Generated maps are num_x_tiles by num_y_tiles from now on, up to
+    // MAX_MAP_TILES each way, and bigger than the canvas they are panned and
+    // zoomed with the camera. 0 by 0 goes back to filling the canvas.
+    pub fn set_map_size(&mut self, num_x_tiles: u32, num_y_tiles: u32) {
+        self.world.map_size = match (num_x_tiles, num_y_tiles) {
+            (0, 0) => None,
+            (1..=MAX_MAP_TILES, 1..=MAX_MAP_TILES) => Some((num_x_tiles, num_y_tiles)),
+            _ => {
+                utils::log_fmt(format!("Invalid map size: {}x{}", num_x_tiles, num_y_tiles));
+                return;
+            }
+        };
+        // An imported map may have shrunk the canvas
+        self.world.fit_to_window();
+        let seed = self.world.seed;
+        self.world.load_seeded_map(seed);
+        utils::log_fmt(format!("Map size: {}x{}", self.world.num_x_tiles, self.world.num_y_tiles));
+        self.set_screen_size();
+        self.show_new_map();
+    }
 
-fn draw(elapsed_time: f64) {
-    let world = &mut WORLD_STATE.lock().unwrap();
-    if world.recent_regen {
-        draw_background(world);
-    }
-    draw_path(world, &world.tiles[world.end_id as usize]);
-    draw_tile_with_color(
-        Layer::Main,
-        &world.tiles[world.start_id as usize],
-        &engine::Color::new(32, 100, 60, 0.3),
-    );
-    draw_tile_with_color(
-        Layer::Main,
-        &world.tiles[world.end_id as usize],
-        &engine::Color::new(112, 89, 61, 1.0),
-    );
-    let path_count = get_path_count(world, &world.tiles[world.end_id as usize], 0);
-    draw_path_count(path_count);
-    // draw_player(world);
-    draw_fps(elapsed_time);
-}
+    // Loads a map in the text format described in world/map.rs.
+    // Invalid maps are rejected with a message and the current map is kept.
+    pub fn import_map(&mut self, text: &str) -> Result<(), String> {
+        self.world.load_map_text(text).map_err(|e| e.to_string())?;
+        self.set_screen_size();
+        self.show_new_map();
+        Ok(())
+    }
 
-fn draw_background(world: &WorldState) {
-    for t in world.tiles.iter() {
-        draw_tile(Layer::TileBg, &t);
+    pub fn export_map(&self) -> String {
+        self.world.map_text()
     }
 }
 
-fn draw_path(world: &WorldState, t: &Tile) {
-    let half_tile = (world.tile_size / 2) as f64;
-    js_draw_circle(
-        Layer::Main as i32,
-        t.transform.pos_x + half_tile,
-        t.transform.pos_y + half_tile,
-        t.transform.scale_x / 5_f64,
-        280,
-        100,
-        73,
-        1_f32,
-    );
-    if t.parent_id >= 0 {
-        draw_path(world, &world.tiles[t.parent_id as usize]);
+impl World {
+    // For running natively, e.g. with a render::HeadlessRenderer under cargo test
+    pub fn with_renderer(layer_base: i32, renderer: Box<dyn Renderer>) -> World {
+        World {
+            world: WorldState::new(),
+            engine: EngineState::new(),
+            renderer,
+            layer_base,
+            drawn_camera: None,
+        }
     }
-}
 
-fn get_path_count(world: &WorldState, t: &Tile, counter: i32) -> i32 {
-    if t.parent_id >= 0 {
-        get_path_count(world, &world.tiles[t.parent_id as usize], counter + 1)
-    } else {
-        counter
+    fn layer_id(&self, layer: Layer) -> i32 {
+        layer.id(self.layer_base)
     }
-}
 
-fn draw_tile(layer: Layer, t: &Tile) {
-    draw_tile_with_color(layer, &t, &t.color);
-}
+    fn clear(&mut self, layer: Layer) {
+        let layer_id = self.layer_id(layer);
+        self.renderer.clear(layer_id);
+    }
 
-fn draw_tile_with_color(layer: Layer, t: &Tile, c: &engine::Color) {
-    js_draw_tile(
-        layer as i32,
-        t.transform.pos_x,
-        t.transform.pos_y,
-        t.transform.scale_x,
-        c.h as i32,
-        c.s as i32,
-        c.l as i32,
-        c.a,
-    );
-}
+    fn painter(&mut self) -> Painter<'_> {
+        Painter {
+            world: &self.world,
+            renderer: self.renderer.as_mut(),
+            layer_base: self.layer_base,
+            camera: self.engine.camera,
+        }
+    }
+
+    // Redraws one background tile after its terrain changed
+    fn draw_tile(&mut self, id: usize) {
+        let mut painter = self.painter();
+        painter.draw_tile(Layer::TileBg, &painter.world.tiles[id]);
+        self.renderer.flush();
+    }
+
+    // Clears both map layers and draws the background as the camera sees it,
+    // after a new map or whenever the camera moved
+    fn redraw_map(&mut self) {
+        self.clear(Layer::TileBg);
+        self.clear(Layer::Main);
+        self.painter().draw_background();
+        self.renderer.flush();
+        self.drawn_camera = Some(self.engine.camera);
+    }
+
+    // A new map starts out with the camera at its top left corner
+    fn show_new_map(&mut self) {
+        self.reset_camera();
+        self.redraw_map();
+    }
+
+    fn clamp_camera(&mut self) {
+        let world = &self.world;
+        let view = (world.width as f64, world.height as f64);
+        let map = (world.map_width() as f64, world.map_height() as f64);
+        self.engine.camera.clamp(view, map, world.tile_size as f64);
+    }
 
-fn draw_path_count(path_count: i32) {
-    js_path_count(Layer::Main as i32, path_count);
+    // CSS pixels on the canvas to world pixels
+    fn to_world(&self, x: f64, y: f64) -> (f64, f64) {
+        let quality = self.world.quality as f64;
+        self.engine.camera.to_world(x * quality, y * quality)
+    }
+
+    fn update(&mut self, elapsed_time: f64) {
+        self.engine.update(elapsed_time);
+        let steps = self.engine.take_steps();
+        self.handle_input(steps);
+        let world = &mut self.world;
+        world.set_start_node();
+        if world.stepping {
+            let steps = world.steps_per_tick;
+            world.step_search(steps);
+        }
+        world.calc_path();
+        world.walk_path(steps as f64 * FIXED_STEP);
+        world.update_agents(elapsed_time);
+        self.renderer.update();
+    }
+
+    // Player movement runs once per fixed step, so it keeps the same speed at
+    // any frame rate and doesn't move at all on a frame with no steps.
+    fn handle_input(&mut self, steps: u32) {
+        let input = &self.engine.input;
+        if input.is_pressed(Action::Regenerate) {
+            self.world.reset();
+            self.show_new_map();
+        }
+        let (x_dir, y_dir) = self.engine.input.move_axis();
+        for _ in 0..steps {
+            self.world.update_player(x_dir, y_dir);
+        }
+    }
+
+    fn initial_draw(&mut self) {
+        let world = &mut self.world;
+        if world.window_width < 600 {
+            world.fit_to_window();
+            let seed = world.seed;
+            world.load_seeded_map(seed);
+        }
+        self.set_screen_size();
+        self.show_new_map();
+    }
+
+    fn set_screen_size(&mut self) {
+        let (width, height, quality) = (self.world.width, self.world.height, self.world.quality);
+        let layer_base = self.layer_base;
+        let renderer = self.renderer.as_mut();
+        renderer.set_screen_size(width, height, quality);
+        renderer.set_layer_size(Layer::TileBg.id(layer_base), width, height, quality);
+        renderer.set_layer_size(Layer::Main.id(layer_base), width, height, quality);
+        renderer.set_layer_size(Layer::Fps.id(layer_base), 300, 100, quality);
+    }
+
+    fn draw(&mut self, elapsed_time: f64) {
+        if self.drawn_camera != Some(self.engine.camera) {
+            self.redraw_map();
+        }
+        let stats = self.engine.frame_stats();
+        let mut painter = Painter {
+            world: &self.world,
+            renderer: self.renderer.as_mut(),
+            layer_base: self.layer_base,
+            camera: self.engine.camera,
+        };
+        painter.draw_frame(elapsed_time);
+        self.engine
+            .render_fps(elapsed_time, 150, || painter.draw_frame_stats(&stats));
+        self.renderer.flush();
+    }
 }
 
-fn draw_fps(elapsed_time: f64) {
-    let engine = &mut ENGINE_STATE.lock().unwrap();
-    let fps = engine.fps;
-    engine.render_fps(elapsed_time, 150, || {
-        browser::clear_screen(Layer::Fps as i32);
-        js_draw_fps(Layer::Fps as i32, fps);
-    });
+fn parse_action(action_name: &str) -> Result<Action, String> {
+    Action::from_name(action_name).ok_or_else(|| format!("Unknown action: {}", action_name))
 }
 
+// x, y pairs one after the other, the layout JS gets as a Float64Array
+fn flatten_points(points: &[(f64, f64)]) -> Vec<f64> {
+    points.iter().flat_map(|(x, y)| [*x, *y]).collect()
+}
@@ -0,0 +1,146 @@
+use wasm_bindgen::prelude::*;
+
+use super::Renderer;
+use crate::engine::{Color, FrameStats};
+
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(js_name = "js_create_layer")]
+    fn js_create_layer(id: &str, key: i32);
+
+    #[wasm_bindgen(js_name = "js_set_screen_size")]
+    fn js_set_screen_size(width: i32, height: i32, quality: i32);
+
+    #[wasm_bindgen(js_name = "js_set_layer_size")]
+    fn js_set_layer_size(layer_id: i32, width: i32, height: i32, quality: i32);
+
+    #[wasm_bindgen(js_name = "js_update")]
+    fn js_update();
+
+    #[wasm_bindgen(js_name = "js_draw_frame_stats")]
+    fn js_draw_frame_stats(layer_id: i32, fps: f64, min_ms: f64, avg_ms: f64, max_ms: f64, p99_ms: f64);
+
+    #[wasm_bindgen(js_name = "js_path_count")]
+    fn js_path_count(layer_id: i32, count: i32);
+
+    #[wasm_bindgen(js_name = "js_search_stats")]
+    fn js_search_stats(layer_id: i32, nodes_expanded: i32, elapsed_us: f64);
+
+    #[wasm_bindgen(js_name = "js_draw_node_scores")]
+    fn js_draw_node_scores(layer_id: i32, px: f64, py: f64, size: f64, g: i32, h: i32, f: i32);
+
+    #[wasm_bindgen(js_name = "js_draw_batch")]
+    fn js_draw_batch(commands: &[f32]);
+
+    #[wasm_bindgen(js_name = "js_draw_line")]
+    fn js_draw_line(layer_id: i32, points: &[f32], width: f64, h: i32, s: i32, l: i32, a: f32);
+}
+
+// Clears, tiles and circles are queued as COMMAND_SIZE floats each
+// (op, layer, x, y, size or radius, h, s, l, a) and handed to js_draw_batch
+// in one call, the js side reads them straight out of wasm memory. Anything
+// else flushes the queue first so the draw order stays the same.
+const COMMAND_SIZE: usize = 9;
+const OP_CLEAR: f32 = 0_f32;
+const OP_TILE: f32 = 1_f32;
+const OP_CIRCLE: f32 = 2_f32;
+
+// Draws through the js_* callbacks onto the canvases in WASM_ASTAR.layers.
+// The queue and the line points (x, y pairs) keep their capacity between frames.
+#[derive(Default)]
+pub struct CanvasRenderer {
+    commands: Vec<f32>,
+    line_points: Vec<f32>,
+}
+
+impl CanvasRenderer {
+    pub fn new() -> CanvasRenderer {
+        CanvasRenderer::default()
+    }
+
+    fn push(&mut self, op: f32, layer_id: i32, px: f64, py: f64, size: f64, c: &Color) {
+        let command: [f32; COMMAND_SIZE] = [
+            op,
+            layer_id as f32,
+            px as f32,
+            py as f32,
+            size as f32,
+            c.h as f32,
+            c.s as f32,
+            c.l as f32,
+            c.a,
+        ];
+        self.commands.extend_from_slice(&command);
+    }
+}
+
+impl Renderer for CanvasRenderer {
+    fn create_layer(&mut self, name: &str, layer_id: i32) {
+        self.flush();
+        js_create_layer(name, layer_id);
+    }
+
+    fn set_screen_size(&mut self, width: u32, height: u32, quality: u32) {
+        self.flush();
+        js_set_screen_size(width as i32, height as i32, quality as i32);
+    }
+
+    fn set_layer_size(&mut self, layer_id: i32, width: u32, height: u32, quality: u32) {
+        self.flush();
+        js_set_layer_size(layer_id, width as i32, height as i32, quality as i32);
+    }
+
+    fn clear(&mut self, layer_id: i32) {
+        self.push(OP_CLEAR, layer_id, 0_f64, 0_f64, 0_f64, &Color::default());
+    }
+
+    fn draw_tile(&mut self, layer_id: i32, px: f64, py: f64, size: f64, c: &Color) {
+        self.push(OP_TILE, layer_id, px, py, size, c);
+    }
+
+    fn draw_circle(&mut self, layer_id: i32, px: f64, py: f64, radius: f64, c: &Color) {
+        self.push(OP_CIRCLE, layer_id, px, py, radius, c);
+    }
+
+    fn draw_line(&mut self, layer_id: i32, points: &[(f64, f64)], width: f64, c: &Color) {
+        self.flush();
+        self.line_points.clear();
+        for (px, py) in points.iter() {
+            self.line_points.push(*px as f32);
+            self.line_points.push(*py as f32);
+        }
+        js_draw_line(layer_id, &self.line_points, width, c.h as i32, c.s as i32, c.l as i32, c.a);
+    }
+
+    fn draw_node_scores(&mut self, layer_id: i32, px: f64, py: f64, size: f64, scores: [i32; 3]) {
+        self.flush();
+        let [g, h, f] = scores;
+        js_draw_node_scores(layer_id, px, py, size, g, h, f);
+    }
+
+    fn draw_frame_stats(&mut self, layer_id: i32, stats: &FrameStats) {
+        self.flush();
+        js_draw_frame_stats(layer_id, stats.fps, stats.min_ms, stats.avg_ms, stats.max_ms, stats.p99_ms);
+    }
+
+    fn draw_path_count(&mut self, layer_id: i32, count: i32) {
+        self.flush();
+        js_path_count(layer_id, count);
+    }
+
+    fn draw_search_stats(&mut self, layer_id: i32, nodes_expanded: u32, elapsed_us: f64) {
+        self.flush();
+        js_search_stats(layer_id, nodes_expanded as i32, elapsed_us);
+    }
+
+    fn update(&mut self) {
+        js_update();
+    }
+
+    fn flush(&mut self) {
+        if !self.commands.is_empty() {
+            js_draw_batch(&self.commands);
+            self.commands.clear();
+        }
+    }
+}
@@ -0,0 +1,381 @@
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::rc::Rc;
+
+use super::Renderer;
+use crate::engine::{Color, FrameStats};
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum DrawCommand {
+    Tile { px: f64, py: f64, size: f64, color: Color },
+    Circle { px: f64, py: f64, radius: f64, color: Color },
+    Line { points: Vec<(f64, f64)>, width: f64, color: Color },
+    NodeScores { px: f64, py: f64, size: f64, g: i32, h: i32, f: i32 },
+    FrameStats(FrameStats),
+    PathCount(i32),
+    SearchStats { nodes_expanded: u32, elapsed_us: f64 },
+}
+
+#[derive(Default)]
+struct Recording {
+    width: u32,
+    height: u32,
+    // Commands drawn since each layer was last cleared
+    layers: BTreeMap<i32, Vec<DrawCommand>>,
+    updates: u32,
+}
+
+// Records draw commands instead of drawing them, so a World can run natively
+// and its frames can be compared in tests. Clones share one recording: box
+// one into the World and keep the other to look at what it drew.
+#[derive(Clone, Default)]
+pub struct HeadlessRenderer {
+    recording: Rc<RefCell<Recording>>,
+}
+
+impl HeadlessRenderer {
+    pub fn new() -> HeadlessRenderer {
+        HeadlessRenderer::default()
+    }
+
+    // What is on a layer right now, oldest first
+    pub fn commands(&self, layer_id: i32) -> Vec<DrawCommand> {
+        let recording = self.recording.borrow();
+        recording.layers.get(&layer_id).cloned().unwrap_or_default()
+    }
+
+    pub fn screen_size(&self) -> (u32, u32) {
+        let recording = self.recording.borrow();
+        (recording.width, recording.height)
+    }
+
+    // Number of ticks drawn so far
+    pub fn updates(&self) -> u32 {
+        self.recording.borrow().updates
+    }
+
+    // Tiles, circles and lines of every layer blended into screen sized RGBA
+    // pixels, row by row from the top left. Text is left out.
+    pub fn rasterize(&self) -> Vec<u8> {
+        let recording = self.recording.borrow();
+        let mut canvas = Canvas {
+            width: recording.width as usize,
+            height: recording.height as usize,
+            pixels: vec![0; recording.width as usize * recording.height as usize * 4],
+        };
+        for command in recording.layers.values().flatten() {
+            match command {
+                DrawCommand::Tile { px, py, size, color } => {
+                    canvas.fill(*px, *py, *px + size, *py + size, color, |_, _| true);
+                }
+                DrawCommand::Circle {
+                    px,
+                    py,
+                    radius,
+                    color,
+                } => {
+                    canvas.fill(px - radius, py - radius, px + radius, py + radius, color, |x, y| {
+                        (x - px).powi(2) + (y - py).powi(2) <= radius * radius
+                    });
+                }
+                DrawCommand::Line {
+                    points,
+                    width,
+                    color,
+                } => {
+                    let r = width / 2_f64;
+                    // One capsule per segment, skipping pixels the previous
+                    // segment already covered so joins aren't blended twice
+                    for (i, pair) in points.windows(2).enumerate() {
+                        let (a, b) = (pair[0], pair[1]);
+                        let (x0, x1) = (a.0.min(b.0) - r, a.0.max(b.0) + r);
+                        let (y0, y1) = (a.1.min(b.1) - r, a.1.max(b.1) + r);
+                        canvas.fill(x0, y0, x1, y1, color, |x, y| {
+                            segment_distance((x, y), a, b) <= r
+                                && (i == 0 || segment_distance((x, y), points[i - 1], a) > r)
+                        });
+                    }
+                }
+                _ => {}
+            }
+        }
+        canvas.pixels
+    }
+
+    fn push(&self, layer_id: i32, command: DrawCommand) {
+        let mut recording = self.recording.borrow_mut();
+        recording.layers.entry(layer_id).or_default().push(command);
+    }
+}
+
+impl Renderer for HeadlessRenderer {
+    fn create_layer(&mut self, _name: &str, layer_id: i32) {
+        let mut recording = self.recording.borrow_mut();
+        recording.layers.entry(layer_id).or_default();
+    }
+
+    fn set_screen_size(&mut self, width: u32, height: u32, _quality: u32) {
+        let mut recording = self.recording.borrow_mut();
+        recording.width = width;
+        recording.height = height;
+    }
+
+    // Layers are all rasterized at the screen size
+    fn set_layer_size(&mut self, _layer_id: i32, _width: u32, _height: u32, _quality: u32) {}
+
+    fn clear(&mut self, layer_id: i32) {
+        let mut recording = self.recording.borrow_mut();
+        recording.layers.entry(layer_id).or_default().clear();
+    }
+
+    fn draw_tile(&mut self, layer_id: i32, px: f64, py: f64, size: f64, color: &Color) {
+        let color = color.clone();
+        self.push(layer_id, DrawCommand::Tile { px, py, size, color });
+    }
+
+    fn draw_circle(&mut self, layer_id: i32, px: f64, py: f64, radius: f64, color: &Color) {
+        let color = color.clone();
+        self.push(layer_id, DrawCommand::Circle { px, py, radius, color });
+    }
+
+    fn draw_line(&mut self, layer_id: i32, points: &[(f64, f64)], width: f64, color: &Color) {
+        let points = points.to_vec();
+        let color = color.clone();
+        self.push(layer_id, DrawCommand::Line { points, width, color });
+    }
+
+    fn draw_node_scores(&mut self, layer_id: i32, px: f64, py: f64, size: f64, scores: [i32; 3]) {
+        let [g, h, f] = scores;
+        self.push(layer_id, DrawCommand::NodeScores { px, py, size, g, h, f });
+    }
+
+    fn draw_frame_stats(&mut self, layer_id: i32, stats: &FrameStats) {
+        self.push(layer_id, DrawCommand::FrameStats(*stats));
+    }
+
+    fn draw_path_count(&mut self, layer_id: i32, count: i32) {
+        self.push(layer_id, DrawCommand::PathCount(count));
+    }
+
+    fn draw_search_stats(&mut self, layer_id: i32, nodes_expanded: u32, elapsed_us: f64) {
+        self.push(
+            layer_id,
+            DrawCommand::SearchStats {
+                nodes_expanded,
+                elapsed_us,
+            },
+        );
+    }
+
+    fn update(&mut self) {
+        self.recording.borrow_mut().updates += 1;
+    }
+}
+
+struct Canvas {
+    width: usize,
+    height: usize,
+    pixels: Vec<u8>,
+}
+
+impl Canvas {
+    // Blends color over the pixels between the corners whose centers pass `inside`.
+    fn fill<F>(&mut self, x0: f64, y0: f64, x1: f64, y1: f64, color: &Color, inside: F)
+    where
+        F: Fn(f64, f64) -> bool,
+    {
+        let rgb = hsl_to_rgb(color);
+        let alpha = color.a.clamp(0_f32, 1_f32) as f64;
+        let x_range = (x0.max(0_f64).floor() as usize)..(x1.max(0_f64).ceil() as usize).min(self.width);
+        let y_range = (y0.max(0_f64).floor() as usize)..(y1.max(0_f64).ceil() as usize).min(self.height);
+        for y in y_range {
+            for x in x_range.clone() {
+                let (cx, cy) = (x as f64 + 0.5, y as f64 + 0.5);
+                if cx < x0 || cx >= x1 || cy < y0 || cy >= y1 || !inside(cx, cy) {
+                    continue;
+                }
+                let i = (y * self.width + x) * 4;
+                for (channel, value) in rgb.iter().enumerate() {
+                    let old = self.pixels[i + channel] as f64;
+                    self.pixels[i + channel] = (value * alpha + old * (1_f64 - alpha)).round() as u8;
+                }
+                let old_alpha = self.pixels[i + 3] as f64 / 255_f64;
+                self.pixels[i + 3] = ((alpha + old_alpha * (1_f64 - alpha)) * 255_f64).round() as u8;
+            }
+        }
+    }
+}
+
+// Distance from p to the closest point of the segment a to b
+fn segment_distance(p: (f64, f64), a: (f64, f64), b: (f64, f64)) -> f64 {
+    let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+    let length_sq = dx * dx + dy * dy;
+    let t = if length_sq > 0_f64 {
+        (((p.0 - a.0) * dx + (p.1 - a.1) * dy) / length_sq).clamp(0_f64, 1_f64)
+    } else {
+        0_f64
+    };
+    (p.0 - a.0 - dx * t).hypot(p.1 - a.1 - dy * t)
+}
+
+// Same hsl() the canvas backend hands to fillStyle, channels 0 to 255.
+fn hsl_to_rgb(color: &Color) -> [f64; 3] {
+    let h = (color.h % 360) as f64 / 60_f64;
+    let s = color.s.min(100) as f64 / 100_f64;
+    let l = color.l.min(100) as f64 / 100_f64;
+    let chroma = (1_f64 - (2_f64 * l - 1_f64).abs()) * s;
+    let x = chroma * (1_f64 - (h % 2_f64 - 1_f64).abs());
+    let (r, g, b) = match h as u32 {
+        0 => (chroma, x, 0_f64),
+        1 => (x, chroma, 0_f64),
+        2 => (0_f64, chroma, x),
+        3 => (0_f64, x, chroma),
+        4 => (x, 0_f64, chroma),
+        _ => (chroma, 0_f64, x),
+    };
+    let m = l - chroma / 2_f64;
+    [(r + m) * 255_f64, (g + m) * 255_f64, (b + m) * 255_f64]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::World;
+
+    // A corridor with one way through it, from the top left to the bottom
+    // right, snaking around the two walls
+    const MAP: &str = "start 0,0
+end 5,3
+0,1,0,0,0,1
+0,1,0,1,0,1
+0,1,0,1,0,1
+0,0,0,1,0,0
+";
+    // A 500 wide window gets a 700x900 canvas, 116px tiles fit the map in it
+    const TILE: f64 = 116_f64;
+    const PATH: [(u32, u32); 15] = [
+        (5, 3),
+        (4, 3),
+        (4, 2),
+        (4, 1),
+        (4, 0),
+        (3, 0),
+        (2, 0),
+        (2, 1),
+        (2, 2),
+        (2, 3),
+        (1, 3),
+        (0, 3),
+        (0, 2),
+        (0, 1),
+        (0, 0),
+    ];
+    const GRASS: [u8; 4] = [77, 77, 77, 255];
+    const WALL: [u8; 4] = [26, 26, 26, 255];
+    // hsl(280, 100%, 73%) over anything
+    const PATH_DOT: [u8; 4] = [209, 117, 255, 255];
+
+    fn headless_world() -> (World, HeadlessRenderer) {
+        let renderer = HeadlessRenderer::new();
+        let mut world = World::with_renderer(0, Box::new(renderer.clone()));
+        world.init(0, 500, 800);
+        world.import_map(MAP).unwrap();
+        world.set_movement(0);
+        (world, renderer)
+    }
+
+    fn pixel(renderer: &HeadlessRenderer, x: usize, y: usize) -> [u8; 4] {
+        let (width, _) = renderer.screen_size();
+        let i = (y * width as usize + x) * 4;
+        renderer.rasterize()[i..i + 4].try_into().unwrap()
+    }
+
+    fn circles(commands: &[DrawCommand]) -> Vec<(f64, f64, f64)> {
+        commands
+            .iter()
+            .filter_map(|command| match command {
+                DrawCommand::Circle { px, py, radius, .. } => Some((*px, *py, *radius)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn background_frame() {
+        let (_, renderer) = headless_world();
+        assert_eq!(renderer.screen_size(), (696, 464));
+        let tiles = renderer.commands(0);
+        assert_eq!(tiles.len(), 24);
+        let walls = [1, 5, 7, 9, 11, 13, 15, 17, 21];
+        for (id, command) in tiles.iter().enumerate() {
+            let lightness = if walls.contains(&id) { 10 } else { 30 };
+            let expected = DrawCommand::Tile {
+                px: (id % 6) as f64 * TILE,
+                py: (id / 6) as f64 * TILE,
+                size: TILE,
+                color: Color::new(0, 0, lightness, 1_f32),
+            };
+            assert_eq!(*command, expected, "tile {}", id);
+        }
+        // Nothing on Main until the first tick
+        assert!(renderer.commands(1).is_empty());
+        assert_eq!(pixel(&renderer, 58, 58), GRASS);
+        assert_eq!(pixel(&renderer, 174, 58), WALL);
+        // The canvas stops at the map edge
+        assert_eq!(renderer.rasterize().len(), 696 * 464 * 4);
+    }
+
+    #[test]
+    fn path_overlay() {
+        let (mut world, renderer) = headless_world();
+        world.tick(16_f64);
+        assert_eq!(renderer.updates(), 1);
+        let main = renderer.commands(1);
+        let expected: Vec<(f64, f64, f64)> = PATH
+            .iter()
+            .map(|(x, y)| (*x as f64 * TILE + 58_f64, *y as f64 * TILE + 58_f64, TILE / 5_f64))
+            .collect();
+        assert_eq!(circles(&main), expected);
+        assert!(main.contains(&DrawCommand::PathCount(14)));
+        // The path dots sit on the tile centers, the corners keep the map
+        assert_eq!(pixel(&renderer, 2 * 116 + 58, 58), PATH_DOT);
+        assert_eq!(pixel(&renderer, 2 * 116 + 2, 116 + 2), GRASS);
+        assert_eq!(pixel(&renderer, 116 + 58, 2 * 116 + 58), WALL);
+    }
+
+    #[test]
+    fn culled_camera_frame() {
+        let (mut world, renderer) = headless_world();
+        world.zoom_camera(1.5, 0_f64, 0_f64);
+        world.tick(16_f64);
+        // Only the 4x3 tiles in view are drawn, the bottom row cut off
+        let size = TILE * 1.5;
+        let drawn: Vec<(f64, f64, f64)> = renderer
+            .commands(0)
+            .iter()
+            .filter_map(|command| match command {
+                DrawCommand::Tile { px, py, size, .. } => Some((*px, *py, *size)),
+                _ => None,
+            })
+            .collect();
+        let expected: Vec<(f64, f64, f64)> = (0..12)
+            .map(|i| ((i % 4) as f64 * size, (i / 4) as f64 * size, size))
+            .collect();
+        assert_eq!(drawn, expected);
+        // Path dots off the canvas are skipped, the rest are zoomed in
+        let expected: Vec<(f64, f64, f64)> = PATH
+            .iter()
+            .filter(|(x, y)| *x < 4 && *y < 3)
+            .map(|(x, y)| {
+                let center = |id: u32| id as f64 * size + size / 2_f64;
+                (center(*x), center(*y), size / 5_f64)
+            })
+            .collect();
+        assert_eq!(expected.len(), 7);
+        assert_eq!(circles(&renderer.commands(1)), expected);
+        assert_eq!(pixel(&renderer, 2 * 174 + 87, 87), PATH_DOT);
+        assert_eq!(pixel(&renderer, 174 + 87, 174 + 87), WALL);
+        assert_eq!(pixel(&renderer, 2 * 174 + 2, 2 * 174 + 2), GRASS);
+        assert_eq!(pixel(&renderer, 695, 463), WALL);
+    }
+}
@@ -0,0 +1,29 @@
+use crate::engine::{Color, FrameStats};
+
+mod canvas;
+mod headless;
+pub use self::canvas::CanvasRenderer;
+pub use self::headless::{DrawCommand, HeadlessRenderer};
+
+// Everything a World puts on screen goes through here. Layers are ids the
+// World picks, drawn bottom to top in id order, positions are canvas pixels
+// (screen size times quality).
+pub trait Renderer {
+    fn create_layer(&mut self, name: &str, layer_id: i32);
+    fn set_screen_size(&mut self, width: u32, height: u32, quality: u32);
+    fn set_layer_size(&mut self, layer_id: i32, width: u32, height: u32, quality: u32);
+    fn clear(&mut self, layer_id: i32);
+    fn draw_tile(&mut self, layer_id: i32, px: f64, py: f64, size: f64, color: &Color);
+    fn draw_circle(&mut self, layer_id: i32, px: f64, py: f64, radius: f64, color: &Color);
+    // Polyline through points with round joins and ends
+    fn draw_line(&mut self, layer_id: i32, points: &[(f64, f64)], width: f64, color: &Color);
+    // Text overlays, laid out by the backend. scores are G, H and F.
+    fn draw_node_scores(&mut self, layer_id: i32, px: f64, py: f64, size: f64, scores: [i32; 3]);
+    fn draw_frame_stats(&mut self, layer_id: i32, stats: &FrameStats);
+    fn draw_path_count(&mut self, layer_id: i32, count: i32);
+    fn draw_search_stats(&mut self, layer_id: i32, nodes_expanded: u32, elapsed_us: f64);
+    // Called once per tick after the world update, before drawing
+    fn update(&mut self) {}
+    // Called at the end of every draw pass, for backends that queue commands
+    fn flush(&mut self) {}
+}
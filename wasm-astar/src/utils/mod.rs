@@ -1,32 +1,64 @@
-use wasm_bindgen::prelude::*;
+mod rng;
+pub use self::rng::Rng;
 
-#[wasm_bindgen]
-extern "C" {
-    #[wasm_bindgen(js_name = "js_random")]
-    fn js_random() -> f32;
-    
-    #[wasm_bindgen(js_name = "js_random_range")]
-    fn js_random_range(min: i32, max: i32) -> i32;
-    
-    #[wasm_bindgen(js_name = "js_log")]
-    fn js_log(msg: &str);
+// The js imports only exist in the browser, native builds (cargo test, the
+// headless renderer) use std instead.
+#[cfg(target_arch = "wasm32")]
+mod js {
+    use wasm_bindgen::prelude::*;
+
+    #[wasm_bindgen]
+    extern "C" {
+        #[wasm_bindgen(js_name = "js_random")]
+        pub fn js_random() -> f32;
+
+        #[wasm_bindgen(js_name = "js_log")]
+        pub fn js_log(msg: &str);
+
+        #[wasm_bindgen(js_name = "js_now")]
+        pub fn js_now() -> f64;
+    }
 }
 
-// TODO: apparently the rand crate now works with wasm.
-// Switch to that!
+// Only used to pick the first map seed, everything after that comes from Rng
+// so maps can be reproduced from their seed.
+#[cfg(target_arch = "wasm32")]
+pub fn random_seed() -> u32 {
+    (js::js_random() as f64 * u32::MAX as f64) as u32
+}
 
-pub fn random_range(min: i32, max: i32) -> i32 {
-    js_random_range(min, max)
+#[cfg(not(target_arch = "wasm32"))]
+pub fn random_seed() -> u32 {
+    use std::time::{SystemTime, UNIX_EPOCH};
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(1, |d| d.subsec_nanos())
 }
 
-pub fn random() -> f32 {
-    js_random()
+// Milliseconds from performance.now(), std::time::Instant panics on wasm32-unknown-unknown.
+#[cfg(target_arch = "wasm32")]
+pub fn now() -> f64 {
+    js::js_now()
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn now() -> f64 {
+    use std::time::{SystemTime, UNIX_EPOCH};
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0_f64, |d| d.as_secs_f64() * 1000_f64)
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn log(msg: &str) {
+    js::js_log(msg);
 }
 
+#[cfg(not(target_arch = "wasm32"))]
 pub fn log(msg: &str) {
-    js_log(msg);
+    println!("{}", msg);
 }
 
 pub fn log_fmt(msg: String) {
-    js_log(&msg);
+    log(&msg);
 }
@@ -0,0 +1,33 @@
+// Mulberry32: tiny, fast and good enough for map generation.
+// Same seed always gives the same sequence, so a map can be reproduced
+// from its seed without going through js Math.random.
+#[derive(Clone)]
+pub struct Rng {
+    state: u32,
+}
+
+impl Rng {
+    pub fn new(seed: u32) -> Rng {
+        Rng { state: seed }
+    }
+
+    pub fn next_u32(&mut self) -> u32 {
+        self.state = self.state.wrapping_add(0x6D2B_79F5);
+        let mut t = self.state;
+        t = (t ^ (t >> 15)).wrapping_mul(t | 1);
+        t ^= t.wrapping_add((t ^ (t >> 7)).wrapping_mul(t | 61));
+        t ^ (t >> 14)
+    }
+
+    // [0, 1)
+    pub fn random(&mut self) -> f32 {
+        // Top 24 bits, all an f32 mantissa can hold
+        (self.next_u32() >> 8) as f32 / (1 << 24) as f32
+    }
+
+    // [min, max], inclusive like the old js_random_range
+    pub fn random_range(&mut self, min: i32, max: i32) -> i32 {
+        let span = (max - min + 1) as u32;
+        min + (self.next_u32() % span) as i32
+    }
+}
@@ -0,0 +1,684 @@
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap, HashSet, VecDeque};
+
+use super::search::OpenNode;
+use super::tile::{Heuristic, Tile, MOVE_COST};
+use crate::utils::Rng;
+
+// Steps each agent plans ahead, everyone replans halfway through (WHCA*).
+const WINDOW: u32 = 16;
+// How long one step takes on screen
+const STEP_MS: f64 = 200_f64;
+const INF: i32 = i32::MAX / 4;
+// Area of a wall tile, walls are in none
+const NO_AREA: u32 = u32::MAX;
+// Goals are picked at most this many tiles away each way, so the search for
+// a goal's distances stays around the agent however big the map is
+const GOAL_RANGE: i32 = 40;
+// Random tiles tried in range before any tile of the agent's area will do
+const GOAL_TRIES: u32 = 32;
+
+// Agents wandering between random goals, planned with windowed hierarchical
+// cooperative A*: each agent searches in space-time (tile, step) in turn and
+// reserves the tiles and moves it takes so the ones after it route around.
+// All agents step at the same time so the reservations line up.
+pub struct Agents {
+    pub agents: Vec<Agent>,
+    // Steps taken since the agents were spawned
+    time: u32,
+    last_step_ms: f64,
+    steps_until_replan: u32,
+    // Rotates who plans first so the same agent doesn't always give way
+    first_to_plan: usize,
+    map_version: u32,
+    rng: Rng,
+    // Open areas of the map at map_version, shared by every agent. None
+    // after an edit until an agent needs them again.
+    areas: Option<Areas>,
+    // Tiles edited since map_version, all_changed when the whole map was
+    changed_ids: Vec<usize>,
+    all_changed: bool,
+}
+
+pub struct Agent {
+    pub tile_id: usize,
+    // Tile the agent is moving away from, for drawing between the two
+    pub prev_tile_id: usize,
+    pub goal_id: usize,
+    // Tiles for the coming steps, front is the next one
+    pub path: VecDeque<usize>,
+    pub hue: u16,
+    // Cost to the goal ignoring other agents, the "hierarchical" heuristic
+    // of the space-time search
+    dist: GoalDistances,
+}
+
+impl Agents {
+    pub fn new() -> Agents {
+        Agents {
+            agents: Vec::new(),
+            time: 0,
+            last_step_ms: 0_f64,
+            steps_until_replan: 0,
+            first_to_plan: 0,
+            map_version: 0,
+            rng: Rng::new(0),
+            areas: None,
+            changed_ids: Vec::new(),
+            all_changed: false,
+        }
+    }
+
+    // Places `count` agents on distinct open tiles, as many as fit.
+    pub fn spawn(&mut self, count: usize, tiles: &[Tile], seed: u32, map_version: u32) {
+        self.rng = Rng::new(seed);
+        self.agents.clear();
+        self.time = 0;
+        self.last_step_ms = 0_f64;
+        self.map_version = map_version;
+        self.changed_ids.clear();
+        self.all_changed = false;
+        let areas = self.areas.insert(Areas::new(tiles));
+        let mut open_ids: Vec<usize> = (0..tiles.len()).filter(|id| !tiles[*id].is_wall()).collect();
+        for i in 0..count.min(open_ids.len()) {
+            let index = self.rng.random_range(0, (open_ids.len() - 1) as i32) as usize;
+            let tile_id = open_ids.swap_remove(index);
+            let mut agent = Agent {
+                tile_id,
+                prev_tile_id: tile_id,
+                goal_id: tile_id,
+                path: VecDeque::new(),
+                // Golden angle apart so neighbors in the list get distinct hues
+                hue: ((i as f64 * 137.5) % 360_f64) as u16,
+                dist: GoalDistances::default(),
+            };
+            agent.pick_goal(tiles, areas, &mut self.rng);
+            self.agents.push(agent);
+        }
+        self.replan(tiles);
+    }
+
+    // changed_ids are tiles whose terrain or side links changed, only the
+    // agents whose goal search reached one of them search again.
+    pub fn mark_changed(&mut self, changed_ids: &[usize]) {
+        if !self.agents.is_empty() && !self.all_changed {
+            self.changed_ids.extend_from_slice(changed_ids);
+        }
+    }
+
+    // Every link may have changed, e.g. with the movement mode
+    pub fn invalidate(&mut self) {
+        self.all_changed = true;
+        self.changed_ids.clear();
+    }
+
+    // Steps every agent along its path once per STEP_MS. Agents that reached
+    // their goal get a new one, and everyone replans when the window runs out
+    // or the map changed under them.
+    pub fn update(&mut self, now_ms: f64, tiles: &[Tile], map_version: u32) {
+        if self.agents.is_empty() {
+            return;
+        }
+        if map_version != self.map_version {
+            self.map_version = map_version;
+            self.areas = None;
+            let changed_ids = std::mem::take(&mut self.changed_ids);
+            let all_changed = std::mem::replace(&mut self.all_changed, false);
+            for agent in self.agents.iter_mut() {
+                // The agent's tile and its way to the goal are inside its
+                // search, an edit nowhere near it leaves both as they were.
+                // A boxed in agent (goal on its own tile) always looks again.
+                let boxed_in = agent.goal_id == agent.tile_id;
+                if !all_changed && !boxed_in && !agent.dist.touches(&changed_ids) {
+                    continue;
+                }
+                let areas = self.areas.get_or_insert_with(|| Areas::new(tiles));
+                if areas.connects(tiles, agent.tile_id, agent.goal_id) {
+                    agent.dist = GoalDistances::new(tiles, agent.goal_id, agent.tile_id, areas);
+                } else {
+                    // Goal walled off or painted over, find another one
+                    agent.pick_goal(tiles, areas, &mut self.rng);
+                }
+            }
+            self.replan(tiles);
+        }
+        // Don't try to catch up after the tab was in the background
+        let since_last_step = now_ms - self.last_step_ms;
+        if self.last_step_ms == 0_f64 || !(0_f64..=STEP_MS * 10_f64).contains(&since_last_step) {
+            self.last_step_ms = now_ms;
+        }
+        while now_ms - self.last_step_ms >= STEP_MS {
+            self.last_step_ms += STEP_MS;
+            self.step(tiles);
+        }
+    }
+
+    // How far between prev_tile_id and tile_id the agents are drawn, 0 to 1.
+    pub fn step_progress(&self, now_ms: f64) -> f64 {
+        ((now_ms - self.last_step_ms) / STEP_MS).clamp(0_f64, 1_f64)
+    }
+
+    fn step(&mut self, tiles: &[Tile]) {
+        self.time += 1;
+        let mut replan = false;
+        for agent in self.agents.iter_mut() {
+            agent.prev_tile_id = agent.tile_id;
+            if let Some(next_id) = agent.path.pop_front() {
+                agent.tile_id = next_id;
+            }
+            if agent.tile_id == agent.goal_id {
+                let areas = self.areas.get_or_insert_with(|| Areas::new(tiles));
+                agent.pick_goal(tiles, areas, &mut self.rng);
+                replan = true;
+            }
+        }
+        self.steps_until_replan = self.steps_until_replan.saturating_sub(1);
+        if replan || self.steps_until_replan == 0 {
+            self.replan(tiles);
+        }
+    }
+
+    // Plans everyone in turn. An agent that finds every option reserved is
+    // moved to the front and the round starts over, so the ones that boxed it
+    // in have to plan around it instead.
+    fn replan(&mut self, tiles: &[Tile]) {
+        let num_agents = self.agents.len();
+        let mut order: Vec<usize> = (0..num_agents)
+            .map(|n| (self.first_to_plan + n) % num_agents)
+            .collect();
+        let mut paths = Vec::new();
+        for _ in 0..num_agents.max(1) {
+            let stuck;
+            (paths, stuck) = self.plan_in_order(tiles, &order);
+            match stuck {
+                Some(i) if order[0] != i => {
+                    order.retain(|j| *j != i);
+                    order.insert(0, i);
+                }
+                _ => break,
+            }
+        }
+        for (agent, path) in self.agents.iter_mut().zip(paths) {
+            agent.path = path.into();
+        }
+        self.first_to_plan = (self.first_to_plan + 1) % num_agents.max(1);
+        self.steps_until_replan = WINDOW / 2;
+    }
+
+    // Paths indexed like self.agents, agents without a plan wait where they
+    // are. Also returns the first agent that had no plan.
+    fn plan_in_order(&mut self, tiles: &[Tile], order: &[usize]) -> (Vec<Vec<usize>>, Option<usize>) {
+        let mut reservations = Reservations::default();
+        // Everyone may wait one step where they stand, so an agent that plans
+        // early can't walk into one that hasn't planned yet.
+        for (i, agent) in self.agents.iter().enumerate() {
+            reservations.tiles.insert((agent.tile_id, self.time + 1), i);
+        }
+        let mut paths = vec![Vec::new(); self.agents.len()];
+        let mut stuck = None;
+        for i in order.iter().copied() {
+            let agent = &mut self.agents[i];
+            let path = plan_window(tiles, agent, i, self.time, &reservations).unwrap_or_else(|| {
+                stuck = stuck.or(Some(i));
+                vec![agent.tile_id; WINDOW as usize]
+            });
+            let mut from_id = agent.tile_id;
+            for (k, id) in path.iter().enumerate() {
+                let t = self.time + 1 + k as u32;
+                reservations.tiles.insert((*id, t), i);
+                reservations.moves.insert((from_id, *id, t));
+                from_id = *id;
+            }
+            paths[i] = path;
+        }
+        (paths, stuck)
+    }
+}
+
+impl Agent {
+    // Random tile reachable from where the agent stands, within GOAL_RANGE
+    // when one turns up there, or stay put when boxed in.
+    fn pick_goal(&mut self, tiles: &[Tile], areas: &Areas, rng: &mut Rng) {
+        let around = areas.around(tiles, self.tile_id);
+        self.goal_id = self.tile_id;
+        let (x_id, y_id) = (tiles[self.tile_id].x_id, tiles[self.tile_id].y_id);
+        for _ in 0..GOAL_TRIES {
+            let x = rng.random_range(
+                (x_id - GOAL_RANGE).max(0),
+                (x_id + GOAL_RANGE).min(areas.num_x_tiles - 1),
+            );
+            let y = rng.random_range(
+                (y_id - GOAL_RANGE).max(0),
+                (y_id + GOAL_RANGE).min(areas.num_y_tiles - 1),
+            );
+            let id = (y * areas.num_x_tiles + x) as usize;
+            if id != self.tile_id && !tiles[id].is_wall() && around.contains(&areas.area[id]) {
+                self.goal_id = id;
+                self.dist = GoalDistances::new(tiles, self.goal_id, self.tile_id, areas);
+                return;
+            }
+        }
+        let count: usize = around.iter().map(|area| areas.members[*area as usize].len()).sum();
+        // The agent's own tile is one of them unless it stands in a wall
+        let others = count - usize::from(!tiles[self.tile_id].is_wall());
+        if others > 0 {
+            let mut index = rng.random_range(0, others as i32 - 1) as usize;
+            for area in around {
+                let members = &areas.members[area as usize];
+                if index < members.len() {
+                    // The last member stands in for the agent's own tile,
+                    // index never gets that far when the agent is in the area
+                    self.goal_id = match members[index] {
+                        id if id == self.tile_id => members[members.len() - 1],
+                        id => id,
+                    };
+                    break;
+                }
+                index -= members.len();
+            }
+        }
+        self.dist = GoalDistances::new(tiles, self.goal_id, self.tile_id, areas);
+    }
+}
+
+// Connected open areas of the map, labeled once per map version so picking
+// or checking a goal doesn't need a flood fill of its own.
+struct Areas {
+    num_x_tiles: i32,
+    num_y_tiles: i32,
+    // Area of each tile, NO_AREA for walls
+    area: Vec<u32>,
+    members: Vec<Vec<usize>>,
+    // Cheapest terrain step on the map, scales the goal distance heuristic
+    min_move_cost: i32,
+}
+
+impl Areas {
+    fn new(tiles: &[Tile]) -> Areas {
+        let mut area = vec![NO_AREA; tiles.len()];
+        let mut members = Vec::new();
+        let mut min_move_cost = INF;
+        let mut queue = VecDeque::new();
+        for id in 0..tiles.len() {
+            if tiles[id].is_wall() || area[id] != NO_AREA {
+                continue;
+            }
+            let label = members.len() as u32;
+            let mut area_members = Vec::new();
+            area[id] = label;
+            queue.push_back(id);
+            while let Some(current) = queue.pop_front() {
+                area_members.push(current);
+                min_move_cost = min_move_cost.min(tiles[current].terrain.move_cost());
+                for s in tiles[current].side_ids().iter().filter(|s| **s >= 0) {
+                    if area[*s as usize] == NO_AREA {
+                        area[*s as usize] = label;
+                        queue.push_back(*s as usize);
+                    }
+                }
+            }
+            members.push(area_members);
+        }
+        // Tiles are stored row by row, the last one is the far corner
+        let (num_x_tiles, num_y_tiles) = tiles.last().map_or((0, 0), |t| (t.x_id + 1, t.y_id + 1));
+        Areas {
+            num_x_tiles,
+            num_y_tiles,
+            area,
+            members,
+            min_move_cost: if min_move_cost < INF { min_move_cost } else { MOVE_COST },
+        }
+    }
+
+    // Areas an agent on id can walk into: its own, or when the tile was
+    // painted over with a wall, the ones of the tiles it can step out onto.
+    fn around(&self, tiles: &[Tile], id: usize) -> Vec<u32> {
+        if !tiles[id].is_wall() {
+            return vec![self.area[id]];
+        }
+        let mut around = Vec::new();
+        for s in tiles[id].side_ids().iter().filter(|s| **s >= 0) {
+            let area = self.area[*s as usize];
+            if !around.contains(&area) {
+                around.push(area);
+            }
+        }
+        around
+    }
+
+    fn connects(&self, tiles: &[Tile], from_id: usize, to_id: usize) -> bool {
+        !tiles[to_id].is_wall() && self.around(tiles, from_id).contains(&self.area[to_id])
+    }
+}
+
+// Costs of walking from tiles to the goal, found with an A* run backwards
+// from the goal towards where the agent stood when it got the goal (reverse
+// resumable A*, as in WHCA*). The search only runs until the tile asked
+// about is closed and picks up from there on the next question, so it
+// covers the tiles around the agent's route instead of the whole map.
+#[derive(Default)]
+struct GoalDistances {
+    target_id: usize,
+    min_move_cost: i32,
+    // Costs found so far, final once the tile is closed
+    g: HashMap<usize, i32>,
+    closed: HashSet<usize>,
+    open_nodes: BinaryHeap<OpenNode>,
+}
+
+impl GoalDistances {
+    fn new(tiles: &[Tile], goal_id: usize, target_id: usize, areas: &Areas) -> GoalDistances {
+        let mut dist = GoalDistances {
+            target_id,
+            min_move_cost: areas.min_move_cost,
+            ..GoalDistances::default()
+        };
+        // A walled goal has no costs, the agent picks another one
+        if !tiles[goal_id].is_wall() {
+            let h = dist.calc_h(tiles, goal_id);
+            dist.g.insert(goal_id, 0);
+            dist.open_nodes.push(OpenNode { f: h, h, id: goal_id });
+        }
+        dist
+    }
+
+    // Whether the search reached any of ids, its costs may be stale if so
+    fn touches(&self, ids: &[usize]) -> bool {
+        ids.iter().any(|id| self.g.contains_key(id))
+    }
+
+    // Cost from id to the goal, INF when there is no way
+    fn get(&mut self, tiles: &[Tile], id: usize) -> i32 {
+        // Nothing steps onto a wall, so searching for one would only flood
+        // the whole area
+        if tiles[id].is_wall() {
+            return INF;
+        }
+        while !self.closed.contains(&id) {
+            let Some(open_node) = self.open_nodes.pop() else {
+                return INF;
+            };
+            let current = open_node.id;
+            if !self.closed.insert(current) {
+                continue;
+            }
+            let cost = self.g[&current];
+            // Side links are symmetric between open tiles so a tile's sides
+            // are also the tiles that can step onto it.
+            for s in tiles[current].side_ids().iter().filter(|s| **s >= 0) {
+                let side_id = *s as usize;
+                let new_cost = cost + tiles[side_id].move_cost_to(&tiles[current]);
+                if self.g.get(&side_id).is_some_and(|g| *g <= new_cost) {
+                    continue;
+                }
+                self.g.insert(side_id, new_cost);
+                let h = self.calc_h(tiles, side_id);
+                self.open_nodes.push(OpenNode {
+                    f: new_cost + h,
+                    h,
+                    id: side_id,
+                });
+            }
+        }
+        self.g[&id]
+    }
+
+    // Octile distance on the cheapest terrain, a lower bound in every
+    // movement mode that never drops by more than a step costs
+    fn calc_h(&self, tiles: &[Tile], id: usize) -> i32 {
+        let h = tiles[id].calc_h(&tiles[self.target_id], Heuristic::Octile);
+        h * self.min_move_cost / MOVE_COST
+    }
+}
+
+#[derive(Default)]
+struct Reservations {
+    // (tile, time) -> agent index
+    tiles: HashMap<(usize, u32), usize>,
+    // (from, to, time) moves, an agent can't take the same edge the other way
+    // at the same time (swapping places)
+    moves: HashSet<(usize, usize, u32)>,
+}
+
+impl Reservations {
+    fn is_free(&self, agent_index: usize, from_id: usize, to_id: usize, t: u32) -> bool {
+        let tile_free = match self.tiles.get(&(to_id, t)) {
+            Some(owner) => *owner == agent_index,
+            None => true,
+        };
+        tile_free && (from_id == to_id || !self.moves.contains(&(to_id, from_id, t)))
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+struct SpaceTimeNode {
+    f: i32,
+    h: i32,
+    id: usize,
+    step: u32,
+}
+
+impl Ord for SpaceTimeNode {
+    // Reversed for BinaryHeap, lowest F then lowest H first
+    fn cmp(&self, other: &SpaceTimeNode) -> Ordering {
+        other
+            .f
+            .cmp(&self.f)
+            .then_with(|| other.h.cmp(&self.h))
+            .then_with(|| other.step.cmp(&self.step))
+            .then_with(|| other.id.cmp(&self.id))
+    }
+}
+
+impl PartialOrd for SpaceTimeNode {
+    fn partial_cmp(&self, other: &SpaceTimeNode) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+// Space-time A* over the next WINDOW steps. Waiting costs a grass step
+// except on the goal, the cost to go past the window is the agent's goal
+// distance. Returns one tile per step, None when every option is reserved.
+fn plan_window(
+    tiles: &[Tile],
+    agent: &mut Agent,
+    agent_index: usize,
+    time: u32,
+    reservations: &Reservations,
+) -> Option<Vec<usize>> {
+    let dist = &mut agent.dist;
+    let mut h = |id: usize| dist.get(tiles, id);
+    let mut open_nodes = BinaryHeap::new();
+    let mut g: HashMap<(usize, u32), i32> = HashMap::new();
+    let mut parent: HashMap<(usize, u32), (usize, u32)> = HashMap::new();
+    g.insert((agent.tile_id, 0), 0);
+    open_nodes.push(SpaceTimeNode {
+        f: h(agent.tile_id),
+        h: h(agent.tile_id),
+        id: agent.tile_id,
+        step: 0,
+    });
+
+    while let Some(node) = open_nodes.pop() {
+        let node_g = g[&(node.id, node.step)];
+        if node.f > node_g + h(node.id) {
+            continue;
+        }
+        // Done at the end of the window, or at the goal if nobody needs it later
+        let goal_free = node.id == agent.goal_id
+            && (node.step + 1..=WINDOW)
+                .all(|k| reservations.is_free(agent_index, node.id, node.id, time + k));
+        if node.step == WINDOW || goal_free {
+            let mut path = vec![node.id; (WINDOW - node.step) as usize];
+            let mut key = (node.id, node.step);
+            while let Some(prev) = parent.get(&key) {
+                path.push(key.0);
+                key = *prev;
+            }
+            path.reverse();
+            return Some(path);
+        }
+
+        let t = time + node.step + 1;
+        let wait_cost = if node.id == agent.goal_id { 0 } else { MOVE_COST };
+        let moves = tiles[node.id]
+            .side_ids()
+            .iter()
+            .filter(|s| **s >= 0)
+            .map(|s| (*s as usize, tiles[node.id].move_cost_to(&tiles[*s as usize])))
+            .chain(std::iter::once((node.id, wait_cost)))
+            .collect::<Vec<(usize, i32)>>();
+        for (id, cost) in moves {
+            if !reservations.is_free(agent_index, node.id, id, t) {
+                continue;
+            }
+            let key = (id, node.step + 1);
+            let new_g = node_g + cost;
+            if g.get(&key).is_some_and(|old_g| *old_g <= new_g) {
+                continue;
+            }
+            g.insert(key, new_g);
+            parent.insert(key, (node.id, node.step));
+            open_nodes.push(SpaceTimeNode {
+                f: new_g + h(id),
+                h: h(id),
+                id,
+                step: node.step + 1,
+            });
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cmp::Reverse;
+
+    use super::*;
+    use crate::world::WorldState;
+
+    fn seeded_world(seed: u32, num_x_tiles: u32, num_y_tiles: u32) -> WorldState {
+        let mut world = WorldState::new();
+        world.map_size = Some((num_x_tiles, num_y_tiles));
+        world.load_seeded_map(seed);
+        world
+    }
+
+    // Cost of walking from every tile to goal_id, INF where there is no way
+    fn dijkstra(tiles: &[Tile], goal_id: usize) -> Vec<i32> {
+        let mut cost = vec![INF; tiles.len()];
+        let mut open_nodes = BinaryHeap::new();
+        cost[goal_id] = 0;
+        open_nodes.push(Reverse((0, goal_id)));
+        while let Some(Reverse((c, id))) = open_nodes.pop() {
+            if c > cost[id] {
+                continue;
+            }
+            for s in tiles[id].side_ids().iter().filter(|s| **s >= 0) {
+                let side_id = *s as usize;
+                let new_cost = c + tiles[side_id].move_cost_to(&tiles[id]);
+                if new_cost < cost[side_id] {
+                    cost[side_id] = new_cost;
+                    open_nodes.push(Reverse((new_cost, side_id)));
+                }
+            }
+        }
+        cost
+    }
+
+    #[test]
+    fn goal_distances_match_dijkstra() {
+        for seed in 0..20 {
+            let world = seeded_world(seed, 60, 40);
+            let tiles = &world.tiles;
+            let areas = Areas::new(tiles);
+            let (goal_id, target_id) = (world.end_id as usize, world.start_id as usize);
+            let exact = dijkstra(tiles, goal_id);
+            let mut dist = GoalDistances::new(tiles, goal_id, target_id, &areas);
+            assert_eq!(dist.get(tiles, target_id), exact[target_id], "seed {}", seed);
+            // Asking about tiles off the first search's route resumes it
+            for id in (0..tiles.len()).rev() {
+                assert_eq!(dist.get(tiles, id), exact[id], "seed {} tile {}", seed, id);
+            }
+        }
+    }
+
+    #[test]
+    fn goals_are_reachable_and_in_range() {
+        let mut world = seeded_world(3, 300, 300);
+        world.set_agent_count(20);
+        let areas = Areas::new(&world.tiles);
+        assert_eq!(world.agents.agents.len(), 20);
+        for agent in world.agents.agents.iter() {
+            let (tile, goal) = (&world.tiles[agent.tile_id], &world.tiles[agent.goal_id]);
+            assert_ne!(agent.goal_id, agent.tile_id);
+            assert!(areas.connects(&world.tiles, agent.tile_id, agent.goal_id));
+            assert!((tile.x_id - goal.x_id).abs() <= GOAL_RANGE);
+            assert!((tile.y_id - goal.y_id).abs() <= GOAL_RANGE);
+        }
+    }
+
+    #[test]
+    fn edits_only_restart_the_searches_they_touch() {
+        let mut world = seeded_world(5, 300, 300);
+        world.set_agent_count(20);
+        world.calc_path();
+        world.update_agents(1000_f64);
+        // Ask about the open tiles around each agent too, more than a replan
+        // needs, so a search that started over would be missing some
+        let tiles = &world.tiles;
+        let areas = Areas::new(tiles);
+        let searched: Vec<HashSet<usize>> = world
+            .agents
+            .agents
+            .iter_mut()
+            .map(|agent| {
+                let (x_id, y_id) = (tiles[agent.tile_id].x_id, tiles[agent.tile_id].y_id);
+                for id in (0..tiles.len()).filter(|id| {
+                    (tiles[*id].x_id - x_id).abs() <= 8
+                        && (tiles[*id].y_id - y_id).abs() <= 8
+                        && areas.connects(tiles, agent.tile_id, *id)
+                }) {
+                    agent.dist.get(tiles, id);
+                }
+                agent.dist.g.keys().copied().collect()
+            })
+            .collect();
+        // Wall over an open tile the first agent's search reached
+        let edited_id = searched[0]
+            .iter()
+            .copied()
+            .filter(|id| {
+                *id != world.end_id as usize
+                    && !tiles[*id].is_wall()
+                    && !world.agents.agents.iter().any(|agent| agent.tile_id == *id)
+            })
+            .min()
+            .unwrap();
+        let tile = &tiles[edited_id];
+        let block: Vec<usize> = (0..tiles.len())
+            .filter(|id| {
+                (tiles[*id].x_id - tile.x_id).abs() <= 1 && (tiles[*id].y_id - tile.y_id).abs() <= 1
+            })
+            .collect();
+        let size = world.tile_size as f64;
+        let (x, y) = (tile.x_id as f64 * size + 1_f64, tile.y_id as f64 * size + 1_f64);
+        assert_eq!(world.begin_edit(x, y), Some(edited_id));
+        world.end_edit();
+        world.calc_path();
+        world.update_agents(1000_f64);
+
+        let tiles = &world.tiles;
+        let mut kept = 0;
+        for (i, agent) in world.agents.agents.iter_mut().enumerate() {
+            if block.iter().all(|id| !searched[i].contains(id)) {
+                // Kept its search instead of starting over
+                assert!(searched[i].iter().all(|id| agent.dist.g.contains_key(id)), "agent {}", i);
+                kept += 1;
+            }
+            let exact = dijkstra(tiles, agent.goal_id);
+            assert_eq!(agent.dist.get(tiles, agent.tile_id), exact[agent.tile_id], "agent {}", i);
+        }
+        assert!(kept > 10, "only {} searches were kept", kept);
+    }
+}
@@ -0,0 +1,437 @@
+use std::collections::VecDeque;
+
+use super::tile::Terrain;
+use crate::utils::Rng;
+
+// Maps to the ids passed to set_map_generator on the client side.
+// Every generator leaves the open tiles in one 4-connected region (or picks
+// its targets inside one), so start and end are always reachable without
+// regenerating until a path is found.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Generator {
+    // The original 30% wall noise
+    Noise = 0,
+    RecursiveBacktracker = 1,
+    Prim = 2,
+    Caves = 3,
+    Rooms = 4,
+}
+
+impl Generator {
+    pub fn from_id(id: u32) -> Option<Generator> {
+        match id {
+            0 => Some(Generator::Noise),
+            1 => Some(Generator::RecursiveBacktracker),
+            2 => Some(Generator::Prim),
+            3 => Some(Generator::Caves),
+            4 => Some(Generator::Rooms),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Generator::Noise => "Noise",
+            Generator::RecursiveBacktracker => "Recursive backtracker",
+            Generator::Prim => "Prim",
+            Generator::Caves => "Cellular caves",
+            Generator::Rooms => "Rooms and corridors",
+        }
+    }
+
+    pub fn generate(self, rng: &mut Rng, num_x_tiles: u32, num_y_tiles: u32) -> GeneratedMap {
+        let mut grid = Grid::new(num_x_tiles as i32, num_y_tiles as i32);
+        match self {
+            Generator::Noise => noise(&mut grid, rng),
+            Generator::RecursiveBacktracker => recursive_backtracker(&mut grid, rng),
+            Generator::Prim => prim(&mut grid, rng),
+            Generator::Caves => caves(&mut grid, rng),
+            Generator::Rooms => rooms(&mut grid, rng),
+        }
+        let (start_id, end_id) = pick_targets(&mut grid, rng);
+        GeneratedMap {
+            terrain: grid.terrain,
+            start_id,
+            end_id,
+        }
+    }
+}
+
+pub struct GeneratedMap {
+    pub terrain: Vec<Terrain>,
+    pub start_id: usize,
+    pub end_id: usize,
+}
+
+struct Grid {
+    num_x_tiles: i32,
+    num_y_tiles: i32,
+    terrain: Vec<Terrain>,
+}
+
+impl Grid {
+    fn new(num_x_tiles: i32, num_y_tiles: i32) -> Grid {
+        Grid {
+            num_x_tiles,
+            num_y_tiles,
+            terrain: vec![Terrain::Wall; (num_x_tiles * num_y_tiles) as usize],
+        }
+    }
+
+    fn contains(&self, x: i32, y: i32) -> bool {
+        x >= 0 && y >= 0 && x < self.num_x_tiles && y < self.num_y_tiles
+    }
+
+    fn id(&self, x: i32, y: i32) -> usize {
+        (y * self.num_x_tiles + x) as usize
+    }
+
+    fn is_wall(&self, x: i32, y: i32) -> bool {
+        !self.contains(x, y) || self.terrain[self.id(x, y)] == Terrain::Wall
+    }
+
+    fn set(&mut self, x: i32, y: i32, terrain: Terrain) {
+        let id = self.id(x, y);
+        self.terrain[id] = terrain;
+    }
+}
+
+const CARDINAL_DIRS: [(i32, i32); 4] = [(0, -1), (0, 1), (1, 0), (-1, 0)];
+
+// Terrain for a tile that isn't a wall, same mix of road/grass/swamp as the noise maps.
+fn open_terrain(rng: &mut Rng) -> Terrain {
+    let r = rng.random();
+    if r >= 0.83 {
+        Terrain::Swamp
+    } else if r < 0.17 {
+        Terrain::Road
+    } else {
+        Terrain::Grass
+    }
+}
+
+// Same 30% walls as always, the open tiles are mostly grass with some roads and swamp.
+fn noise(grid: &mut Grid, rng: &mut Rng) {
+    for t in grid.terrain.iter_mut() {
+        let r = rng.random();
+        *t = if r >= 0.7 {
+            Terrain::Wall
+        } else if r >= 0.58 {
+            Terrain::Swamp
+        } else if r < 0.12 {
+            Terrain::Road
+        } else {
+            Terrain::Grass
+        };
+    }
+}
+
+// Maze cells sit on even x/y, the odd rows and columns between them are the
+// walls that get knocked out to join two cells.
+fn maze_cells(grid: &Grid) -> (i32, i32) {
+    ((grid.num_x_tiles + 1) / 2, (grid.num_y_tiles + 1) / 2)
+}
+
+fn carve_passage(grid: &mut Grid, rng: &mut Rng, cx: i32, cy: i32, dx: i32, dy: i32) {
+    let t = open_terrain(rng);
+    grid.set(cx * 2 + dx, cy * 2 + dy, t);
+    let t = open_terrain(rng);
+    grid.set((cx + dx) * 2, (cy + dy) * 2, t);
+}
+
+// Depth-first walk with an explicit stack, long winding corridors.
+fn recursive_backtracker(grid: &mut Grid, rng: &mut Rng) {
+    let (num_x_cells, num_y_cells) = maze_cells(grid);
+    let mut visited = vec![false; (num_x_cells * num_y_cells) as usize];
+    let cx = rng.random_range(0, num_x_cells - 1);
+    let cy = rng.random_range(0, num_y_cells - 1);
+    let mut stack = vec![(cx, cy)];
+    visited[(cy * num_x_cells + cx) as usize] = true;
+    let t = open_terrain(rng);
+    grid.set(cx * 2, cy * 2, t);
+
+    while let Some(&(cx, cy)) = stack.last() {
+        let unvisited: Vec<(i32, i32)> = CARDINAL_DIRS
+            .iter()
+            .copied()
+            .filter(|(dx, dy)| {
+                let (nx, ny) = (cx + dx, cy + dy);
+                nx >= 0
+                    && ny >= 0
+                    && nx < num_x_cells
+                    && ny < num_y_cells
+                    && !visited[(ny * num_x_cells + nx) as usize]
+            })
+            .collect();
+        if unvisited.is_empty() {
+            stack.pop();
+            continue;
+        }
+        let (dx, dy) = unvisited[rng.random_range(0, unvisited.len() as i32 - 1) as usize];
+        carve_passage(grid, rng, cx, cy, dx, dy);
+        visited[((cy + dy) * num_x_cells + cx + dx) as usize] = true;
+        stack.push((cx + dx, cy + dy));
+    }
+}
+
+// Randomized Prim: grows from one cell by joining a random frontier cell to
+// the maze each step, lots of short dead ends.
+fn prim(grid: &mut Grid, rng: &mut Rng) {
+    let (num_x_cells, num_y_cells) = maze_cells(grid);
+    let mut in_maze = vec![false; (num_x_cells * num_y_cells) as usize];
+    let mut frontier = Vec::new();
+    let cx = rng.random_range(0, num_x_cells - 1);
+    let cy = rng.random_range(0, num_y_cells - 1);
+    in_maze[(cy * num_x_cells + cx) as usize] = true;
+    let t = open_terrain(rng);
+    grid.set(cx * 2, cy * 2, t);
+    frontier.push((cx, cy));
+
+    // Frontier holds maze cells that may still have neighbors outside the maze
+    while !frontier.is_empty() {
+        let index = rng.random_range(0, frontier.len() as i32 - 1) as usize;
+        let (cx, cy) = frontier[index];
+        let outside: Vec<(i32, i32)> = CARDINAL_DIRS
+            .iter()
+            .copied()
+            .filter(|(dx, dy)| {
+                let (nx, ny) = (cx + dx, cy + dy);
+                nx >= 0
+                    && ny >= 0
+                    && nx < num_x_cells
+                    && ny < num_y_cells
+                    && !in_maze[(ny * num_x_cells + nx) as usize]
+            })
+            .collect();
+        if outside.is_empty() {
+            frontier.swap_remove(index);
+            continue;
+        }
+        let (dx, dy) = outside[rng.random_range(0, outside.len() as i32 - 1) as usize];
+        carve_passage(grid, rng, cx, cy, dx, dy);
+        in_maze[((cy + dy) * num_x_cells + cx + dx) as usize] = true;
+        frontier.push((cx + dx, cy + dy));
+    }
+}
+
+// Cellular automaton smoothing of 45% noise, then every cave but the
+// largest one is filled in.
+fn caves(grid: &mut Grid, rng: &mut Rng) {
+    for t in grid.terrain.iter_mut() {
+        *t = if rng.random() < 0.45 {
+            Terrain::Wall
+        } else {
+            Terrain::Grass
+        };
+    }
+    for _ in 0..5 {
+        let mut next = grid.terrain.clone();
+        for y in 0..grid.num_y_tiles {
+            for x in 0..grid.num_x_tiles {
+                let mut walls = 0;
+                for ny in y - 1..=y + 1 {
+                    for nx in x - 1..=x + 1 {
+                        if grid.is_wall(nx, ny) {
+                            walls += 1;
+                        }
+                    }
+                }
+                next[grid.id(x, y)] = if walls >= 5 {
+                    Terrain::Wall
+                } else {
+                    Terrain::Grass
+                };
+            }
+        }
+        grid.terrain = next;
+    }
+
+    let region = largest_region(grid);
+    for (t, in_region) in grid.terrain.iter_mut().zip(region) {
+        *t = if in_region {
+            open_terrain(rng)
+        } else {
+            Terrain::Wall
+        };
+    }
+}
+
+// Rectangular rooms joined in the order they were placed by L shaped road
+// corridors, so each room is connected to the one before it.
+fn rooms(grid: &mut Grid, rng: &mut Rng) {
+    let max_size = (grid.num_x_tiles.min(grid.num_y_tiles) / 4).max(3);
+    let attempts = (grid.num_x_tiles * grid.num_y_tiles / 40).max(1);
+    let mut centers: Vec<(i32, i32)> = Vec::new();
+    let mut placed: Vec<(i32, i32, i32, i32)> = Vec::new();
+
+    for _ in 0..attempts {
+        let w = rng.random_range(3, max_size).min(grid.num_x_tiles);
+        let h = rng.random_range(3, max_size).min(grid.num_y_tiles);
+        let x = rng.random_range(0, grid.num_x_tiles - w);
+        let y = rng.random_range(0, grid.num_y_tiles - h);
+        // Keep a wall between rooms so they read as separate rooms
+        let overlaps = placed
+            .iter()
+            .any(|&(px, py, pw, ph)| x <= px + pw && px <= x + w && y <= py + ph && py <= y + h);
+        if overlaps {
+            continue;
+        }
+        placed.push((x, y, w, h));
+        for ry in y..y + h {
+            for rx in x..x + w {
+                let t = open_terrain(rng);
+                grid.set(rx, ry, t);
+            }
+        }
+
+        let center = (x + w / 2, y + h / 2);
+        if let Some(&(px, py)) = centers.last() {
+            let (cx, cy) = center;
+            if rng.random() < 0.5 {
+                carve_corridor(grid, px, cx, py, true);
+                carve_corridor(grid, py, cy, cx, false);
+            } else {
+                carve_corridor(grid, py, cy, px, false);
+                carve_corridor(grid, px, cx, cy, true);
+            }
+        }
+        centers.push(center);
+    }
+}
+
+// Straight run of road from `from` to `to` along x (horizontal) or y, at `at` on the other axis.
+// Tiles already open keep their terrain so rooms stay as they are.
+fn carve_corridor(grid: &mut Grid, from: i32, to: i32, at: i32, horizontal: bool) {
+    for i in from.min(to)..=from.max(to) {
+        let (x, y) = if horizontal { (i, at) } else { (at, i) };
+        if grid.is_wall(x, y) {
+            grid.set(x, y, Terrain::Road);
+        }
+    }
+}
+
+// Tiles in the largest 4-connected region of open tiles, none if every tile is a wall.
+fn largest_region(grid: &Grid) -> Vec<bool> {
+    let mut region_of = vec![usize::MAX; grid.terrain.len()];
+    let mut best_region = None;
+    let mut best_size = 0;
+    let mut queue = VecDeque::new();
+
+    for seed in 0..grid.terrain.len() {
+        if grid.terrain[seed] == Terrain::Wall || region_of[seed] != usize::MAX {
+            continue;
+        }
+        region_of[seed] = seed;
+        queue.push_back(seed);
+        let mut size = 0;
+        while let Some(id) = queue.pop_front() {
+            size += 1;
+            let x = id as i32 % grid.num_x_tiles;
+            let y = id as i32 / grid.num_x_tiles;
+            for (dx, dy) in CARDINAL_DIRS {
+                if grid.is_wall(x + dx, y + dy) {
+                    continue;
+                }
+                let n = grid.id(x + dx, y + dy);
+                if region_of[n] == usize::MAX {
+                    region_of[n] = seed;
+                    queue.push_back(n);
+                }
+            }
+        }
+        if size > best_size {
+            best_size = size;
+            best_region = Some(seed);
+        }
+    }
+    // Walls keep usize::MAX, so they must not be compared against a missing region
+    region_of.iter().map(|r| Some(*r) == best_region).collect()
+}
+
+// Start and end are two different tiles of the largest open region, which
+// every movement mode can cross since they all allow cardinal steps.
+fn pick_targets(grid: &mut Grid, rng: &mut Rng) -> (usize, usize) {
+    let region: Vec<usize> = largest_region(grid)
+        .iter()
+        .enumerate()
+        .filter(|(_, in_region)| **in_region)
+        .map(|(id, _)| id)
+        .collect();
+    match region.len() {
+        // Solid walls, open a tile to stand on
+        0 => {
+            grid.terrain[0] = Terrain::Grass;
+            (0, 0)
+        }
+        1 => (region[0], region[0]),
+        len => {
+            let start = rng.random_range(0, len as i32 - 1) as usize;
+            let mut end = rng.random_range(0, len as i32 - 2) as usize;
+            if end >= start {
+                end += 1;
+            }
+            (region[start], region[end])
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GENERATORS: [Generator; 5] = [
+        Generator::Noise,
+        Generator::RecursiveBacktracker,
+        Generator::Prim,
+        Generator::Caves,
+        Generator::Rooms,
+    ];
+
+    // Whether end can be reached from start over open tiles with cardinal steps
+    fn connected(map: &GeneratedMap, num_x_tiles: i32, num_y_tiles: i32) -> bool {
+        let mut grid = Grid::new(num_x_tiles, num_y_tiles);
+        grid.terrain = map.terrain.clone();
+        let mut seen = vec![false; grid.terrain.len()];
+        let mut queue = VecDeque::from([map.start_id]);
+        seen[map.start_id] = true;
+        while let Some(id) = queue.pop_front() {
+            if id == map.end_id {
+                return true;
+            }
+            let x = id as i32 % num_x_tiles;
+            let y = id as i32 / num_x_tiles;
+            for (dx, dy) in CARDINAL_DIRS {
+                if !grid.is_wall(x + dx, y + dy) && !seen[grid.id(x + dx, y + dy)] {
+                    seen[grid.id(x + dx, y + dy)] = true;
+                    queue.push_back(grid.id(x + dx, y + dy));
+                }
+            }
+        }
+        false
+    }
+
+    #[test]
+    fn targets_are_open_and_connected() {
+        let sizes = [(1, 1), (2, 1), (1, 2), (2, 2), (3, 1), (3, 3), (5, 4), (10, 7), (24, 16)];
+        for generator in GENERATORS {
+            for (num_x_tiles, num_y_tiles) in sizes {
+                for seed in 0..200 {
+                    let mut rng = Rng::new(seed);
+                    let map = generator.generate(&mut rng, num_x_tiles, num_y_tiles);
+                    let at =
+                        format!("{} {}x{} seed {}", generator.name(), num_x_tiles, num_y_tiles, seed);
+                    assert_eq!(map.terrain.len(), (num_x_tiles * num_y_tiles) as usize, "{}", at);
+                    assert!(map.terrain[map.start_id] != Terrain::Wall, "{}", at);
+                    assert!(map.terrain[map.end_id] != Terrain::Wall, "{}", at);
+                    assert!(connected(&map, num_x_tiles as i32, num_y_tiles as i32), "{}", at);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn all_walls_have_no_region() {
+        let grid = Grid::new(3, 2);
+        assert!(largest_region(&grid).iter().all(|in_region| !in_region));
+    }
+}
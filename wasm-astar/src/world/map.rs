@@ -0,0 +1,235 @@
+use std::fmt;
+
+use super::tile::Terrain;
+
+// Text map format, one line per row of tiles:
+//
+//   start 3,4
+//   end 10,2
+//   0,0,1,2,3
+//   0,1,1,0,0
+//
+// Terrain codes are Terrain ids (0 grass, 1 wall, 2 road, 3 swamp) so the
+// old comma separated 0/1 maps still load, trailing commas included.
+// start/end are x,y tile positions and must be on the map, off the walls.
+pub struct MapData {
+    pub num_x_tiles: u32,
+    pub num_y_tiles: u32,
+    pub terrain: Vec<Terrain>,
+    pub start: (u32, u32),
+    pub end: (u32, u32),
+}
+
+// Rows are counted from 1 like a text editor, they refer to the tile rows
+// (header lines and blank lines are not counted).
+#[derive(Debug, PartialEq)]
+pub enum MapError {
+    Empty,
+    MissingTarget(&'static str),
+    InvalidTarget(&'static str, String),
+    TargetOutOfRange(&'static str, u32, u32),
+    TargetOnWall(&'static str, u32, u32),
+    UnknownTerrain { row: usize, col: usize, code: String },
+    RaggedRow { row: usize, expected: usize, found: usize },
+}
+
+impl fmt::Display for MapError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MapError::Empty => write!(f, "map has no tile rows"),
+            MapError::MissingTarget(name) => write!(f, "missing '{} x,y' line", name),
+            MapError::InvalidTarget(name, value) => {
+                write!(f, "invalid {} position '{}', expected x,y", name, value)
+            }
+            MapError::TargetOutOfRange(name, x, y) => {
+                write!(f, "{} position {},{} is outside the map", name, x, y)
+            }
+            MapError::TargetOnWall(name, x, y) => {
+                write!(f, "{} position {},{} is a wall", name, x, y)
+            }
+            MapError::UnknownTerrain { row, col, code } => {
+                write!(f, "unknown terrain '{}' at row {}, column {}", code, row, col)
+            }
+            MapError::RaggedRow {
+                row,
+                expected,
+                found,
+            } => write!(
+                f,
+                "row {} has {} tiles, expected {} like the first row",
+                row, found, expected
+            ),
+        }
+    }
+}
+
+pub fn parse_map(text: &str) -> Result<MapData, MapError> {
+    let mut start = None;
+    let mut end = None;
+    let mut terrain = Vec::new();
+    let mut num_x_tiles = 0;
+    let mut num_y_tiles = 0;
+
+    for line in text.lines().map(str::trim).filter(|l| !l.is_empty()) {
+        if let Some(value) = line.strip_prefix("start") {
+            start = Some(parse_target("start", value)?);
+            continue;
+        }
+        if let Some(value) = line.strip_prefix("end") {
+            end = Some(parse_target("end", value)?);
+            continue;
+        }
+
+        num_y_tiles += 1;
+        let codes: Vec<&str> = line.split_terminator(',').map(str::trim).collect();
+        if num_y_tiles == 1 {
+            num_x_tiles = codes.len();
+        } else if codes.len() != num_x_tiles {
+            return Err(MapError::RaggedRow {
+                row: num_y_tiles,
+                expected: num_x_tiles,
+                found: codes.len(),
+            });
+        }
+        for (x, code) in codes.iter().enumerate() {
+            let t = code.parse().ok().and_then(Terrain::from_id);
+            terrain.push(t.ok_or_else(|| MapError::UnknownTerrain {
+                row: num_y_tiles,
+                col: x + 1,
+                code: code.to_string(),
+            })?);
+        }
+    }
+
+    if terrain.is_empty() {
+        return Err(MapError::Empty);
+    }
+    let start = start.ok_or(MapError::MissingTarget("start"))?;
+    let end = end.ok_or(MapError::MissingTarget("end"))?;
+    let num_x_tiles = num_x_tiles as u32;
+    let num_y_tiles = num_y_tiles as u32;
+    for (name, (x, y)) in [("start", start), ("end", end)] {
+        if x >= num_x_tiles || y >= num_y_tiles {
+            return Err(MapError::TargetOutOfRange(name, x, y));
+        }
+        if terrain[(y * num_x_tiles + x) as usize] == Terrain::Wall {
+            return Err(MapError::TargetOnWall(name, x, y));
+        }
+    }
+    Ok(MapData {
+        num_x_tiles,
+        num_y_tiles,
+        terrain,
+        start,
+        end,
+    })
+}
+
+fn parse_target(name: &'static str, value: &str) -> Result<(u32, u32), MapError> {
+    let invalid = || MapError::InvalidTarget(name, value.trim().to_string());
+    let (x, y) = value.trim().split_once(',').ok_or_else(invalid)?;
+    let x = x.trim().parse().map_err(|_| invalid())?;
+    let y = y.trim().parse().map_err(|_| invalid())?;
+    Ok((x, y))
+}
+
+pub fn format_map(map: &MapData) -> String {
+    let mut text = format!(
+        "start {},{}\nend {},{}\n",
+        map.start.0, map.start.1, map.end.0, map.end.1
+    );
+    for row in map.terrain.chunks(map.num_x_tiles as usize) {
+        let codes: Vec<String> = row.iter().map(|t| (*t as u32).to_string()).collect();
+        text.push_str(&codes.join(","));
+        text.push('\n');
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ROWS: &str = "0,1,2\n3,0,0\n";
+
+    #[test]
+    fn rejects_an_empty_map() {
+        assert_eq!(parse_map("start 0,0\nend 1,0\n\n").err(), Some(MapError::Empty));
+    }
+
+    #[test]
+    fn rejects_missing_targets() {
+        let no_start = format!("end 1,0\n{}", ROWS);
+        assert_eq!(parse_map(&no_start).err(), Some(MapError::MissingTarget("start")));
+        let no_end = format!("start 0,0\n{}", ROWS);
+        assert_eq!(parse_map(&no_end).err(), Some(MapError::MissingTarget("end")));
+    }
+
+    #[test]
+    fn rejects_invalid_targets() {
+        let text = format!("start 0;0\nend 1,0\n{}", ROWS);
+        let error = MapError::InvalidTarget("start", "0;0".to_string());
+        assert_eq!(parse_map(&text).err(), Some(error));
+        let text = format!("start 0,0\nend 1,x\n{}", ROWS);
+        let error = MapError::InvalidTarget("end", "1,x".to_string());
+        assert_eq!(parse_map(&text).err(), Some(error));
+    }
+
+    #[test]
+    fn rejects_targets_outside_the_map() {
+        let text = format!("start 3,0\nend 1,0\n{}", ROWS);
+        assert_eq!(parse_map(&text).err(), Some(MapError::TargetOutOfRange("start", 3, 0)));
+        let text = format!("start 0,0\nend 0,2\n{}", ROWS);
+        assert_eq!(parse_map(&text).err(), Some(MapError::TargetOutOfRange("end", 0, 2)));
+    }
+
+    #[test]
+    fn rejects_targets_on_walls() {
+        let text = format!("start 1,0\nend 2,1\n{}", ROWS);
+        assert_eq!(parse_map(&text).err(), Some(MapError::TargetOnWall("start", 1, 0)));
+        let text = format!("start 0,0\nend 1,0\n{}", ROWS);
+        assert_eq!(parse_map(&text).err(), Some(MapError::TargetOnWall("end", 1, 0)));
+    }
+
+    #[test]
+    fn rejects_unknown_terrain() {
+        let text = "start 0,0\nend 1,0\n0,0,0\n0,4,0\n";
+        let error = MapError::UnknownTerrain {
+            row: 2,
+            col: 2,
+            code: "4".to_string(),
+        };
+        assert_eq!(parse_map(text).err(), Some(error));
+    }
+
+    #[test]
+    fn rejects_ragged_rows() {
+        let text = "start 0,0\nend 1,0\n0,0,0\n0,0,0\n0,0\n";
+        let error = MapError::RaggedRow {
+            row: 3,
+            expected: 3,
+            found: 2,
+        };
+        assert_eq!(parse_map(text).err(), Some(error));
+    }
+
+    #[test]
+    fn formatted_maps_parse_back_unchanged() {
+        let map = MapData {
+            num_x_tiles: 4,
+            num_y_tiles: 3,
+            terrain: [0, 1, 2, 3, 3, 2, 1, 0, 0, 0, 2, 2]
+                .iter()
+                .map(|id| Terrain::from_id(*id).unwrap())
+                .collect(),
+            start: (3, 2),
+            end: (0, 0),
+        };
+        let text = format_map(&map);
+        let parsed = parse_map(&text).unwrap();
+        assert_eq!((parsed.num_x_tiles, parsed.num_y_tiles), (4, 3));
+        assert!(parsed.terrain == map.terrain);
+        assert_eq!((parsed.start, parsed.end), (map.start, map.end));
+        assert_eq!(format_map(&parsed), text);
+    }
+}
@@ -1,24 +1,134 @@
-use std::collections::HashSet;
+use crate::engine::Transform;
+use crate::utils::{log_fmt, now, Rng};
+
+mod agents;
+mod generator;
+mod map;
+mod path;
+mod search;
+mod tile;
+use self::map::MapData;
+pub use self::agents::Agents;
+pub use self::generator::Generator;
+pub use self::map::MapError;
+pub use self::path::PathSmoothing;
+use self::search::{ClusterGraph, Planner, SearchContext};
+pub use self::search::{Algorithm, ClusterStats, NodeState, ReplanStats, SearchNodes, SearchStats};
+pub use self::tile::{Heuristic, Terrain, Tile};
+
+// Maps to the ids passed to set_movement on the client side
+#[derive(Clone, Copy, PartialEq)]
+pub enum Movement {
+    // Top/bottom/left/right only
+    Cardinal = 0,
+    // Diagonals allowed even when squeezing past a wall corner
+    Diagonal = 1,
+    // Diagonals only when both tiles beside the move are open
+    DiagonalNoCornerCutting = 2,
+}
 
-use crate::engine::{Color, Transform};
-use crate::utils::{log_fmt, random, random_range};
+impl Movement {
+    pub fn from_id(id: u32) -> Option<Movement> {
+        match id {
+            0 => Some(Movement::Cardinal),
+            1 => Some(Movement::Diagonal),
+            2 => Some(Movement::DiagonalNoCornerCutting),
+            _ => None,
+        }
+    }
 
-mod tile;
-pub use self::tile::Tile;
+    pub fn name(self) -> &'static str {
+        match self {
+            Movement::Cardinal => "Cardinal",
+            Movement::Diagonal => "Diagonal",
+            Movement::DiagonalNoCornerCutting => "Diagonal (no corner cutting)",
+        }
+    }
+}
+
+// What a mouse drag does, picked from the tile the drag started on
+#[derive(Clone, Copy, PartialEq)]
+enum Edit {
+    PaintWalls,
+    EraseWalls,
+    DragGoal,
+}
+
+// Everything calc_path depends on, a frame with the same inputs as the
+// last one keeps the current path instead of searching again.
+#[derive(Clone, Copy, PartialEq)]
+struct PlanInputs {
+    start_id: i32,
+    end_id: i32,
+    map_version: u32,
+    algorithm: Algorithm,
+    heuristic: Heuristic,
+    max_expansions: u32,
+}
+
+// Map seed until wasm_init picks a random one
+const DEFAULT_SEED: u32 = 1;
 
 pub struct WorldState {
     pub debug: bool,
     pub window_width: u32,
     pub window_height: u32,
+    // Canvas size in canvas pixels (CSS pixels times quality). The map can be
+    // larger than the canvas, the camera picks the part that is shown.
     pub width: u32,
     pub height: u32,
     pub quality: u32,
+    // Tile size in world pixels, tile x/y_id times tile_size is its transform
     pub tile_size: u32,
+    pub num_x_tiles: u32,
+    pub num_y_tiles: u32,
+    // Size of generated maps in tiles, None fills the canvas
+    pub map_size: Option<(u32, u32)>,
     pub start_id: i32,
     pub end_id: i32,
     pub player: Transform,
+    // Follow mode: the player walks the path at player_speed tiles per second
+    // instead of following the mouse. The path is planned from player_tile_id,
+    // the tile it stands on or is walking to.
+    pub follow_path: bool,
+    pub player_speed: f64,
+    player_tile_id: usize,
+    player_prev_tile_id: usize,
+    // Extra agents wandering the map alongside the player, 0 by default
+    pub agent_count: usize,
+    pub agents: Agents,
     pub tiles: Vec<Tile>,
-    pub recent_regen: bool,
+    pub algorithm: Algorithm,
+    pub heuristic: Heuristic,
+    pub movement: Movement,
+    pub generator: Generator,
+    pub search_stats: SearchStats,
+    pub replan_stats: ReplanStats,
+    // Post-processed copies of the path from the last calc_path as tile
+    // centers in world pixels, start first. Empty when smoothing is off or
+    // there is no path, smoothed_path is only filled for Catmull-Rom.
+    pub path_smoothing: PathSmoothing,
+    pub path_waypoints: Vec<(f64, f64)>,
+    pub smoothed_path: Vec<(f64, f64)>,
+    // Bumped whenever terrain or side links change
+    map_version: u32,
+    // Tiles whose terrain or side links changed since the last calc_path
+    changed_tiles: Vec<usize>,
+    last_plan: Option<PlanInputs>,
+    planner: Planner,
+    clusters: ClusterGraph,
+    // Step mode: calc_path stops after step_budget expansions so the open and
+    // closed sets can be watched growing, steps_per_tick is added every update.
+    pub stepping: bool,
+    pub steps_per_tick: u32,
+    step_budget: u32,
+    step_targets: (i32, i32),
+    // Set while a mouse button is held over the map
+    edit: Option<Edit>,
+    // Seed the current map was generated from
+    pub seed: u32,
+    rng: Rng,
+    nodes: SearchNodes,
 }
 
 impl WorldState {
@@ -36,120 +146,492 @@ impl WorldState {
             height,
             quality,
             tile_size,
+            num_x_tiles: 0,
+            num_y_tiles: 0,
+            map_size: None,
             tiles: Vec::new(),
             player: Transform::default(),
+            follow_path: false,
+            player_speed: 4_f64,
+            player_tile_id: 0,
+            player_prev_tile_id: 0,
+            agent_count: 0,
+            agents: Agents::new(),
             start_id: -1,
             end_id: -1,
-            recent_regen: false,
+            algorithm: Algorithm::AStar,
+            heuristic: Heuristic::Manhattan,
+            movement: Movement::Cardinal,
+            generator: Generator::Noise,
+            search_stats: SearchStats::default(),
+            replan_stats: ReplanStats::default(),
+            path_smoothing: PathSmoothing::Off,
+            path_waypoints: Vec::new(),
+            smoothed_path: Vec::new(),
+            map_version: 0,
+            changed_tiles: Vec::new(),
+            last_plan: None,
+            planner: Planner::new(),
+            clusters: ClusterGraph::new(),
+            stepping: false,
+            steps_per_tick: 1,
+            step_budget: 0,
+            step_targets: (-1, -1),
+            edit: None,
+            seed: DEFAULT_SEED,
+            rng: Rng::new(DEFAULT_SEED),
+            nodes: SearchNodes::new(),
         };
-        w.reset();
+        w.load_seeded_map(DEFAULT_SEED);
         w
     }
 
+    // Next map seed comes from the current map's generator, so a run of
+    // regenerated maps is reproducible from the first seed as well.
     pub fn reset(&mut self) {
-        self.load_random_map();
+        let seed = self.rng.next_u32();
+        self.load_seeded_map(seed);
         // self.load_test_map();
     }
 
-    pub fn update_player(&mut self, x_dir: i32, y_dir: i32) {
-        let new_x = self.player.pos_x + (7_f64 * x_dir as f64);
-        let new_y = self.player.pos_y + (7_f64 * y_dir as f64);
-        if new_x + (self.tile_size as f64) < self.width as f64 && new_x > 0_f64 {
+    pub fn load_seeded_map(&mut self, seed: u32) {
+        self.seed = seed;
+        self.rng = Rng::new(seed);
+        self.load_random_map();
+    }
+
+    // Largest canvas the window has room for
+    pub fn fit_to_window(&mut self) {
+        if self.window_width < 600 {
+            self.width = 350 * self.quality;
+            self.height = 450 * self.quality;
+        } else {
+            self.width = 900 * self.quality;
+            self.height = 600 * self.quality;
+        }
+    }
+
+    // Width and height of the whole map in world pixels
+    pub fn map_width(&self) -> u32 {
+        self.num_x_tiles * self.tile_size
+    }
+
+    pub fn map_height(&self) -> u32 {
+        self.num_y_tiles * self.tile_size
+    }
+
+    // Replaces the current map with one in the map.rs text format. Tiles are
+    // made as large as the window allows and the canvas shrinks to fit them,
+    // maps too big for that get 1px tiles and a canvas as large as the window.
+    // The current map is left untouched when the text is invalid.
+    pub fn load_map_text(&mut self, text: &str) -> Result<(), MapError> {
+        let map = map::parse_map(text)?;
+        self.fit_to_window();
+        self.tile_size = (self.width / map.num_x_tiles)
+            .min(self.height / map.num_y_tiles)
+            .max(1);
+        self.num_x_tiles = map.num_x_tiles;
+        self.num_y_tiles = map.num_y_tiles;
+        self.width = self.width.min(self.map_width());
+        self.height = self.height.min(self.map_height());
+        self.tiles = build_tiles(map.num_x_tiles, self.tile_size, &map.terrain);
+        self.set_all_tile_sides();
+        self.spawn_agents();
+        let start_id = self.get_tile_id_at(map.start.0, map.start.1);
+        let end_id = self.get_tile_id_at(map.end.0, map.end.1);
+        self.set_target_tiles(start_id, end_id);
+        self.calc_path();
+        Ok(())
+    }
+
+    pub fn map_text(&self) -> String {
+        let start = &self.tiles[self.start_id as usize];
+        let end = &self.tiles[self.end_id as usize];
+        map::format_map(&MapData {
+            num_x_tiles: self.num_x_tiles,
+            num_y_tiles: self.num_y_tiles,
+            terrain: self.tiles.iter().map(|t| t.terrain).collect(),
+            start: (start.x_id as u32, start.y_id as u32),
+            end: (end.x_id as u32, end.y_id as u32),
+        })
+    }
+
+    // Arrow keys, blocked by walls unless the player is already stuck in one.
+    pub fn update_player(&mut self, x_dir: f64, y_dir: f64) {
+        if self.follow_path {
+            return;
+        }
+        let stuck = !self.is_player_box_open(self.player.pos_x, self.player.pos_y);
+        let new_x = self.player.pos_x + (7_f64 * x_dir);
+        let new_y = self.player.pos_y + (7_f64 * y_dir);
+        if new_x + (self.tile_size as f64) < self.map_width() as f64
+            && new_x > 0_f64
+            && (stuck || self.is_player_box_open(new_x, self.player.pos_y))
+        {
             self.player.pos_x = new_x;
         }
-        if new_y + (self.tile_size as f64) < self.height as f64 && new_y > 0_f64 {
+        if new_y + (self.tile_size as f64) < self.map_height() as f64
+            && new_y > 0_f64
+            && (stuck || self.is_player_box_open(self.player.pos_x, new_y))
+        {
             self.player.pos_y = new_y;
         }
     }
 
     pub fn set_start_node(&mut self) {
+        if self.follow_path {
+            self.start_id = self.player_tile_id as i32;
+            return;
+        }
         let half_tile = (self.tile_size / 2) as f64;
         self.start_id = self
             .get_tile_id_closest_to(self.player.pos_x - half_tile, self.player.pos_y - half_tile)
             as i32;
     }
 
-    pub fn calc_astar(&mut self) {
-        let mut open_nodes: Vec<usize> = Vec::new();
-        let mut closed_nodes = HashSet::new();
+    // The player starts walking from the tile it is closest to.
+    pub fn set_follow_path(&mut self, enabled: bool) {
+        self.follow_path = false;
+        self.set_start_node();
+        self.follow_path = enabled;
+        self.player_tile_id = self.start_id as usize;
+        self.player_prev_tile_id = self.player_tile_id;
+    }
+
+    // Moves the player delta seconds along the path from the last calc_path,
+    // it stops at the goal or wherever the path ends. A new goal needs no
+    // special handling, calc_path replans and the walk carries on from the
+    // tile the player is heading to.
+    pub fn walk_path(&mut self, delta: f64) {
+        if !self.follow_path {
+            return;
+        }
+        // The tile ahead was painted over, head back to the one we came from
+        if self.tiles[self.player_tile_id].is_wall() {
+            self.player_tile_id = self.player_prev_tile_id;
+        }
+        let mut distance = self.player_speed * self.tile_size as f64 * delta;
+        loop {
+            let target = &self.tiles[self.player_tile_id].transform;
+            let dx = target.pos_x - self.player.pos_x;
+            let dy = target.pos_y - self.player.pos_y;
+            let remaining = dx.hypot(dy);
+            if remaining > distance {
+                self.player.pos_x += dx / remaining * distance;
+                self.player.pos_y += dy / remaining * distance;
+                return;
+            }
+            self.player.pos_x = target.pos_x;
+            self.player.pos_y = target.pos_y;
+            distance -= remaining;
+            match self.next_path_tile_id(self.player_tile_id) {
+                Some(next_id) => {
+                    self.player_prev_tile_id = self.player_tile_id;
+                    self.player_tile_id = next_id;
+                }
+                None => return,
+            }
+        }
+    }
+
+    // parent_id links run from the end back to the start, so the tile after
+    // id is the one whose parent it is.
+    fn next_path_tile_id(&self, id: usize) -> Option<usize> {
+        let mut next_id = self.end_id;
+        while next_id >= 0 {
+            let parent_id = self.tiles[next_id as usize].parent_id;
+            if parent_id == id as i32 {
+                return Some(next_id as usize).filter(|n| !self.tiles[*n].is_wall());
+            }
+            next_id = parent_id;
+        }
+        None
+    }
 
-        open_nodes.push(self.start_id as usize);
-        let mut current_node;
-        let end = self.tiles[self.end_id as usize].clone();
+    pub fn calc_path(&mut self) {
+        let max_expansions = if self.stepping {
+            // Start watching from scratch whenever the start or end moves
+            if self.step_targets != (self.start_id, self.end_id) {
+                self.step_targets = (self.start_id, self.end_id);
+                self.step_budget = 0;
+            }
+            self.step_budget
+        } else {
+            u32::MAX
+        };
+        let inputs = PlanInputs {
+            start_id: self.start_id,
+            end_id: self.end_id,
+            map_version: self.map_version,
+            algorithm: self.algorithm,
+            heuristic: self.heuristic,
+            max_expansions,
+        };
+        if self.last_plan == Some(inputs) {
+            self.replan_stats.skipped += 1;
+            return;
+        }
+        self.last_plan = Some(inputs);
 
+        let start_time = now();
+        self.nodes.reset(self.tiles.len());
+        let mut min_move_cost = i32::MAX;
+        let mut max_move_cost = 0;
         for t in self.tiles.iter_mut() {
-            t.reset(&end);
-        }
-
-        // Stop searching when either:
-        // 1) target is closed, in which case the path has been found
-        // 2) failed to find the target and the open list is empty (no path)
-        while !closed_nodes.contains(&(self.end_id as usize)) && open_nodes.len() > 0 {
-            // Find lowest F score
-            open_nodes.sort_by(|a, b| {
-                let a_f = &self.tiles[*a].f;
-                let b_f = &self.tiles[*b].f;
-                a_f.cmp(b_f)
-            });
-
-            current_node = open_nodes.swap_remove(0);
-            closed_nodes.insert(current_node);
-
-            let side_ids = vec![
-                self.tiles[current_node].top,
-                self.tiles[current_node].bottom,
-                self.tiles[current_node].right,
-                self.tiles[current_node].left,
-            ];
-
-            // Check each side node.
-            // If the side exists (id >= 0)
-            // If it's a wall, it's not set as a side so we don't need to worry about it.
-            for s in side_ids.iter() {
-                let id = *s as usize;
-                if *s >= 0 && !closed_nodes.contains(&id) {
-                    self.check_node(&mut open_nodes, current_node, id);
-                }
+            t.reset();
+            if !t.is_wall() {
+                min_move_cost = min_move_cost.min(t.terrain.move_cost());
+                max_move_cost = max_move_cost.max(t.terrain.move_cost());
             }
         }
+        // All walls, nothing to step onto
+        if max_move_cost == 0 {
+            min_move_cost = tile::MOVE_COST;
+            max_move_cost = tile::MOVE_COST;
+        }
+
+        let mut ctx = SearchContext {
+            tiles: &mut self.tiles,
+            nodes: &mut self.nodes,
+            start_id: self.start_id as usize,
+            end_id: self.end_id as usize,
+            num_x_tiles: self.num_x_tiles as i32,
+            num_y_tiles: self.num_y_tiles as i32,
+            heuristic: self.heuristic,
+            movement: self.movement,
+            min_move_cost,
+            max_move_cost,
+            max_expansions,
+        };
+        // D* Lite keeps its search between frames and only repairs it, the
+        // others (and D* Lite while stepping) search from scratch. HPA* keeps
+        // its cluster graph, which hears about every change whichever
+        // algorithm is running so it never has to start over for an edit.
+        // So do the agents, whose goal searches are only redone near edits.
+        self.clusters.mark_changed(&self.changed_tiles);
+        self.agents.mark_changed(&self.changed_tiles);
+        let selected = self.algorithm.searcher();
+        let searcher = selected.fallback(min_move_cost, max_move_cost).unwrap_or(selected);
+        let (nodes_expanded, incremental) = match self.algorithm {
+            Algorithm::DStarLite if !self.stepping => {
+                self.planner.plan(&mut ctx, &self.changed_tiles)
+            }
+            Algorithm::Hierarchical => {
+                self.planner.invalidate();
+                self.clusters.plan(&mut ctx)
+            }
+            _ => {
+                self.planner.invalidate();
+                (searcher.search(&mut ctx), false)
+            }
+        };
+        if incremental {
+            self.replan_stats.incremental += 1;
+        } else {
+            self.replan_stats.full += 1;
+        }
+        self.changed_tiles.clear();
+
+        self.search_stats = SearchStats {
+            nodes_expanded,
+            elapsed_us: (now() - start_time) * 1000_f64,
+            searched_with: searcher.name(),
+        };
+        self.smooth_path();
+    }
+
+    pub fn set_path_smoothing(&mut self, path_smoothing: PathSmoothing) {
+        self.path_smoothing = path_smoothing;
+        self.smooth_path();
+    }
+
+    // Redoes path_waypoints and smoothed_path for the current parent_id
+    // chain. A chain that doesn't get back to the start (no path, or a
+    // search cut short in step mode) leaves them empty.
+    fn smooth_path(&mut self) {
+        self.path_waypoints.clear();
+        self.smoothed_path.clear();
+        if self.path_smoothing == PathSmoothing::Off || self.end_id < 0 {
+            return;
+        }
+        let mut path = vec![self.end_id as usize];
+        while self.tiles[path[path.len() - 1]].parent_id >= 0 {
+            path.push(self.tiles[path[path.len() - 1]].parent_id as usize);
+        }
+        if path[path.len() - 1] != self.start_id as usize {
+            return;
+        }
+        path.reverse();
+        let half_tile = (self.tile_size / 2) as f64;
+        self.path_waypoints = path::string_pull(&self.tiles, self.num_x_tiles as i32, &path)
+            .iter()
+            .map(|id| {
+                let t = &self.tiles[*id].transform;
+                (t.pos_x + half_tile, t.pos_y + half_tile)
+            })
+            .collect();
+        if self.path_smoothing == PathSmoothing::CatmullRom {
+            self.smoothed_path = path::catmull_rom(&self.path_waypoints);
+        }
     }
 
+    pub fn set_agent_count(&mut self, count: usize) {
+        self.agent_count = count;
+        self.spawn_agents();
+    }
+
+    pub fn update_agents(&mut self, now_ms: f64) {
+        self.agents.update(now_ms, &self.tiles, self.map_version);
+    }
+
+    fn spawn_agents(&mut self) {
+        self.agents
+            .spawn(self.agent_count, &self.tiles, self.seed, self.map_version);
+    }
+
+    pub fn set_stepping(&mut self, stepping: bool, steps_per_tick: u32) {
+        self.stepping = stepping;
+        self.steps_per_tick = steps_per_tick;
+        self.step_budget = 0;
+    }
+
+    // Lets the next calc_path expand `steps` more nodes than the last one.
+    pub fn step_search(&mut self, steps: u32) {
+        // The last search finished within its budget, nothing left to step through
+        if self.search_stats.nodes_expanded < self.step_budget {
+            return;
+        }
+        self.step_budget = self.step_budget.saturating_add(steps);
+    }
+
+    // Open/closed sets and G/H/F left by the last calc_path
+    pub fn search_nodes(&self) -> &SearchNodes {
+        &self.nodes
+    }
+
+    pub fn cluster_stats(&self) -> ClusterStats {
+        self.clusters.stats
+    }
+
+    // Cost of the path from the last calc_path over the cheapest possible
+    // one between the same tiles: 1 when it is the cheapest, 0 when there is
+    // no path. The cheapest is found with a Dijkstra over the whole map, too
+    // slow to ship, so it is only for checking HPA* (or an inadmissible
+    // heuristic) in tests.
+    #[cfg(test)]
+    pub fn path_suboptimality(&mut self) -> f64 {
+        let end_id = self.end_id as usize;
+        let mut start_id = end_id;
+        let mut path_cost = 0;
+        while self.tiles[start_id].parent_id >= 0 {
+            let parent_id = self.tiles[start_id].parent_id as usize;
+            path_cost += self.tiles[parent_id].move_cost_to(&self.tiles[start_id]);
+            start_id = parent_id;
+        }
+        if start_id == end_id {
+            return if self.start_id == self.end_id {
+                1_f64
+            } else {
+                0_f64
+            };
+        }
+        let ctx = SearchContext {
+            tiles: &mut self.tiles,
+            nodes: &mut self.nodes,
+            start_id,
+            end_id,
+            num_x_tiles: self.num_x_tiles as i32,
+            num_y_tiles: self.num_y_tiles as i32,
+            heuristic: Heuristic::Zero,
+            movement: self.movement,
+            min_move_cost: tile::MOVE_COST,
+            max_move_cost: tile::MOVE_COST,
+            max_expansions: u32::MAX,
+        };
+        match search::cheapest_cost(&ctx, start_id, end_id) {
+            Some(cheapest) if cheapest > 0 => path_cost as f64 / cheapest as f64,
+            _ => 0_f64,
+        }
+    }
+
+    // Side links depend on the movement mode so they are rebuilt on change.
+    pub fn set_movement(&mut self, movement: Movement) {
+        self.movement = movement;
+        self.set_all_tile_sides();
+    }
+
+    // x/y in world pixels, see engine::Camera for the mouse position
     pub fn set_player_pos(&mut self, x: f64, y: f64) {
         let half_tile = (self.tile_size / 2) as f64;
-        let new_x = (x * self.quality as f64) - half_tile;
-        let new_y = (y * self.quality as f64) - half_tile;
-        if new_x + (self.tile_size as f64) < self.width as f64 && new_x > 0_f64 {
+        let new_x = x - half_tile;
+        let new_y = y - half_tile;
+        if new_x + (self.tile_size as f64) < self.map_width() as f64 && new_x > 0_f64 {
             self.player.pos_x = new_x;
         }
-        if new_y + (self.tile_size as f64) < self.height as f64 && new_y > 0_f64 {
+        if new_y + (self.tile_size as f64) < self.map_height() as f64 && new_y > 0_f64 {
             self.player.pos_y = new_y;
         }
     }
 
-    fn check_node(
-        &mut self,
-        open_nodes: &mut Vec<usize>,
-        curr_node_id: usize,
-        side_node_id: usize,
-    ) {
-        let id = side_node_id as usize;
-        let mut parent_id = 0;
-        let mut parent_g = -1;
-        // if it's not already on the open list
-        if !open_nodes.contains(&id) {
-            open_nodes.push(id);
-            parent_id = curr_node_id;
-            parent_g = self.tiles[parent_id].g;
+    // Position in world pixels, like set_player_pos.
+    // Returns the tile id whose terrain changed so only it has to be redrawn.
+    pub fn begin_edit(&mut self, x: f64, y: f64) -> Option<usize> {
+        let id = self.get_tile_id_under(x, y)?;
+        if id == self.end_id as usize {
+            self.edit = Some(Edit::DragGoal);
+            return None;
         }
-        // if it's already on the open list and the path is better (lower G value)
-        else if self.tiles[id].g > self.tiles[curr_node_id].g + tile::MOVE_COST {
-            parent_id = curr_node_id;
-            parent_g = self.tiles[parent_id].g;
+        self.edit = Some(if self.tiles[id].is_wall() {
+            Edit::EraseWalls
+        } else {
+            Edit::PaintWalls
+        });
+        self.continue_edit(x, y)
+    }
+
+    pub fn continue_edit(&mut self, x: f64, y: f64) -> Option<usize> {
+        let id = self.get_tile_id_under(x, y)?;
+        match self.edit? {
+            Edit::DragGoal => {
+                if !self.tiles[id].is_wall() {
+                    self.end_id = id as i32;
+                }
+                None
+            }
+            // The goal is never painted over, it stays walkable while editing
+            _ if id == self.end_id as usize => None,
+            Edit::PaintWalls if !self.tiles[id].is_wall() => {
+                self.set_tile_terrain(id, Terrain::Wall);
+                Some(id)
+            }
+            Edit::EraseWalls if self.tiles[id].is_wall() => {
+                self.set_tile_terrain(id, Terrain::Grass);
+                Some(id)
+            }
+            _ => None,
         }
-        if parent_g != -1 {
-            self.tiles[id].parent_id = parent_id as i32;
-            self.tiles[id].calc_f_g(parent_g);
+    }
+
+    pub fn end_edit(&mut self) {
+        self.edit = None;
+    }
+
+    pub fn is_editing(&self) -> bool {
+        self.edit.is_some()
+    }
+
+    fn get_tile_id_under(&self, x: f64, y: f64) -> Option<usize> {
+        let size = self.tile_size as f64;
+        let x_id = (x / size).floor();
+        let y_id = (y / size).floor();
+        if x_id < 0_f64
+            || y_id < 0_f64
+            || x_id >= self.num_x_tiles as f64
+            || y_id >= self.num_y_tiles as f64
+        {
+            return None;
         }
+        Some(self.get_tile_id_at(x_id as u32, y_id as u32))
     }
 
     #[allow(dead_code)]
@@ -160,21 +642,28 @@ impl WorldState {
 
     #[allow(dead_code)]
     fn get_random_tile(&mut self) -> Tile {
-        let num_x_tiles = (self.width / self.tile_size) as i32;
-        let num_y_tiles = (self.height / self.tile_size) as i32;
-        let index = self.get_tile_id_at(
-            random_range(0, num_x_tiles - 1) as u32,
-            random_range(0, num_y_tiles - 1) as u32,
-        );
+        let index = self.get_random_tile_id();
         self.tiles[index].clone()
     }
 
     fn get_tile_id_at(&self, x: u32, y: u32) -> usize {
-        let num_tiles = self.width / self.tile_size;
-        let index = y * num_tiles + x;
+        let index = y * self.num_x_tiles + x;
         index as usize
     }
 
+    // Whether a tile sized box with its top left corner at x/y (world
+    // pixels, like the player transform) only covers open tiles.
+    fn is_player_box_open(&self, x: f64, y: f64) -> bool {
+        let size = self.tile_size as f64;
+        let far = size - 1_f64;
+        [(x, y), (x + far, y), (x, y + far), (x + far, y + far)]
+            .iter()
+            .all(|(px, py)| {
+                let id = self.get_tile_id_at((px / size) as u32, (py / size) as u32);
+                self.tiles.get(id).is_some_and(|t| !t.is_wall())
+            })
+    }
+
     fn get_tile_id_closest_to(&self, x: f64, y: f64) -> usize {
         let size = self.tile_size as f64;
         let x_id = (x / size).ceil() as u32;
@@ -182,89 +671,126 @@ impl WorldState {
         self.get_tile_id_at(x_id, y_id)
     }
 
-    fn get_random_tile_id(&self) -> usize {
-        let num_x_tiles = (self.width / self.tile_size) as i32;
-        let num_y_tiles = (self.height / self.tile_size) as i32;
-        self.get_tile_id_at(
-            random_range(0, num_x_tiles - 1) as u32,
-            random_range(0, num_y_tiles - 1) as u32,
-        )
+    #[allow(dead_code)]
+    fn get_random_tile_id(&mut self) -> usize {
+        let num_x_tiles = self.num_x_tiles as i32;
+        let num_y_tiles = self.num_y_tiles as i32;
+        let x = self.rng.random_range(0, num_x_tiles - 1) as u32;
+        let y = self.rng.random_range(0, num_y_tiles - 1) as u32;
+        self.get_tile_id_at(x, y)
     }
 
-    fn set_target_tiles(&mut self) {
-        self.start_id = self.get_random_tile_id() as i32;
-        self.end_id = self.get_random_tile_id() as i32;
+    fn set_target_tiles(&mut self, start_id: usize, end_id: usize) {
+        self.start_id = start_id as i32;
+        self.end_id = end_id as i32;
+        // New map, step through its search from the beginning
+        self.step_budget = 0;
         self.player.pos_x = self.tiles[self.start_id as usize].transform.pos_x;
         self.player.pos_y = self.tiles[self.start_id as usize].transform.pos_y;
+        self.player_tile_id = start_id;
+        self.player_prev_tile_id = start_id;
     }
 
     fn set_all_tile_sides(&mut self) {
-        let num_x_tiles = (self.width / self.tile_size) as i32;
-        let num_y_tiles = (self.height / self.tile_size) as i32;
         for t_id in 0..self.tiles.len() {
-            let x_id = self.tiles[t_id].x_id;
-            let y_id = self.tiles[t_id].y_id;
-            if x_id + 1 < num_x_tiles {
-                let right = y_id * num_x_tiles + x_id + 1;
-                if !self.tiles[right as usize].is_wall {
-                    self.tiles[t_id].right = right;
-                }
-            }
-            if x_id - 1 >= 0 {
-                let left = y_id * num_x_tiles + x_id - 1;
-                if !self.tiles[left as usize].is_wall {
-                    self.tiles[t_id].left = left;
-                }
-            }
+            self.set_tile_sides(t_id);
+        }
+        self.map_version = self.map_version.wrapping_add(1);
+        self.changed_tiles.clear();
+        self.planner.invalidate();
+        self.clusters.invalidate();
+        self.agents.invalidate();
+    }
 
-            if y_id - 1 >= 0 {
-                let top = ((y_id - 1) * num_x_tiles) + x_id;
-                if !self.tiles[top as usize].is_wall {
-                    self.tiles[t_id].top = top;
-                }
-            }
-            if y_id + 1 < num_y_tiles {
-                let bottom = ((y_id + 1) * num_x_tiles) + x_id;
-                if !self.tiles[bottom as usize].is_wall {
-                    self.tiles[t_id].bottom = bottom;
-                }
+    fn set_tile_sides(&mut self, t_id: usize) {
+        let x_id = self.tiles[t_id].x_id;
+        let y_id = self.tiles[t_id].y_id;
+        self.tiles[t_id].top = self.get_side_id(x_id, y_id, 0, -1);
+        self.tiles[t_id].bottom = self.get_side_id(x_id, y_id, 0, 1);
+        self.tiles[t_id].left = self.get_side_id(x_id, y_id, -1, 0);
+        self.tiles[t_id].right = self.get_side_id(x_id, y_id, 1, 0);
+        self.tiles[t_id].top_left = self.get_side_id(x_id, y_id, -1, -1);
+        self.tiles[t_id].top_right = self.get_side_id(x_id, y_id, 1, -1);
+        self.tiles[t_id].bottom_left = self.get_side_id(x_id, y_id, -1, 1);
+        self.tiles[t_id].bottom_right = self.get_side_id(x_id, y_id, 1, 1);
+    }
+
+    // A tile turning into or out of a wall only changes links inside the 3x3
+    // block around it: the links into it, and (without corner cutting) the
+    // diagonals between its neighbors that squeeze past it.
+    fn update_sides_around(&mut self, t_id: usize) {
+        let x_id = self.tiles[t_id].x_id;
+        let y_id = self.tiles[t_id].y_id;
+        let num_x_tiles = self.num_x_tiles as i32;
+        let num_y_tiles = self.num_y_tiles as i32;
+        for y in (y_id - 1).max(0)..=(y_id + 1).min(num_y_tiles - 1) {
+            for x in (x_id - 1).max(0)..=(x_id + 1).min(num_x_tiles - 1) {
+                let id = self.get_tile_id_at(x as u32, y as u32);
+                self.set_tile_sides(id);
+                self.changed_tiles.push(id);
             }
         }
     }
 
-    #[allow(dead_code)]
-    fn print_map(&self) {
-        let num_y_tiles = self.height / self.tile_size;
-        let num_x_tiles = self.width / self.tile_size;
-        let mut map = String::from("");
-
-        for y in 0..num_y_tiles {
-            for x in 0..num_x_tiles {
-                let id = self.get_tile_id_at(x, y);
-                if self.tiles[id].is_wall {
-                    map = format!("{}{},", map, "1");
+    fn set_tile_terrain(&mut self, t_id: usize, terrain: Terrain) {
+        self.tiles[t_id].set_terrain(terrain);
+        // Links only change when a wall comes or goes, but the neighbors'
+        // cost of stepping onto the tile changes either way.
+        self.update_sides_around(t_id);
+        self.map_version = self.map_version.wrapping_add(1);
+    }
+
+    // Id of the tile dx/dy away if it can be moved onto from x_id/y_id, otherwise -1.
+    fn get_side_id(&self, x_id: i32, y_id: i32, dx: i32, dy: i32) -> i32 {
+        let side_id = self.get_open_tile_id(x_id + dx, y_id + dy);
+        if dx == 0 || dy == 0 || side_id < 0 {
+            return side_id;
+        }
+        match self.movement {
+            Movement::Cardinal => -1,
+            Movement::Diagonal => side_id,
+            Movement::DiagonalNoCornerCutting => {
+                if self.get_open_tile_id(x_id + dx, y_id) >= 0
+                    && self.get_open_tile_id(x_id, y_id + dy) >= 0
+                {
+                    side_id
                 } else {
-                    map = format!("{}{},", map, "0");
+                    -1
                 }
             }
-            map = format!("{}\n", map);
         }
-        log_fmt(map);
+    }
+
+    // Id of the tile at x_id/y_id, -1 when off the map or a wall.
+    fn get_open_tile_id(&self, x_id: i32, y_id: i32) -> i32 {
+        let num_x_tiles = self.num_x_tiles as i32;
+        let num_y_tiles = self.num_y_tiles as i32;
+        if x_id < 0 || y_id < 0 || x_id >= num_x_tiles || y_id >= num_y_tiles {
+            return -1;
+        }
+        let id = y_id * num_x_tiles + x_id;
+        if self.tiles[id as usize].is_wall() {
+            -1
+        } else {
+            id
+        }
     }
 
     fn load_random_map(&mut self) {
-        let tile_sizes = vec![10, 20, 50];
-        self.tile_size = tile_sizes[random_range(0, (tile_sizes.len() - 1) as i32) as usize];
-        self.tiles = generate_tiles(self.width, self.height, self.tile_size);
+        let tile_sizes = [10, 20, 50];
+        self.tile_size = tile_sizes[self.rng.random_range(0, (tile_sizes.len() - 1) as i32) as usize];
+        let (num_x_tiles, num_y_tiles) = self
+            .map_size
+            .unwrap_or((self.width / self.tile_size, self.height / self.tile_size));
+        self.num_x_tiles = num_x_tiles;
+        self.num_y_tiles = num_y_tiles;
+        let map = self.generator.generate(&mut self.rng, num_x_tiles, num_y_tiles);
+        self.tiles = build_tiles(num_x_tiles, self.tile_size, &map.terrain);
         self.set_all_tile_sides();
-        self.set_target_tiles();
+        self.spawn_agents();
+        self.set_target_tiles(map.start_id, map.end_id);
         self.set_start_node();
-        self.calc_astar();
-
-        // Force a new map if no path found.
-        if self.tiles[self.end_id as usize].parent_id == -1 {
-            self.reset();
-        }
+        self.calc_path();
     }
 
     #[allow(dead_code)]
@@ -294,72 +820,27 @@ impl WorldState {
             0,0,0,0,0,0,0,0,0,0,0,1,0,0,1,1,0,1,0,0,0,1,0,0,1,0,0,0,0,0,0,0,0,1,1,0,
             0,0,0,0,0,0,1,1,0,0,0,1,1,1,0,0,0,0,0,0,0,0,1,1,1,0,1,0,1,0,1,0,1,0,0,0,";
 
-        self.tile_size = 50;
-        self.tiles = load_map(self.tile_size, test_map);
-        self.set_all_tile_sides();
-        self.start_id = 418;
-        self.end_id = 316;
-        self.player.pos_x = self.tiles[self.start_id as usize].transform.pos_x;
-        self.player.pos_y = self.tiles[self.start_id as usize].transform.pos_y;
-    }
-}
-
-#[allow(dead_code)]
-fn load_map(tile_size: u32, map: &str) -> Vec<Tile> {
-    let mut vec = Vec::new();
-    let rows: Vec<&str> = map.split_terminator("\n").collect();
-
-    for (y, row) in rows.iter().enumerate() {
-        let cols: Vec<&str> = row.trim().split_terminator(",").collect();
-        let num_cols = cols.len();
-        for (x, col) in cols.iter().enumerate() {
-            let px = x as f64 * tile_size as f64;
-            let py = y as f64 * tile_size as f64;
-            let size = tile_size as f64;
-            let mut t: Tile = Tile::new(px, py, size);
-            t.x_id = x as i32;
-            t.y_id = y as i32;
-            t.node_id = (y * num_cols + x) as usize;
-            t.is_wall = {
-                if String::from(*col).eq("1") {
-                    true
-                } else {
-                    false
-                }
-            };
-            let lightness = if t.is_wall { 20 } else { 30 };
-            t.color = Color::new(0, 0, lightness, 1_f32);
-            vec.push(t);
+        let map = format!("start 22,11\nend 28,8\n{}", test_map);
+        if let Err(e) = self.load_map_text(&map) {
+            log_fmt(format!("Test map: {}", e));
         }
     }
-    vec
 }
 
-fn generate_tiles(grid_width: u32, grid_height: u32, tile_size: u32) -> Vec<Tile> {
+fn build_tiles(num_x_tiles: u32, tile_size: u32, terrain: &[Terrain]) -> Vec<Tile> {
     let mut vec = Vec::new();
-    let num_y_tiles = grid_height / tile_size;
-    let num_x_tiles = grid_width / tile_size;
-
-    for y in 0..num_y_tiles {
-        for x in 0..num_x_tiles {
-            let px = x as f64 * tile_size as f64;
-            let py = y as f64 * tile_size as f64;
-            let size = tile_size as f64;
-            let mut t: Tile = Tile::new(px, py, size);
-            t.x_id = x as i32;
-            t.y_id = y as i32;
-            t.node_id = (y * num_x_tiles + x) as usize;
-            t.is_wall = {
-                if random() >= 0.7 {
-                    true
-                } else {
-                    false
-                }
-            };
-            let lightness = if t.is_wall { 20 } else { 30 };
-            t.color = Color::new(0, 0, lightness, 1_f32);
-            vec.push(t);
-        }
+    for (id, t_terrain) in terrain.iter().enumerate() {
+        let x = id as u32 % num_x_tiles;
+        let y = id as u32 / num_x_tiles;
+        let px = x as f64 * tile_size as f64;
+        let py = y as f64 * tile_size as f64;
+        let size = tile_size as f64;
+        let mut t: Tile = Tile::new(px, py, size);
+        t.x_id = x as i32;
+        t.y_id = y as i32;
+        t.node_id = id;
+        t.set_terrain(*t_terrain);
+        vec.push(t);
     }
     vec
 }
@@ -0,0 +1,352 @@
+use std::cmp::Ordering;
+
+use super::Tile;
+
+// Maps to the ids passed to set_path_smoothing on the client side
+#[derive(Clone, Copy, PartialEq)]
+pub enum PathSmoothing {
+    // Only the tile by tile path
+    Off = 0,
+    // Waypoints where the path has to turn, straight lines between them
+    StringPull = 1,
+    // A Catmull-Rom curve through the string pulled waypoints
+    CatmullRom = 2,
+}
+
+impl PathSmoothing {
+    pub fn from_id(id: u32) -> Option<PathSmoothing> {
+        match id {
+            0 => Some(PathSmoothing::Off),
+            1 => Some(PathSmoothing::StringPull),
+            2 => Some(PathSmoothing::CatmullRom),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            PathSmoothing::Off => "Off",
+            PathSmoothing::StringPull => "String pulling",
+            PathSmoothing::CatmullRom => "Catmull-Rom",
+        }
+    }
+}
+
+// Curve points from one waypoint up to the next
+const CURVE_SAMPLES: usize = 8;
+
+// Tile ids of the path (start first) where it has to turn. From each
+// waypoint the path is followed as far as a straight line between tile
+// centers stays clear: no walls, and no terrain dearer than the dearest tile
+// of the stretch it replaces, so the line doesn't cut through a swamp the
+// search went around. Nor may the line cost more than the stretch, walking
+// the whole length of it on the dearest tile it crosses.
+pub fn string_pull(tiles: &[Tile], num_x_tiles: i32, path: &[usize]) -> Vec<usize> {
+    let mut waypoints: Vec<usize> = path.iter().take(1).copied().collect();
+    if path.len() < 2 {
+        return waypoints;
+    }
+    let mut anchor = 0;
+    let mut max_cost = tiles[path[0]].terrain.move_cost();
+    let mut stretch_cost = 0_f64;
+    for k in 1..path.len() {
+        let cost = tiles[path[k]].terrain.move_cost();
+        let step = step_cost(&tiles[path[k - 1]], &tiles[path[k]]);
+        let (new_max_cost, new_stretch_cost) = (max_cost.max(cost), stretch_cost + step);
+        // Neighbors on the path are always fine, even a diagonal squeezing
+        // between two walls
+        let (from_id, to_id) = (path[anchor], path[k]);
+        if k > anchor + 1
+            && !is_line_clear(tiles, num_x_tiles, from_id, to_id, new_max_cost, new_stretch_cost)
+        {
+            anchor = k - 1;
+            waypoints.push(path[anchor]);
+            max_cost = tiles[path[anchor]].terrain.move_cost().max(cost);
+            stretch_cost = step;
+        } else {
+            max_cost = new_max_cost;
+            stretch_cost = new_stretch_cost;
+        }
+    }
+    waypoints.push(path[path.len() - 1]);
+    waypoints
+}
+
+// Cost of walking between the centers of two tiles, half the way on each
+fn step_cost(from: &Tile, to: &Tile) -> f64 {
+    let length = ((to.x_id - from.x_id) as f64).hypot((to.y_id - from.y_id) as f64);
+    length * (from.terrain.move_cost() + to.terrain.move_cost()) as f64 / 2_f64
+}
+
+// Whether the line between the centers of two tiles only crosses open tiles
+// costing at most max_cost, and its length times the dearest of them comes
+// to at most max_line_cost. Every tile the line touches is checked, a line
+// through a corner checks both tiles beside the corner.
+fn is_line_clear(
+    tiles: &[Tile],
+    num_x_tiles: i32,
+    from_id: usize,
+    to_id: usize,
+    max_cost: i32,
+    max_line_cost: f64,
+) -> bool {
+    let mut dearest = tiles[from_id].terrain.move_cost();
+    let mut is_open = |x: i32, y: i32| {
+        let t = &tiles[(y * num_x_tiles + x) as usize];
+        dearest = dearest.max(t.terrain.move_cost());
+        !t.is_wall() && t.terrain.move_cost() <= max_cost
+    };
+    let (mut x, mut y) = (tiles[from_id].x_id, tiles[from_id].y_id);
+    let dx = tiles[to_id].x_id - x;
+    let dy = tiles[to_id].y_id - y;
+    let (nx, ny) = (dx.abs(), dy.abs());
+    let (mut ix, mut iy) = (0, 0);
+    while ix < nx || iy < ny {
+        // Which tile edge the line crosses next, scaled so it stays integral
+        match ((1 + 2 * ix) * ny).cmp(&((1 + 2 * iy) * nx)) {
+            Ordering::Less => {
+                x += dx.signum();
+                ix += 1;
+            }
+            Ordering::Greater => {
+                y += dy.signum();
+                iy += 1;
+            }
+            Ordering::Equal => {
+                if !is_open(x + dx.signum(), y) || !is_open(x, y + dy.signum()) {
+                    return false;
+                }
+                x += dx.signum();
+                y += dy.signum();
+                ix += 1;
+                iy += 1;
+            }
+        }
+        if !is_open(x, y) {
+            return false;
+        }
+    }
+    // Some slack for the rounding in a straight run of diagonal steps
+    (dx as f64).hypot(dy as f64) * dearest as f64 <= max_line_cost + 1e-6
+}
+
+// Centripetal Catmull-Rom curve through points, which unlike the uniform
+// kind doesn't loop or overshoot where a short hop follows a long straight.
+// The ends get a mirrored neighbor so the curve starts and ends on them.
+// It passes through every point but may shave wall corners between them.
+pub fn catmull_rom(points: &[(f64, f64)]) -> Vec<(f64, f64)> {
+    let n = points.len();
+    if n < 3 {
+        return points.to_vec();
+    }
+    let mirror = |p: (f64, f64), q: (f64, f64)| (2_f64 * p.0 - q.0, 2_f64 * p.1 - q.1);
+    let mut curve = Vec::with_capacity((n - 1) * CURVE_SAMPLES + 1);
+    for i in 0..n - 1 {
+        let before = if i > 0 {
+            points[i - 1]
+        } else {
+            mirror(points[0], points[1])
+        };
+        let after = if i + 2 < n {
+            points[i + 2]
+        } else {
+            mirror(points[n - 1], points[n - 2])
+        };
+        let segment = [before, points[i], points[i + 1], after];
+        for s in 0..CURVE_SAMPLES {
+            curve.push(curve_point(segment, s as f64 / CURVE_SAMPLES as f64));
+        }
+    }
+    curve.push(points[n - 1]);
+    curve
+}
+
+// Point u of the way from p[1] to p[2] (Barry and Goldman's pyramid), with
+// knots spaced by the square root of the distance between the points.
+fn curve_point(p: [(f64, f64); 4], u: f64) -> (f64, f64) {
+    let knot = |a: (f64, f64), b: (f64, f64)| (b.0 - a.0).hypot(b.1 - a.1).sqrt();
+    let t0 = 0_f64;
+    let t1 = t0 + knot(p[0], p[1]);
+    let t2 = t1 + knot(p[1], p[2]);
+    let t3 = t2 + knot(p[2], p[3]);
+    let t = t1 + (t2 - t1) * u;
+    let lerp = |a: (f64, f64), b: (f64, f64), ta: f64, tb: f64| {
+        let w = (t - ta) / (tb - ta);
+        (a.0 + (b.0 - a.0) * w, a.1 + (b.1 - a.1) * w)
+    };
+    let a1 = lerp(p[0], p[1], t0, t1);
+    let a2 = lerp(p[1], p[2], t1, t2);
+    let a3 = lerp(p[2], p[3], t2, t3);
+    let b1 = lerp(a1, a2, t0, t2);
+    let b2 = lerp(a2, a3, t1, t3);
+    lerp(b1, b2, t1, t2)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::Rng;
+    use crate::world::{Algorithm, Heuristic, Movement, WorldState};
+
+    // Samples per tile length when walking a line between tile centers
+    const LINE_SAMPLES: f64 = 64_f64;
+
+    // A 24x16 map of grass, road, swamp and walls with a Dijkstra path from
+    // corner to corner, the tile ids of the path start first (empty when
+    // there is none)
+    fn random_path(rng: &mut Rng, movement: Movement) -> (WorldState, Vec<usize>) {
+        let (num_x_tiles, num_y_tiles) = (24, 16);
+        let mut text = format!("start 0,0\nend {},{}\n", num_x_tiles - 1, num_y_tiles - 1);
+        for y in 0..num_y_tiles {
+            let row: Vec<&str> = (0..num_x_tiles)
+                .map(|x| {
+                    let target = (x, y) == (0, 0) || (x, y) == (num_x_tiles - 1, num_y_tiles - 1);
+                    if !target && rng.random() < 0.2 {
+                        "1"
+                    } else {
+                        ["0", "2", "3"][rng.random_range(0, 2) as usize]
+                    }
+                })
+                .collect();
+            text.push_str(&row.join(","));
+            text.push('\n');
+        }
+        let mut world = WorldState::new();
+        world.set_movement(movement);
+        world.heuristic = Heuristic::Octile;
+        world.algorithm = Algorithm::Dijkstra;
+        world.load_map_text(&text).unwrap();
+        let mut path = vec![world.end_id as usize];
+        while world.tiles[path[path.len() - 1]].parent_id >= 0 {
+            path.push(world.tiles[path[path.len() - 1]].parent_id as usize);
+        }
+        if path[path.len() - 1] != world.start_id as usize {
+            path.clear();
+        }
+        path.reverse();
+        (world, path)
+    }
+
+    // Walks the straight line between two tile centers, calling visit with
+    // the tile under each sample and the length of line it stands for
+    fn walk_line(
+        world: &WorldState,
+        from_id: usize,
+        to_id: usize,
+        mut visit: impl FnMut(&Tile, f64),
+    ) {
+        let center = |id: usize| {
+            let t = &world.tiles[id];
+            (t.x_id as f64 + 0.5, t.y_id as f64 + 0.5)
+        };
+        let (from, to) = (center(from_id), center(to_id));
+        let length = (to.0 - from.0).hypot(to.1 - from.1);
+        // Always an even count, so no sample lands on the corner in the
+        // middle of a diagonal step
+        let samples = 2 * (length * LINE_SAMPLES / 2_f64).ceil().max(1_f64) as usize;
+        for s in 0..samples {
+            let u = (s as f64 + 0.5) / samples as f64;
+            let (x, y) = (from.0 + (to.0 - from.0) * u, from.1 + (to.1 - from.1) * u);
+            let id = y.floor() as usize * world.num_x_tiles as usize + x.floor() as usize;
+            visit(&world.tiles[id], length / samples as f64);
+        }
+    }
+
+    // Terrain cost of walking along the lines between the given tiles
+    fn line_cost(world: &WorldState, ids: &[usize]) -> f64 {
+        let mut cost = 0_f64;
+        for pair in ids.windows(2) {
+            walk_line(world, pair[0], pair[1], |t, length| {
+                cost += t.terrain.move_cost() as f64 * length;
+            });
+        }
+        cost
+    }
+
+    #[test]
+    fn pulled_waypoints_keep_line_of_sight_and_cost_no_more() {
+        let mut rng = Rng::new(5);
+        let mut pulled_paths = 0;
+        let movements = [Movement::Cardinal, Movement::Diagonal, Movement::DiagonalNoCornerCutting];
+        for movement in movements {
+            for round in 0..60 {
+                let (world, path) = random_path(&mut rng, movement);
+                if path.is_empty() {
+                    continue;
+                }
+                let waypoints = string_pull(&world.tiles, world.num_x_tiles as i32, &path);
+                assert_eq!(waypoints[0], world.start_id as usize);
+                assert_eq!(waypoints[waypoints.len() - 1], world.end_id as usize);
+                pulled_paths += i32::from(waypoints.len() < path.len());
+
+                // Waypoints are path tiles in path order, each line between
+                // two of them clear of walls and of terrain dearer than the
+                // stretch of path it replaces
+                let mut k = 0;
+                for pair in waypoints.windows(2) {
+                    let from = k + path[k..].iter().position(|id| *id == pair[0]).unwrap();
+                    let to = from + path[from..].iter().position(|id| *id == pair[1]).unwrap();
+                    let max_cost = path[from..=to]
+                        .iter()
+                        .map(|id| world.tiles[*id].terrain.move_cost())
+                        .max()
+                        .unwrap();
+                    if to > from + 1 {
+                        walk_line(&world, pair[0], pair[1], |t, _| {
+                            assert!(!t.is_wall(), "{} round {}", movement.name(), round);
+                            assert!(t.terrain.move_cost() <= max_cost);
+                        });
+                    }
+                    k = to;
+                }
+                let (pulled, tile_path) = (line_cost(&world, &waypoints), line_cost(&world, &path));
+                assert!(
+                    pulled <= tile_path + 1e-6,
+                    "{} round {}: {} over {}",
+                    movement.name(),
+                    round,
+                    pulled,
+                    tile_path
+                );
+            }
+        }
+        assert!(pulled_paths > 100, "only {} paths had anything to pull", pulled_paths);
+    }
+
+    #[test]
+    fn catmull_rom_passes_through_every_point() {
+        // A long straight, a short hop and a sharp turn back
+        let points = [
+            (0_f64, 0_f64),
+            (30_f64, 0_f64),
+            (35_f64, 10_f64),
+            (35_f64, 60_f64),
+            (0_f64, 60_f64),
+        ];
+        let curve = catmull_rom(&points);
+        assert_eq!(curve.len(), (points.len() - 1) * CURVE_SAMPLES + 1);
+        for (i, p) in points.iter().enumerate() {
+            let q = curve[i * CURVE_SAMPLES];
+            assert!((q.0 - p.0).abs() < 1e-9 && (q.1 - p.1).abs() < 1e-9, "point {}", i);
+        }
+        assert_eq!(curve[0], points[0]);
+        assert_eq!(curve[curve.len() - 1], points[points.len() - 1]);
+    }
+
+    #[test]
+    fn catmull_rom_keeps_up_to_two_points_as_they_are() {
+        assert!(catmull_rom(&[]).is_empty());
+        assert_eq!(catmull_rom(&[(4_f64, 2_f64)]), vec![(4_f64, 2_f64)]);
+        let line = [(4_f64, 2_f64), (10_f64, 20_f64)];
+        assert_eq!(catmull_rom(&line), line.to_vec());
+    }
+
+    #[test]
+    fn string_pull_keeps_short_paths() {
+        let (world, _) = random_path(&mut Rng::new(1), Movement::Cardinal);
+        let num_x_tiles = world.num_x_tiles as i32;
+        assert!(string_pull(&world.tiles, num_x_tiles, &[]).is_empty());
+        assert_eq!(string_pull(&world.tiles, num_x_tiles, &[0]), vec![0]);
+        assert_eq!(string_pull(&world.tiles, num_x_tiles, &[0, 1]), vec![0, 1]);
+    }
+}
@@ -0,0 +1,355 @@
+use std::collections::BinaryHeap;
+
+use super::{NodeState, OpenNode, SearchAlgorithm, SearchContext};
+
+// A*, Dijkstra and greedy best-first are the same loop over a binary heap,
+// they only differ in how G and H are combined into the priority.
+pub struct AStar;
+pub struct Dijkstra;
+pub struct GreedyBestFirst;
+
+impl SearchAlgorithm for AStar {
+    fn name(&self) -> &'static str {
+        "A*"
+    }
+
+    fn search(&self, ctx: &mut SearchContext) -> u32 {
+        best_first(ctx, |g, h| g + h)
+    }
+}
+
+impl SearchAlgorithm for Dijkstra {
+    fn name(&self) -> &'static str {
+        "Dijkstra"
+    }
+
+    fn search(&self, ctx: &mut SearchContext) -> u32 {
+        best_first(ctx, |g, _| g)
+    }
+}
+
+impl SearchAlgorithm for GreedyBestFirst {
+    fn name(&self) -> &'static str {
+        "Greedy best-first"
+    }
+
+    fn search(&self, ctx: &mut SearchContext) -> u32 {
+        best_first(ctx, |_, h| h)
+    }
+}
+
+fn best_first<F>(ctx: &mut SearchContext, priority: F) -> u32
+where
+    F: Fn(i32, i32) -> i32,
+{
+    let start_id = ctx.start_id;
+    let mut open_nodes = BinaryHeap::new();
+    let mut nodes_expanded = 0;
+
+    ctx.nodes.h[start_id] = ctx.calc_h(start_id, ctx.end_id);
+    ctx.nodes.f[start_id] = priority(0, ctx.nodes.h[start_id]);
+    ctx.nodes.state[start_id] = NodeState::Open;
+    open_nodes.push(OpenNode {
+        f: ctx.nodes.f[start_id],
+        h: ctx.nodes.h[start_id],
+        id: start_id,
+    });
+
+    // Stop searching when either:
+    // 1) target is closed, in which case the path has been found
+    // 2) failed to find the target and the open list is empty (no path)
+    // 3) the step budget ran out, the open/closed sets are left as they are
+    while nodes_expanded < ctx.max_expansions {
+        let current_node = match open_nodes.pop() {
+            Some(open_node) => open_node.id,
+            None => break,
+        };
+        // Skip stale heap entries left behind when a better G was found
+        if ctx.nodes.state[current_node] == NodeState::Closed {
+            continue;
+        }
+        ctx.nodes.state[current_node] = NodeState::Closed;
+        nodes_expanded += 1;
+        if current_node == ctx.end_id {
+            break;
+        }
+
+        // Check each side node.
+        // If the side exists (id >= 0)
+        // If it's a wall, it's not set as a side so we don't need to worry about it.
+        for s in ctx.tiles[current_node].side_ids().iter() {
+            if *s >= 0 && ctx.nodes.state[*s as usize] != NodeState::Closed {
+                check_node(ctx, &mut open_nodes, current_node, *s as usize, &priority);
+            }
+        }
+    }
+    nodes_expanded
+}
+
+fn check_node<F>(
+    ctx: &mut SearchContext,
+    open_nodes: &mut BinaryHeap<OpenNode>,
+    curr_node_id: usize,
+    side_node_id: usize,
+    priority: &F,
+) where
+    F: Fn(i32, i32) -> i32,
+{
+    let id = side_node_id;
+    let new_g = ctx.nodes.g[curr_node_id] + ctx.move_cost(curr_node_id, id);
+    // if it's not already on the open list
+    if ctx.nodes.state[id] == NodeState::Unvisited {
+        ctx.nodes.state[id] = NodeState::Open;
+        ctx.nodes.h[id] = ctx.calc_h(id, ctx.end_id);
+    }
+    // if it's already on the open list and the path is not better (lower G value)
+    else if ctx.nodes.g[id] <= new_g {
+        return;
+    }
+    ctx.tiles[id].parent_id = curr_node_id as i32;
+    ctx.nodes.g[id] = new_g;
+    ctx.nodes.f[id] = priority(new_g, ctx.nodes.h[id]);
+    open_nodes.push(OpenNode {
+        f: ctx.nodes.f[id],
+        h: ctx.nodes.h[id],
+        id,
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{HashSet, VecDeque};
+
+    use crate::utils::Rng;
+    use crate::world::tile::MOVE_COST;
+    use crate::world::{Algorithm, Generator, Heuristic, Movement, Terrain, Tile, WorldState};
+
+    // A generated map flattened to the grass/wall maps the sort-based
+    // calc_astar ran on
+    fn seeded_map(seed: u32, generator: Generator) -> String {
+        let (num_x_tiles, num_y_tiles) = (31, 21);
+        let map = generator.generate(&mut Rng::new(seed), num_x_tiles, num_y_tiles);
+        let target = |id: usize| (id as u32 % num_x_tiles, id as u32 / num_x_tiles);
+        let (start, end) = (target(map.start_id), target(map.end_id));
+        let mut text = format!("start {},{}\nend {},{}\n", start.0, start.1, end.0, end.1);
+        for row in map.terrain.chunks(num_x_tiles as usize) {
+            let codes: Vec<&str> = row
+                .iter()
+                .map(|t| if *t == Terrain::Wall { "1" } else { "0" })
+                .collect();
+            text.push_str(&codes.join(","));
+            text.push('\n');
+        }
+        text
+    }
+
+    // The sort-based calc_astar this module replaced, kept as the reference:
+    // Manhattan H, cardinal moves of MOVE_COST, the open list sorted by F
+    // every expansion. Returns the parent_id of every tile.
+    fn baseline_astar(tiles: &[Tile], start_id: usize, end_id: usize) -> Vec<i32> {
+        let end = &tiles[end_id];
+        let h: Vec<i32> = tiles
+            .iter()
+            .map(|t| ((t.x_id - end.x_id).abs() + (t.y_id - end.y_id).abs()) * MOVE_COST)
+            .collect();
+        let mut g = vec![0; tiles.len()];
+        let mut f = vec![0; tiles.len()];
+        let mut parent_ids = vec![-1; tiles.len()];
+        let mut open_nodes: Vec<usize> = vec![start_id];
+        let mut closed_nodes = HashSet::new();
+        while !closed_nodes.contains(&end_id) && !open_nodes.is_empty() {
+            open_nodes.sort_by(|a, b| f[*a].cmp(&f[*b]));
+            let current_node = open_nodes.swap_remove(0);
+            closed_nodes.insert(current_node);
+            let t = &tiles[current_node];
+            for s in [t.top, t.bottom, t.right, t.left] {
+                let id = s as usize;
+                if s < 0 || closed_nodes.contains(&id) {
+                    continue;
+                }
+                if !open_nodes.contains(&id) {
+                    open_nodes.push(id);
+                } else if g[id] <= g[current_node] + MOVE_COST {
+                    continue;
+                }
+                parent_ids[id] = current_node as i32;
+                g[id] = g[current_node] + MOVE_COST;
+                f[id] = g[id] + h[id];
+            }
+        }
+        parent_ids
+    }
+
+    // Tiles from the end back to the start, following parent ids
+    fn chain(parent_ids: &[i32], end_id: usize) -> Vec<usize> {
+        let mut path = vec![end_id];
+        while parent_ids[path[path.len() - 1]] >= 0 {
+            path.push(parent_ids[path[path.len() - 1]] as usize);
+        }
+        path
+    }
+
+    // Number of different shortest paths from start to end, capped at 2
+    fn shortest_path_count(tiles: &[Tile], start_id: usize, end_id: usize) -> u32 {
+        let mut dist = vec![u32::MAX; tiles.len()];
+        let mut count = vec![0_u32; tiles.len()];
+        let mut queue = VecDeque::from([start_id]);
+        dist[start_id] = 0;
+        count[start_id] = 1;
+        while let Some(id) = queue.pop_front() {
+            let t = &tiles[id];
+            for s in [t.top, t.bottom, t.right, t.left].into_iter().filter(|s| *s >= 0) {
+                let side_id = s as usize;
+                if dist[side_id] == u32::MAX {
+                    dist[side_id] = dist[id] + 1;
+                    queue.push_back(side_id);
+                }
+                if dist[side_id] == dist[id] + 1 {
+                    count[side_id] = (count[side_id] + count[id]).min(2);
+                }
+            }
+        }
+        count[end_id]
+    }
+
+    // Ties between equally cheap paths are broken differently (lowest H then
+    // lowest id instead of wherever swap_remove left them), so the chains
+    // only have to match where the cheapest path is the only one.
+    #[test]
+    fn heap_astar_matches_sort_based_baseline() {
+        let mut world = WorldState::new();
+        let mut unique = 0;
+        for seed in 1..=200 {
+            // Noise maps have lots of equally cheap paths, mazes have only one
+            let generator = if seed % 2 == 0 {
+                Generator::Noise
+            } else {
+                Generator::RecursiveBacktracker
+            };
+            world.load_map_text(&seeded_map(seed, generator)).unwrap();
+            world.calc_path();
+            let (start_id, end_id) = (world.start_id as usize, world.end_id as usize);
+            let expected = chain(&baseline_astar(&world.tiles, start_id, end_id), end_id);
+            let parent_ids: Vec<i32> = world.tiles.iter().map(|t| t.parent_id).collect();
+            let found = chain(&parent_ids, end_id);
+
+            assert_eq!(chain_cost(&world, &found), chain_cost(&world, &expected), "seed {}", seed);
+            assert_eq!(found[found.len() - 1], start_id, "seed {}", seed);
+            for pair in found.windows(2) {
+                let t = &world.tiles[pair[1]];
+                assert!(t.side_ids().contains(&(pair[0] as i32)), "seed {}", seed);
+            }
+            if shortest_path_count(&world.tiles, start_id, end_id) == 1 {
+                assert_eq!(found, expected, "seed {}", seed);
+                unique += 1;
+            }
+        }
+        // The mazes at least
+        assert!(unique >= 100, "only {} maps with a unique cheapest path", unique);
+    }
+
+    #[test]
+    fn heap_astar_matches_baseline_on_test_map() {
+        let mut world = WorldState::new();
+        world.load_test_map();
+        let (start_id, end_id) = (world.start_id as usize, world.end_id as usize);
+        let expected = chain(&baseline_astar(&world.tiles, start_id, end_id), end_id);
+        let parent_ids: Vec<i32> = world.tiles.iter().map(|t| t.parent_id).collect();
+        let found = chain(&parent_ids, end_id);
+        assert!(expected.len() > 1);
+        assert_eq!(found[found.len() - 1], start_id);
+        for pair in found.windows(2) {
+            assert!(world.tiles[pair[1]].side_ids().contains(&(pair[0] as i32)));
+        }
+        assert_eq!(path_cost(&world), chain_cost(&world, &expected));
+        if shortest_path_count(&world.tiles, start_id, end_id) == 1 {
+            assert_eq!(found, expected);
+        }
+    }
+
+    #[test]
+    fn heap_astar_leaves_no_chain_when_the_end_is_walled_off() {
+        let mut world = WorldState::new();
+        world
+            .load_map_text("start 0,0\nend 4,2\n0,0,0,0,0\n0,0,0,1,1\n0,0,0,1,0\n")
+            .unwrap();
+        let (start_id, end_id) = (world.start_id as usize, world.end_id as usize);
+        assert_eq!(chain(&baseline_astar(&world.tiles, start_id, end_id), end_id), vec![end_id]);
+        let parent_ids: Vec<i32> = world.tiles.iter().map(|t| t.parent_id).collect();
+        assert_eq!(chain(&parent_ids, end_id), vec![end_id]);
+    }
+
+    // Cost of a chain from chain(), stepping from each tile's parent onto it
+    fn chain_cost(world: &WorldState, path: &[usize]) -> i32 {
+        path.windows(2)
+            .map(|pair| world.tiles[pair[1]].move_cost_to(&world.tiles[pair[0]]))
+            .sum()
+    }
+
+    fn path_cost(world: &WorldState) -> i32 {
+        let parent_ids: Vec<i32> = world.tiles.iter().map(|t| t.parent_id).collect();
+        chain_cost(world, &chain(&parent_ids, world.end_id as usize))
+    }
+
+    // Octile, Chebyshev and Euclidean are admissible in every movement mode
+    // and Manhattan with cardinal moves, so A* has to find paths as cheap as
+    // Dijkstra's. All grass maps, cheaper terrain would scale the heuristics
+    // down and hide an overestimate.
+    #[test]
+    fn astar_is_optimal_with_every_heuristic() {
+        let mut world = WorldState::new();
+        for movement in [Movement::Cardinal, Movement::Diagonal, Movement::DiagonalNoCornerCutting] {
+            world.set_movement(movement);
+            for seed in 1..=50 {
+                world.load_map_text(&seeded_map(seed, Generator::Noise)).unwrap();
+                world.algorithm = Algorithm::Dijkstra;
+                world.calc_path();
+                let cheapest = path_cost(&world);
+                world.algorithm = Algorithm::AStar;
+                for heuristic in [Heuristic::Manhattan, Heuristic::Octile, Heuristic::Chebyshev, Heuristic::Euclidean] {
+                    // Manhattan overestimates diagonal moves
+                    if heuristic == Heuristic::Manhattan && movement != Movement::Cardinal {
+                        continue;
+                    }
+                    world.heuristic = heuristic;
+                    world.calc_path();
+                    assert_eq!(path_cost(&world), cheapest, "{} seed {}", heuristic.name(), seed);
+                }
+            }
+        }
+    }
+
+    // A big open map with scattered walls and a target far off along a
+    // diagonal, where scaling Euclidean by MOVE_COST once overestimated
+    // enough for A* to settle for a costlier path
+    #[test]
+    fn euclidean_astar_is_optimal_on_long_diagonals() {
+        let (size, end_y) = (250, 249);
+        let mut rng = Rng::new(40);
+        let mut text = format!("start 0,0\nend {},{}\n", size - 1, end_y);
+        for y in 0..size {
+            let row: Vec<&str> = (0..size)
+                .map(|x| {
+                    let target = (x, y) == (0, 0) || (x, y) == (size - 1, end_y);
+                    if !target && rng.random() < 0.1 {
+                        "1"
+                    } else {
+                        "0"
+                    }
+                })
+                .collect();
+            text.push_str(&row.join(","));
+            text.push('\n');
+        }
+        let mut world = WorldState::new();
+        world.set_movement(Movement::Diagonal);
+        world.load_map_text(&text).unwrap();
+        world.algorithm = Algorithm::Dijkstra;
+        world.calc_path();
+        let cheapest = path_cost(&world);
+        world.algorithm = Algorithm::AStar;
+        world.heuristic = Heuristic::Euclidean;
+        world.calc_path();
+        assert_eq!(path_cost(&world), cheapest);
+    }
+}
@@ -0,0 +1,49 @@
+use std::collections::VecDeque;
+
+use super::{NodeState, SearchAlgorithm, SearchContext};
+
+// Plain FIFO flood fill. Ignores H and step costs entirely, so it finds the
+// path with the fewest steps, which is only the cheapest when every step costs the same.
+pub struct BreadthFirst;
+
+impl SearchAlgorithm for BreadthFirst {
+    fn name(&self) -> &'static str {
+        "Breadth-first"
+    }
+
+    fn search(&self, ctx: &mut SearchContext) -> u32 {
+        let mut queue = VecDeque::new();
+        let mut nodes_expanded = 0;
+
+        ctx.nodes.state[ctx.start_id] = NodeState::Open;
+        queue.push_back(ctx.start_id);
+
+        while nodes_expanded < ctx.max_expansions {
+            let current_node = match queue.pop_front() {
+                Some(id) => id,
+                None => break,
+            };
+            ctx.nodes.state[current_node] = NodeState::Closed;
+            nodes_expanded += 1;
+            if current_node == ctx.end_id {
+                break;
+            }
+
+            for s in ctx.tiles[current_node].side_ids().iter() {
+                if *s < 0 {
+                    continue;
+                }
+                let id = *s as usize;
+                // First visit is the shortest in steps, never re-parent a node
+                if ctx.nodes.state[id] == NodeState::Unvisited {
+                    ctx.nodes.state[id] = NodeState::Open;
+                    ctx.nodes.g[id] = ctx.nodes.g[current_node] + ctx.move_cost(current_node, id);
+                    ctx.nodes.f[id] = ctx.nodes.g[id];
+                    ctx.tiles[id].parent_id = current_node as i32;
+                    queue.push_back(id);
+                }
+            }
+        }
+        nodes_expanded
+    }
+}
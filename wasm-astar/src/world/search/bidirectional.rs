@@ -0,0 +1,241 @@
+use std::collections::BinaryHeap;
+
+use super::{NodeState, OpenNode, SearchAlgorithm, SearchContext};
+
+// Runs one A* from the start and one from the end, alternating expansions,
+// and joins them at the cheapest node both sides have reached.
+pub struct BidirectionalAStar;
+
+// One direction of the search. The forward side targets the end tile,
+// the backward side targets the start tile.
+struct Frontier {
+    target_id: usize,
+    reversed: bool,
+    open_nodes: BinaryHeap<OpenNode>,
+    state: Vec<NodeState>,
+    g: Vec<i32>,
+    h: Vec<i32>,
+    parent: Vec<i32>,
+}
+
+// Cheapest node reached from both sides so far.
+struct Meet {
+    id: Option<usize>,
+    cost: i32,
+}
+
+impl Frontier {
+    fn new(ctx: &SearchContext, origin_id: usize, target_id: usize, reversed: bool) -> Frontier {
+        let num_nodes = ctx.tiles.len();
+        let mut frontier = Frontier {
+            target_id,
+            reversed,
+            open_nodes: BinaryHeap::new(),
+            state: vec![NodeState::Unvisited; num_nodes],
+            g: vec![0; num_nodes],
+            h: vec![0; num_nodes],
+            parent: vec![-1; num_nodes],
+        };
+        frontier.h[origin_id] = ctx.calc_h(origin_id, target_id);
+        frontier.state[origin_id] = NodeState::Open;
+        frontier.open_nodes.push(OpenNode {
+            f: frontier.h[origin_id],
+            h: frontier.h[origin_id],
+            id: origin_id,
+        });
+        frontier
+    }
+
+    // Lowest F still waiting on this side. Stale entries can only make this
+    // an underestimate, which keeps the stopping check safe.
+    fn min_f(&self) -> Option<i32> {
+        self.open_nodes.peek().map(|n| n.f)
+    }
+
+    // Expands the lowest F node, returns false when the popped entry was stale.
+    fn expand(&mut self, ctx: &SearchContext, other: &Frontier, meet: &mut Meet) -> bool {
+        let current_node = match self.open_nodes.pop() {
+            Some(open_node) => open_node.id,
+            None => return false,
+        };
+        if self.state[current_node] == NodeState::Closed {
+            return false;
+        }
+        self.state[current_node] = NodeState::Closed;
+        // Side links never point into a wall, so walking them backwards
+        // must not leave one either (the end tile can be a wall).
+        if self.reversed && ctx.tiles[current_node].is_wall() {
+            return true;
+        }
+
+        for s in ctx.tiles[current_node].side_ids().iter() {
+            if *s < 0 || self.state[*s as usize] == NodeState::Closed {
+                continue;
+            }
+            let id = *s as usize;
+            // The backward side walks the real path in reverse, so it pays for
+            // stepping from the side onto the current node.
+            let step_cost = if self.reversed {
+                ctx.move_cost(id, current_node)
+            } else {
+                ctx.move_cost(current_node, id)
+            };
+            let new_g = self.g[current_node] + step_cost;
+            if self.state[id] == NodeState::Unvisited {
+                self.state[id] = NodeState::Open;
+                self.h[id] = ctx.calc_h(id, self.target_id);
+            } else if self.g[id] <= new_g {
+                continue;
+            }
+            self.parent[id] = current_node as i32;
+            self.g[id] = new_g;
+            self.open_nodes.push(OpenNode {
+                f: new_g + self.h[id],
+                h: self.h[id],
+                id,
+            });
+            if other.state[id] != NodeState::Unvisited && new_g + other.g[id] < meet.cost {
+                meet.id = Some(id);
+                meet.cost = new_g + other.g[id];
+            }
+        }
+        true
+    }
+}
+
+impl SearchAlgorithm for BidirectionalAStar {
+    fn name(&self) -> &'static str {
+        "Bidirectional A*"
+    }
+
+    fn search(&self, ctx: &mut SearchContext) -> u32 {
+        let mut forward = Frontier::new(ctx, ctx.start_id, ctx.end_id, false);
+        let mut backward = Frontier::new(ctx, ctx.end_id, ctx.start_id, true);
+        let mut nodes_expanded = 0;
+        let mut meet = if ctx.start_id == ctx.end_id {
+            Meet {
+                id: Some(ctx.start_id),
+                cost: 0,
+            }
+        } else {
+            Meet {
+                id: None,
+                cost: i32::MAX,
+            }
+        };
+        let mut expand_forward = true;
+
+        // Every unexplored path has to pass through both open lists, so once
+        // either side's lowest F reaches the best joined cost nothing can beat it.
+        while let (Some(forward_f), Some(backward_f)) = (forward.min_f(), backward.min_f()) {
+            if forward_f >= meet.cost || backward_f >= meet.cost || nodes_expanded >= ctx.max_expansions {
+                break;
+            }
+            let expanded = if expand_forward {
+                forward.expand(ctx, &backward, &mut meet)
+            } else {
+                backward.expand(ctx, &forward, &mut meet)
+            };
+            if expanded {
+                nodes_expanded += 1;
+                expand_forward = !expand_forward;
+            }
+        }
+
+        // Record what each side saw for debug drawing, forward values take priority.
+        for id in 0..ctx.tiles.len() {
+            let (state, g, h) = if forward.state[id] != NodeState::Unvisited {
+                (forward.state[id], forward.g[id], forward.h[id])
+            } else {
+                (backward.state[id], backward.g[id], backward.h[id])
+            };
+            ctx.nodes.state[id] = state;
+            ctx.nodes.g[id] = g;
+            ctx.nodes.h[id] = h;
+            ctx.nodes.f[id] = g + h;
+        }
+
+        // Stitch both halves into the single parent_id chain ending at the end tile.
+        if let Some(meet_id) = meet.id {
+            let mut id = meet_id;
+            while forward.parent[id] >= 0 {
+                ctx.tiles[id].parent_id = forward.parent[id];
+                id = forward.parent[id] as usize;
+            }
+            let mut id = meet_id;
+            while backward.parent[id] >= 0 {
+                let next_id = backward.parent[id] as usize;
+                ctx.tiles[next_id].parent_id = id as i32;
+                id = next_id;
+            }
+        }
+        nodes_expanded
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::utils::Rng;
+    use crate::world::{Algorithm, Heuristic, Movement, WorldState};
+
+    // A 24x16 map of grass, road, swamp and walls, start and end anywhere open
+    fn random_map(rng: &mut Rng) -> String {
+        let (num_x_tiles, num_y_tiles) = (24, 16);
+        let start = (rng.random_range(0, num_x_tiles - 1), rng.random_range(0, num_y_tiles - 1));
+        let end = (rng.random_range(0, num_x_tiles - 1), rng.random_range(0, num_y_tiles - 1));
+        let mut text = format!("start {},{}\nend {},{}\n", start.0, start.1, end.0, end.1);
+        for y in 0..num_y_tiles {
+            let row: Vec<&str> = (0..num_x_tiles)
+                .map(|x| {
+                    let target = (x, y) == start || (x, y) == end;
+                    if !target && rng.random() < 0.25 {
+                        "1"
+                    } else {
+                        ["0", "2", "3"][rng.random_range(0, 2) as usize]
+                    }
+                })
+                .collect();
+            text.push_str(&row.join(","));
+            text.push('\n');
+        }
+        text
+    }
+
+    // Cost of the current path, None when it doesn't lead back to the start
+    fn path_cost(world: &WorldState) -> Option<i32> {
+        let mut cost = 0;
+        let mut id = world.end_id as usize;
+        while world.tiles[id].parent_id >= 0 {
+            let parent = &world.tiles[world.tiles[id].parent_id as usize];
+            cost += parent.move_cost_to(&world.tiles[id]);
+            id = world.tiles[id].parent_id as usize;
+        }
+        Some(cost).filter(|_| id == world.start_id as usize)
+    }
+
+    // The two sides meet somewhere in the middle, the joined path still has
+    // to cost what Dijkstra's does, and neither finds one when there is none
+    #[test]
+    fn bidirectional_matches_dijkstra_in_every_movement_mode() {
+        let mut rng = Rng::new(11);
+        let mut world = WorldState::new();
+        // Admissible in every movement mode, unlike the default Manhattan
+        world.heuristic = Heuristic::Octile;
+        let mut unreachable = 0;
+        let movements = [Movement::Cardinal, Movement::Diagonal, Movement::DiagonalNoCornerCutting];
+        for movement in movements {
+            world.set_movement(movement);
+            for round in 0..100 {
+                world.load_map_text(&random_map(&mut rng)).unwrap();
+                world.algorithm = Algorithm::Dijkstra;
+                world.calc_path();
+                let cheapest = path_cost(&world);
+                unreachable += i32::from(cheapest.is_none());
+                world.algorithm = Algorithm::BidirectionalAStar;
+                world.calc_path();
+                assert_eq!(path_cost(&world), cheapest, "{} round {}", movement.name(), round);
+            }
+        }
+        assert!(unreachable < 300, "no map had a path");
+    }
+}
@@ -0,0 +1,381 @@
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+
+use super::{NodeState, SearchAlgorithm, SearchContext};
+use crate::world::Heuristic;
+
+// D* Lite (Koenig & Likhachev). Searches backwards from the end tile so the
+// G values are costs to the end, which stay valid when the start moves.
+// A Planner kept between frames only has to repair what changed: moving the
+// start just bumps km, toggling a wall re-evaluates the tiles around it.
+pub struct DStarLite;
+
+impl SearchAlgorithm for DStarLite {
+    fn name(&self) -> &'static str {
+        "D* Lite"
+    }
+
+    // One-off run with a fresh planner, used when stepping through the search.
+    fn search(&self, ctx: &mut SearchContext) -> u32 {
+        let mut planner = Planner::new();
+        let (nodes_expanded, _) = planner.plan(ctx, &[]);
+        planner.record_nodes(ctx);
+        nodes_expanded
+    }
+}
+
+// Kept well below i32::MAX so adding a step cost to it can't overflow
+const INF: i32 = i32::MAX / 4;
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+struct Key(i32, i32);
+
+#[derive(PartialEq, Eq)]
+struct QueueEntry {
+    key: Key,
+    id: usize,
+}
+
+impl Ord for QueueEntry {
+    // Reversed for BinaryHeap, lowest key first then lowest id.
+    fn cmp(&self, other: &QueueEntry) -> Ordering {
+        other.key.cmp(&self.key).then_with(|| other.id.cmp(&self.id))
+    }
+}
+
+impl PartialOrd for QueueEntry {
+    fn partial_cmp(&self, other: &QueueEntry) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+pub struct Planner {
+    g: Vec<i32>,
+    rhs: Vec<i32>,
+    // Key a node is queued with, None when it isn't queued. Heap entries with
+    // any other key are stale and skipped.
+    queued: Vec<Option<Key>>,
+    queue: BinaryHeap<QueueEntry>,
+    km: i32,
+    start_id: usize,
+    end_id: usize,
+    heuristic: Heuristic,
+    min_move_cost: i32,
+    valid: bool,
+}
+
+impl Planner {
+    pub fn new() -> Planner {
+        Planner {
+            g: Vec::new(),
+            rhs: Vec::new(),
+            queued: Vec::new(),
+            queue: BinaryHeap::new(),
+            km: 0,
+            start_id: 0,
+            end_id: 0,
+            heuristic: Heuristic::Manhattan,
+            min_move_cost: 0,
+            valid: false,
+        }
+    }
+
+    // Forces the next plan to start over, for when the whole map or its
+    // side links were rebuilt.
+    pub fn invalidate(&mut self) {
+        self.valid = false;
+    }
+
+    // Plans from ctx.start_id to ctx.end_id and writes the parent_id chain.
+    // changed_ids are tiles whose terrain or side links changed since the last plan.
+    // Returns the nodes expanded and whether the previous search was reused.
+    pub fn plan(&mut self, ctx: &mut SearchContext, changed_ids: &[usize]) -> (u32, bool) {
+        let reusable = self.valid
+            && self.g.len() == ctx.tiles.len()
+            && self.end_id == ctx.end_id
+            && self.heuristic == ctx.heuristic
+            && self.min_move_cost == ctx.min_move_cost;
+        if reusable {
+            if self.start_id != ctx.start_id {
+                self.km += ctx.calc_h(self.start_id, ctx.start_id);
+                self.start_id = ctx.start_id;
+                // Nothing links into a wall, so a wall start is never reached as
+                // a predecessor and has to be brought up to date by hand.
+                if ctx.tiles[ctx.start_id].is_wall() {
+                    self.update_rhs(ctx, ctx.start_id);
+                }
+            }
+            for id in changed_ids.iter() {
+                self.update_rhs(ctx, *id);
+            }
+        } else {
+            self.reset(ctx);
+        }
+
+        let nodes_expanded = self.compute_shortest_path(ctx);
+        self.write_path(ctx);
+        (nodes_expanded, reusable)
+    }
+
+    fn reset(&mut self, ctx: &SearchContext) {
+        let num_nodes = ctx.tiles.len();
+        self.g.clear();
+        self.g.resize(num_nodes, INF);
+        self.rhs.clear();
+        self.rhs.resize(num_nodes, INF);
+        self.queued.clear();
+        self.queued.resize(num_nodes, None);
+        self.queue.clear();
+        self.km = 0;
+        self.start_id = ctx.start_id;
+        self.end_id = ctx.end_id;
+        self.heuristic = ctx.heuristic;
+        self.min_move_cost = ctx.min_move_cost;
+        self.valid = true;
+        self.rhs[self.end_id] = 0;
+        self.update_vertex(ctx, self.end_id);
+    }
+
+    fn calc_key(&self, ctx: &SearchContext, id: usize) -> Key {
+        let best = self.g[id].min(self.rhs[id]);
+        Key(best + ctx.calc_h(id, self.start_id) + self.km, best)
+    }
+
+    fn update_vertex(&mut self, ctx: &SearchContext, id: usize) {
+        if self.g[id] != self.rhs[id] {
+            let key = self.calc_key(ctx, id);
+            self.queued[id] = Some(key);
+            self.queue.push(QueueEntry { key, id });
+        } else {
+            self.queued[id] = None;
+        }
+    }
+
+    // Recomputes rhs from the node's sides, the end tile's rhs is always 0.
+    fn update_rhs(&mut self, ctx: &SearchContext, id: usize) {
+        if id != self.end_id {
+            self.rhs[id] = self.best_side(ctx, id).map_or(INF, |(_, cost)| cost);
+        }
+        self.update_vertex(ctx, id);
+    }
+
+    // Side with the cheapest cost to the end through it.
+    fn best_side(&self, ctx: &SearchContext, id: usize) -> Option<(usize, i32)> {
+        ctx.tiles[id]
+            .side_ids()
+            .iter()
+            .filter(|s| **s >= 0)
+            .map(|s| *s as usize)
+            .filter(|s| self.g[*s] < INF)
+            .map(|s| (s, ctx.move_cost(id, s) + self.g[s]))
+            .min_by_key(|(s, cost)| (*cost, *s))
+    }
+
+    // Tiles with a side link onto id. Links are symmetric between open tiles,
+    // walls are never linked into and the only wall that matters is the start.
+    fn predecessors(&self, ctx: &SearchContext, id: usize) -> Vec<usize> {
+        if ctx.tiles[id].is_wall() {
+            return Vec::new();
+        }
+        let mut preds: Vec<usize> = ctx.tiles[id]
+            .side_ids()
+            .iter()
+            .filter(|s| **s >= 0)
+            .map(|s| *s as usize)
+            .collect();
+        let start = &ctx.tiles[self.start_id];
+        if start.is_wall() && start.side_ids().contains(&(id as i32)) {
+            preds.push(self.start_id);
+        }
+        preds
+    }
+
+    fn top(&mut self) -> Option<(Key, usize)> {
+        while let Some(entry) = self.queue.peek() {
+            if self.queued[entry.id] == Some(entry.key) {
+                return Some((entry.key, entry.id));
+            }
+            self.queue.pop();
+        }
+        None
+    }
+
+    fn compute_shortest_path(&mut self, ctx: &SearchContext) -> u32 {
+        let start_id = self.start_id;
+        let mut nodes_expanded = 0;
+        while nodes_expanded < ctx.max_expansions {
+            let (old_key, id) = match self.top() {
+                Some(top) => top,
+                None => break,
+            };
+            if old_key >= self.calc_key(ctx, start_id) && self.rhs[start_id] <= self.g[start_id] {
+                break;
+            }
+            let new_key = self.calc_key(ctx, id);
+            if old_key < new_key {
+                // Queued before km last grew, try again with its current key
+                self.update_vertex(ctx, id);
+                continue;
+            }
+            self.queue.pop();
+            self.queued[id] = None;
+            nodes_expanded += 1;
+
+            if self.g[id] > self.rhs[id] {
+                self.g[id] = self.rhs[id];
+                for pred in self.predecessors(ctx, id) {
+                    if pred != self.end_id {
+                        let cost = ctx.move_cost(pred, id) + self.g[id];
+                        self.rhs[pred] = self.rhs[pred].min(cost);
+                    }
+                    self.update_vertex(ctx, pred);
+                }
+            } else {
+                let old_g = self.g[id];
+                self.g[id] = INF;
+                for pred in self.predecessors(ctx, id) {
+                    if self.rhs[pred] == ctx.move_cost(pred, id) + old_g {
+                        self.update_rhs(ctx, pred);
+                    }
+                }
+                self.update_vertex(ctx, id);
+            }
+        }
+        nodes_expanded
+    }
+
+    // Follows the cheapest sides from the start. The start itself can be left
+    // overconsistent (only its rhs is up to date), past that the cost to the
+    // end strictly drops each step so the chain can't loop even if the search
+    // stopped early.
+    fn write_path(&self, ctx: &mut SearchContext) {
+        let mut id = self.start_id;
+        let mut cost_to_end = self.rhs[id].min(self.g[id]);
+        while id != self.end_id {
+            let next_id = match self.best_side(ctx, id) {
+                Some((next_id, _)) if self.g[next_id] < cost_to_end => next_id,
+                _ => break,
+            };
+            ctx.tiles[next_id].parent_id = id as i32;
+            cost_to_end = self.g[next_id];
+            id = next_id;
+        }
+    }
+
+    // Copies queue membership and G (cost to the end, rhs for nodes still
+    // waiting in the queue) into SearchNodes for drawing.
+    fn record_nodes(&self, ctx: &mut SearchContext) {
+        for id in 0..ctx.tiles.len() {
+            ctx.nodes.state[id] = if self.queued[id].is_some() {
+                NodeState::Open
+            } else if self.g[id] < INF {
+                NodeState::Closed
+            } else {
+                continue;
+            };
+            ctx.nodes.g[id] = self.g[id].min(self.rhs[id]);
+            ctx.nodes.h[id] = ctx.calc_h(id, self.start_id);
+            ctx.nodes.f[id] = ctx.nodes.g[id] + ctx.nodes.h[id];
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::utils::Rng;
+    use crate::world::{Algorithm, Heuristic, Movement, Terrain, WorldState};
+
+    const TERRAINS: [Terrain; 4] = [Terrain::Grass, Terrain::Road, Terrain::Swamp, Terrain::Wall];
+
+    // A 24x16 map with a fifth of the tiles walls and the rest grass, road or
+    // swamp. Start and end are road so the cheapest terrain never changes
+    // under the edits, which would make the planner start over.
+    fn random_map(rng: &mut Rng) -> String {
+        let (num_x_tiles, num_y_tiles) = (24, 16);
+        let mut text = format!("start 0,0\nend {},{}\n", num_x_tiles - 1, num_y_tiles - 1);
+        for y in 0..num_y_tiles {
+            let row: Vec<&str> = (0..num_x_tiles)
+                .map(|x| {
+                    if (x, y) == (0, 0) || (x, y) == (num_x_tiles - 1, num_y_tiles - 1) {
+                        "2"
+                    } else if rng.random() < 0.2 {
+                        "1"
+                    } else {
+                        ["0", "2", "3"][rng.random_range(0, 2) as usize]
+                    }
+                })
+                .collect();
+            text.push_str(&row.join(","));
+            text.push('\n');
+        }
+        text
+    }
+
+    // Cost of the current path, None when it doesn't lead back to the start
+    fn path_cost(world: &WorldState) -> Option<i32> {
+        let mut cost = 0;
+        let mut id = world.end_id as usize;
+        while world.tiles[id].parent_id >= 0 {
+            let parent = &world.tiles[world.tiles[id].parent_id as usize];
+            cost += parent.move_cost_to(&world.tiles[id]);
+            id = world.tiles[id].parent_id as usize;
+        }
+        Some(cost).filter(|_| id == world.start_id as usize)
+    }
+
+    // Every repair has to come out as cheap as a fresh Dijkstra run on the
+    // edited map, without the planner ever starting over
+    #[test]
+    fn repaired_paths_match_a_fresh_dijkstra() {
+        let mut rng = Rng::new(3);
+        let movements = [Movement::Cardinal, Movement::Diagonal, Movement::DiagonalNoCornerCutting];
+        let mut reachable = 0;
+        for movement in movements {
+            for map in 0..5 {
+                let mut world = WorldState::new();
+                world.set_movement(movement);
+                // Admissible in every movement mode, unlike the default Manhattan
+                world.heuristic = Heuristic::Octile;
+                world.algorithm = Algorithm::DStarLite;
+                world.load_map_text(&random_map(&mut rng)).unwrap();
+                world.calc_path();
+                let (full, incremental) = (world.replan_stats.full, world.replan_stats.incremental);
+                for edit in 1..=40 {
+                    let num_tiles = world.tiles.len() as i32;
+                    // A few tiles at a time, now and then with the start moved too
+                    for _ in 0..rng.random_range(1, 3) {
+                        let id = rng.random_range(0, num_tiles - 1);
+                        if id != world.start_id && id != world.end_id {
+                            let terrain = TERRAINS[rng.random_range(0, 3) as usize];
+                            world.set_tile_terrain(id as usize, terrain);
+                        }
+                    }
+                    if edit % 5 == 0 {
+                        let id = rng.random_range(0, num_tiles - 1);
+                        if !world.tiles[id as usize].is_wall() && id != world.end_id {
+                            world.start_id = id;
+                        }
+                    }
+                    world.calc_path();
+
+                    let mut fresh = WorldState::new();
+                    fresh.set_movement(movement);
+                    fresh.algorithm = Algorithm::Dijkstra;
+                    fresh.load_map_text(&world.map_text()).unwrap();
+                    assert_eq!(
+                        path_cost(&world),
+                        path_cost(&fresh),
+                        "{} map {} edit {}",
+                        movement.name(),
+                        map,
+                        edit
+                    );
+                    reachable += i32::from(path_cost(&world).is_some());
+                    assert_eq!(world.replan_stats.full, full);
+                    assert_eq!(world.replan_stats.incremental, incremental + edit);
+                }
+            }
+        }
+        assert!(reachable > 300, "only {} edits left a path", reachable);
+    }
+}
@@ -0,0 +1,834 @@
+use std::collections::{BinaryHeap, HashMap};
+
+use wasm_bindgen::prelude::*;
+
+use super::{NodeState, OpenNode, SearchAlgorithm, SearchContext};
+use crate::world::Tile;
+
+// HPA* (Botea, Müller & Schaeffer). The map is cut into square clusters, the
+// tiles where a path can cross from one cluster into the next become
+// entrances and the cheapest paths between the entrances of each cluster are
+// worked out ahead of time. A query searches the small graph of entrances
+// and then fills in the tiles with an A* kept to the clusters it went through.
+//
+// A ClusterGraph kept between frames only rebuilds the clusters around tiles
+// that changed. Paths are close to the cheapest but not always the cheapest:
+// where the cheapest path crosses a cluster border, the entrance graph can
+// only cross at the entrance of that opening. Walking along the opening to
+// the entrance and back on the other side costs at most
+// ClusterStats::max_detour_per_border, so a path costs at most that much more
+// than the cheapest per cluster border the cheapest path crosses. Refining
+// only ever makes it cheaper.
+pub struct Hierarchical;
+
+impl SearchAlgorithm for Hierarchical {
+    fn name(&self) -> &'static str {
+        "HPA*"
+    }
+
+    // One-off run over a freshly built graph
+    fn search(&self, ctx: &mut SearchContext) -> u32 {
+        let (nodes_expanded, _) = ClusterGraph::new().plan(ctx);
+        nodes_expanded
+    }
+}
+
+// Tiles along each side of a cluster
+const CLUSTER_SIZE: i32 = 10;
+// Openings at least this wide get an entrance at both ends instead of one in the middle
+const WIDE_OPENING: i32 = 6;
+// Farthest a crossing can be from the entrance of its opening, in the middle
+// of an opening as wide as the cluster with entrances at both ends. Narrow
+// openings have theirs in the middle, at most (WIDE_OPENING - 1) / 2 away.
+const MAX_ENTRANCE_OFFSET: i32 = (CLUSTER_SIZE - 1) / 2;
+const INF: i32 = i32::MAX / 4;
+
+// Totals for the current graph, rebuilt_clusters is for the most recent plan.
+// max_detour_per_border is the most a path can cost over the cheapest one for
+// each cluster border the cheapest one crosses: MAX_ENTRANCE_OFFSET steps to
+// the entrance and back, plus one for a diagonal crossing, on both sides and
+// all on the costliest terrain of the map. path_cost is the cost of the
+// path the most recent plan found and max_path_detour that bound for the
+// borders it crosses, both 0 when there was none.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ClusterStats {
+    pub clusters: u32,
+    pub entrances: u32,
+    pub rebuilt_clusters: u32,
+    pub max_detour_per_border: u32,
+    pub path_cost: u32,
+    pub max_path_detour: u32,
+}
+
+// Tile rectangle, x0/y0 inclusive and x1/y1 exclusive
+#[derive(Clone, Copy)]
+struct Area {
+    x0: i32,
+    y0: i32,
+    x1: i32,
+    y1: i32,
+}
+
+impl Area {
+    fn contains(&self, t: &Tile) -> bool {
+        t.x_id >= self.x0 && t.x_id < self.x1 && t.y_id >= self.y0 && t.y_id < self.y1
+    }
+
+    fn index(&self, t: &Tile) -> usize {
+        ((t.y_id - self.y0) * (self.x1 - self.x0) + t.x_id - self.x0) as usize
+    }
+}
+
+// Tiles a search may visit, each with its own slot in the search arrays
+trait Region {
+    fn num_slots(&self) -> usize;
+    fn slot(&self, t: &Tile) -> Option<usize>;
+}
+
+impl Region for Area {
+    fn num_slots(&self) -> usize {
+        ((self.x1 - self.x0) * (self.y1 - self.y0)) as usize
+    }
+
+    fn slot(&self, t: &Tile) -> Option<usize> {
+        Some(self.index(t)).filter(|_| self.contains(t))
+    }
+}
+
+// A set of whole clusters, CLUSTER_SIZE squared slots each
+struct Corridor {
+    num_x_clusters: i32,
+    cluster_slots: HashMap<usize, usize>,
+}
+
+impl Corridor {
+    fn add(&mut self, t: &Tile) {
+        let cluster_id =
+            ((t.y_id / CLUSTER_SIZE) * self.num_x_clusters + t.x_id / CLUSTER_SIZE) as usize;
+        let next_slot = self.cluster_slots.len();
+        self.cluster_slots.entry(cluster_id).or_insert(next_slot);
+    }
+}
+
+impl Region for Corridor {
+    fn num_slots(&self) -> usize {
+        self.cluster_slots.len() * (CLUSTER_SIZE * CLUSTER_SIZE) as usize
+    }
+
+    fn slot(&self, t: &Tile) -> Option<usize> {
+        let cluster_id =
+            ((t.y_id / CLUSTER_SIZE) * self.num_x_clusters + t.x_id / CLUSTER_SIZE) as usize;
+        let local = (t.y_id % CLUSTER_SIZE) * CLUSTER_SIZE + t.x_id % CLUSTER_SIZE;
+        self.cluster_slots
+            .get(&cluster_id)
+            .map(|slot| slot * (CLUSTER_SIZE * CLUSTER_SIZE) as usize + local as usize)
+    }
+}
+
+struct Cluster {
+    area: Area,
+    // Tile ids of the entrances inside this cluster
+    entrances: Vec<usize>,
+    // Cheapest cost from entrance i to entrance j without leaving the
+    // cluster at costs[i * entrances.len() + j], INF when there is no way
+    costs: Vec<i32>,
+    // Tiles in other clusters each entrance steps straight onto
+    links: Vec<Vec<usize>>,
+    // Crossings on the right and bottom borders and through the bottom
+    // corners, the left and top borders belong to the neighbors
+    transitions: Vec<(usize, usize)>,
+    dirty: bool,
+}
+
+pub struct ClusterGraph {
+    num_x_tiles: i32,
+    num_y_tiles: i32,
+    num_x_clusters: i32,
+    num_y_clusters: i32,
+    clusters: Vec<Cluster>,
+    // Index into its cluster's entrances for each tile, -1 when it isn't one
+    entrance_index: Vec<i32>,
+    valid: bool,
+    pub stats: ClusterStats,
+}
+
+// Result of a search that stays inside a Region, indexed by Region::slot.
+// g is INF and parent usize::MAX for tiles that weren't reached.
+struct RegionSearch {
+    g: Vec<i32>,
+    parent: Vec<usize>,
+    nodes_expanded: u32,
+}
+
+impl ClusterGraph {
+    pub fn new() -> ClusterGraph {
+        ClusterGraph {
+            num_x_tiles: 0,
+            num_y_tiles: 0,
+            num_x_clusters: 0,
+            num_y_clusters: 0,
+            clusters: Vec::new(),
+            entrance_index: Vec::new(),
+            valid: false,
+            stats: ClusterStats::default(),
+        }
+    }
+
+    // Forces the next plan to rebuild every cluster, for when the whole map
+    // or its side links were rebuilt.
+    pub fn invalidate(&mut self) {
+        self.valid = false;
+    }
+
+    // changed_ids are tiles whose terrain or side links changed, their
+    // clusters are rebuilt on the next plan.
+    pub fn mark_changed(&mut self, changed_ids: &[usize]) {
+        if !self.valid {
+            return;
+        }
+        for id in changed_ids
+            .iter()
+            .filter(|id| **id < self.entrance_index.len())
+        {
+            let x = *id as i32 % self.num_x_tiles;
+            let y = *id as i32 / self.num_x_tiles;
+            let cluster_id = ((y / CLUSTER_SIZE) * self.num_x_clusters + x / CLUSTER_SIZE) as usize;
+            self.clusters[cluster_id].dirty = true;
+        }
+    }
+
+    // Plans from ctx.start_id to ctx.end_id and writes the parent_id chain.
+    // Returns the nodes expanded and whether the graph from the last plan was reused.
+    pub fn plan(&mut self, ctx: &mut SearchContext) -> (u32, bool) {
+        let reusable = self.valid
+            && self.num_x_tiles == ctx.num_x_tiles
+            && self.num_y_tiles == ctx.num_y_tiles
+            && self.entrance_index.len() == ctx.tiles.len();
+        if !reusable {
+            self.reset(ctx);
+        }
+        self.repair(ctx);
+        let detour_steps = 2 * (MAX_ENTRANCE_OFFSET + 1);
+        self.stats.max_detour_per_border = (detour_steps * ctx.max_move_cost) as u32;
+        let nodes_expanded = self.search(ctx);
+        let (path_cost, borders_crossed) = path_cost_and_borders(ctx);
+        self.stats.path_cost = path_cost;
+        self.stats.max_path_detour = borders_crossed * self.stats.max_detour_per_border;
+        (nodes_expanded, reusable)
+    }
+
+    fn reset(&mut self, ctx: &SearchContext) {
+        self.num_x_tiles = ctx.num_x_tiles;
+        self.num_y_tiles = ctx.num_y_tiles;
+        self.num_x_clusters = (ctx.num_x_tiles + CLUSTER_SIZE - 1) / CLUSTER_SIZE;
+        self.num_y_clusters = (ctx.num_y_tiles + CLUSTER_SIZE - 1) / CLUSTER_SIZE;
+        self.clusters.clear();
+        for cy in 0..self.num_y_clusters {
+            for cx in 0..self.num_x_clusters {
+                let area = Area {
+                    x0: cx * CLUSTER_SIZE,
+                    y0: cy * CLUSTER_SIZE,
+                    x1: ((cx + 1) * CLUSTER_SIZE).min(ctx.num_x_tiles),
+                    y1: ((cy + 1) * CLUSTER_SIZE).min(ctx.num_y_tiles),
+                };
+                self.clusters.push(Cluster {
+                    area,
+                    entrances: Vec::new(),
+                    costs: Vec::new(),
+                    links: Vec::new(),
+                    transitions: Vec::new(),
+                    dirty: true,
+                });
+            }
+        }
+        self.entrance_index.clear();
+        self.entrance_index.resize(ctx.tiles.len(), -1);
+        self.valid = true;
+    }
+
+    fn cluster_id(&self, cx: i32, cy: i32) -> Option<usize> {
+        if cx < 0 || cy < 0 || cx >= self.num_x_clusters || cy >= self.num_y_clusters {
+            return None;
+        }
+        Some((cy * self.num_x_clusters + cx) as usize)
+    }
+
+    fn cluster_of(&self, t: &Tile) -> usize {
+        ((t.y_id / CLUSTER_SIZE) * self.num_x_clusters + t.x_id / CLUSTER_SIZE) as usize
+    }
+
+    // A dirty cluster's crossings are on its own borders and its neighbors',
+    // and new crossings change the entrances on both sides, so everything in
+    // the 3x3 block around it is rebuilt.
+    fn repair(&mut self, ctx: &SearchContext) {
+        let num_clusters = self.clusters.len();
+        let mut owners = vec![false; num_clusters];
+        let mut affected = vec![false; num_clusters];
+        for cluster_id in 0..num_clusters {
+            if !self.clusters[cluster_id].dirty {
+                continue;
+            }
+            let cx = cluster_id as i32 % self.num_x_clusters;
+            let cy = cluster_id as i32 / self.num_x_clusters;
+            for (dx, dy) in [(0, 0), (-1, 0), (0, -1), (-1, -1), (1, -1)] {
+                if let Some(id) = self.cluster_id(cx + dx, cy + dy) {
+                    owners[id] = true;
+                }
+            }
+            for dy in -1..=1 {
+                for dx in -1..=1 {
+                    if let Some(id) = self.cluster_id(cx + dx, cy + dy) {
+                        affected[id] = true;
+                    }
+                }
+            }
+        }
+        for cluster_id in (0..num_clusters).filter(|id| owners[*id]) {
+            self.clusters[cluster_id].transitions = self.find_transitions(ctx, cluster_id);
+        }
+        let mut rebuilt_clusters = 0;
+        for cluster_id in (0..num_clusters).filter(|id| affected[*id]) {
+            self.build_entrances(ctx, cluster_id);
+            rebuilt_clusters += 1;
+        }
+        if rebuilt_clusters > 0 {
+            self.stats = ClusterStats {
+                clusters: num_clusters as u32,
+                entrances: self.clusters.iter().map(|c| c.entrances.len() as u32).sum(),
+                rebuilt_clusters,
+                ..self.stats
+            };
+        } else {
+            self.stats.rebuilt_clusters = 0;
+        }
+    }
+
+    fn find_transitions(&self, ctx: &SearchContext, cluster_id: usize) -> Vec<(usize, usize)> {
+        let area = self.clusters[cluster_id].area;
+        let cx = cluster_id as i32 % self.num_x_clusters;
+        let cy = cluster_id as i32 / self.num_x_clusters;
+        let has_right = cx + 1 < self.num_x_clusters;
+        let has_bottom = cy + 1 < self.num_y_clusters;
+        let mut transitions = Vec::new();
+        if has_right {
+            let first = (area.x1 - 1, area.y0);
+            let len = area.y1 - area.y0;
+            border_transitions(ctx, &mut transitions, first, (0, 1), (1, 0), len);
+        }
+        if has_bottom {
+            let first = (area.x0, area.y1 - 1);
+            let len = area.x1 - area.x0;
+            border_transitions(ctx, &mut transitions, first, (1, 0), (0, 1), len);
+        }
+        if has_right && has_bottom {
+            corner_transition(ctx, &mut transitions, (area.x1 - 1, area.y1 - 1), (1, 1));
+        }
+        if cx > 0 && has_bottom {
+            corner_transition(ctx, &mut transitions, (area.x0, area.y1 - 1), (-1, 1));
+        }
+        transitions
+    }
+
+    fn build_entrances(&mut self, ctx: &SearchContext, cluster_id: usize) {
+        for id in self.clusters[cluster_id].entrances.iter() {
+            self.entrance_index[*id] = -1;
+        }
+        let area = self.clusters[cluster_id].area;
+        let cx = cluster_id as i32 % self.num_x_clusters;
+        let cy = cluster_id as i32 / self.num_x_clusters;
+        let mut entrances: Vec<usize> = Vec::new();
+        let mut links: Vec<Vec<usize>> = Vec::new();
+        for (dx, dy) in [(0, 0), (-1, 0), (0, -1), (-1, -1), (1, -1)] {
+            let owner_id = match self.cluster_id(cx + dx, cy + dy) {
+                Some(id) => id,
+                None => continue,
+            };
+            for (a, b) in self.clusters[owner_id].transitions.iter() {
+                for (inside, outside) in [(*a, *b), (*b, *a)] {
+                    if !area.contains(&ctx.tiles[inside]) {
+                        continue;
+                    }
+                    let index = match entrances.iter().position(|e| *e == inside) {
+                        Some(index) => index,
+                        None => {
+                            entrances.push(inside);
+                            links.push(Vec::new());
+                            entrances.len() - 1
+                        }
+                    };
+                    links[index].push(outside);
+                }
+            }
+        }
+
+        let num_entrances = entrances.len();
+        let mut costs = vec![INF; num_entrances * num_entrances];
+        for (i, from_id) in entrances.iter().enumerate() {
+            let search = search_region(ctx, &area, *from_id, None, false);
+            for (j, to_id) in entrances.iter().enumerate() {
+                costs[i * num_entrances + j] = search.g[area.index(&ctx.tiles[*to_id])];
+            }
+            self.entrance_index[*from_id] = i as i32;
+        }
+        let cluster = &mut self.clusters[cluster_id];
+        cluster.entrances = entrances;
+        cluster.costs = costs;
+        cluster.links = links;
+        cluster.dirty = false;
+    }
+
+    fn search(&self, ctx: &mut SearchContext) -> u32 {
+        let start_id = ctx.start_id;
+        let end_id = ctx.end_id;
+        let mut nodes_expanded = 0;
+
+        // Costs into the end from the rest of its cluster
+        let end_area = self.clusters[self.cluster_of(&ctx.tiles[end_id])].area;
+        let to_end = search_region(ctx, &end_area, end_id, None, true);
+        nodes_expanded += to_end.nodes_expanded;
+
+        // Ways out of the start as (tile, cost, seed): the entrances of its
+        // cluster and the end if it's in there too. A start stuck in a wall
+        // steps onto each open tile beside it first, those are the seeds.
+        let seeds: Vec<(usize, i32)> = if ctx.tiles[start_id].is_wall() {
+            ctx.tiles[start_id]
+                .side_ids()
+                .iter()
+                .filter(|s| **s >= 0)
+                .map(|s| (*s as usize, ctx.move_cost(start_id, *s as usize)))
+                .collect()
+        } else {
+            vec![(start_id, 0)]
+        };
+        let mut start_edges: Vec<(usize, i32, usize)> = Vec::new();
+        for (seed_id, seed_cost) in seeds {
+            let cluster = &self.clusters[self.cluster_of(&ctx.tiles[seed_id])];
+            let from_seed = search_region(ctx, &cluster.area, seed_id, None, false);
+            nodes_expanded += from_seed.nodes_expanded;
+            for id in cluster.entrances.iter().chain(std::iter::once(&end_id)) {
+                if !cluster.area.contains(&ctx.tiles[*id]) {
+                    continue;
+                }
+                let g = from_seed.g[cluster.area.index(&ctx.tiles[*id])];
+                if g < INF {
+                    start_edges.push((*id, seed_cost + g, seed_id));
+                }
+            }
+        }
+
+        // A* over the start, the entrances and the end. ctx.nodes holds its
+        // open and closed sets so stepping shows the entrances being searched.
+        let mut parent: HashMap<usize, usize> = HashMap::new();
+        let mut via_seed: HashMap<usize, usize> = HashMap::new();
+        let mut open_nodes = BinaryHeap::new();
+        let mut abstract_expanded = 0;
+        ctx.nodes.h[start_id] = ctx.calc_h(start_id, end_id);
+        ctx.nodes.f[start_id] = ctx.nodes.h[start_id];
+        ctx.nodes.state[start_id] = NodeState::Open;
+        open_nodes.push(OpenNode {
+            f: ctx.nodes.f[start_id],
+            h: ctx.nodes.h[start_id],
+            id: start_id,
+        });
+        while abstract_expanded < ctx.max_expansions {
+            let current = match open_nodes.pop() {
+                Some(open_node) => open_node.id,
+                None => break,
+            };
+            if ctx.nodes.state[current] == NodeState::Closed {
+                continue;
+            }
+            ctx.nodes.state[current] = NodeState::Closed;
+            abstract_expanded += 1;
+            if current == end_id {
+                break;
+            }
+            for (id, cost, seed_id) in self.edges(ctx, current, &start_edges, end_area, &to_end) {
+                if ctx.nodes.state[id] == NodeState::Closed {
+                    continue;
+                }
+                let new_g = ctx.nodes.g[current] + cost;
+                if ctx.nodes.state[id] == NodeState::Unvisited {
+                    ctx.nodes.state[id] = NodeState::Open;
+                    ctx.nodes.h[id] = ctx.calc_h(id, end_id);
+                } else if ctx.nodes.g[id] <= new_g {
+                    continue;
+                }
+                parent.insert(id, current);
+                if current == start_id {
+                    via_seed.insert(id, seed_id);
+                }
+                ctx.nodes.g[id] = new_g;
+                ctx.nodes.f[id] = new_g + ctx.nodes.h[id];
+                open_nodes.push(OpenNode {
+                    f: ctx.nodes.f[id],
+                    h: ctx.nodes.h[id],
+                    id,
+                });
+            }
+        }
+        nodes_expanded += abstract_expanded;
+
+        if start_id != end_id && ctx.nodes.state[end_id] == NodeState::Closed {
+            nodes_expanded += self.refine(ctx, &parent, &via_seed);
+        }
+        nodes_expanded
+    }
+
+    // Abstract edges out of id as (tile, cost, seed), seed only matters for
+    // edges out of the start.
+    fn edges(
+        &self,
+        ctx: &SearchContext,
+        id: usize,
+        start_edges: &[(usize, i32, usize)],
+        end_area: Area,
+        to_end: &RegionSearch,
+    ) -> Vec<(usize, i32, usize)> {
+        let mut edges = Vec::new();
+        let cluster = &self.clusters[self.cluster_of(&ctx.tiles[id])];
+        let index = self.entrance_index[id];
+        if id == ctx.start_id {
+            edges.extend_from_slice(start_edges);
+        } else if index >= 0 {
+            let num_entrances = cluster.entrances.len();
+            for (j, to_id) in cluster.entrances.iter().enumerate() {
+                let cost = cluster.costs[index as usize * num_entrances + j];
+                if *to_id != id && cost < INF {
+                    edges.push((*to_id, cost, id));
+                }
+            }
+            if end_area.contains(&ctx.tiles[id]) {
+                let cost = to_end.g[end_area.index(&ctx.tiles[id])];
+                if cost < INF {
+                    edges.push((ctx.end_id, cost, id));
+                }
+            }
+        }
+        if index >= 0 {
+            for to_id in cluster.links[index as usize].iter() {
+                edges.push((*to_id, ctx.move_cost(id, *to_id), id));
+            }
+        }
+        edges
+    }
+
+    // Turns the abstract path into tiles with one more A*, allowed into
+    // every cluster the abstract path went through. That path is in there so
+    // the search can't come back worse, and it straightens out the detours
+    // through the entrances. Returns the nodes expanded doing so.
+    fn refine(
+        &self,
+        ctx: &mut SearchContext,
+        parent: &HashMap<usize, usize>,
+        via_seed: &HashMap<usize, usize>,
+    ) -> u32 {
+        let mut corridor = Corridor {
+            num_x_clusters: self.num_x_clusters,
+            cluster_slots: HashMap::new(),
+        };
+        let mut id = ctx.end_id;
+        corridor.add(&ctx.tiles[id]);
+        while let Some(parent_id) = parent.get(&id) {
+            if *parent_id == ctx.start_id {
+                corridor.add(&ctx.tiles[via_seed[&id]]);
+            }
+            id = *parent_id;
+            corridor.add(&ctx.tiles[id]);
+        }
+
+        let (start_id, end_id) = (ctx.start_id, ctx.end_id);
+        let search = search_region(ctx, &corridor, start_id, Some(end_id), false);
+        if write_path(ctx, &corridor, &search) {
+            return search.nodes_expanded;
+        }
+        // The abstract path should be in the corridor, but if the graph
+        // doesn't match the map the whole map is searched instead, and no
+        // path is left when that doesn't reach the end either
+        let area = whole_map(ctx);
+        let flat = search_region(ctx, &area, start_id, Some(end_id), false);
+        write_path(ctx, &area, &flat);
+        search.nodes_expanded + flat.nodes_expanded
+    }
+}
+
+// Crossings along the border starting at first and running len tiles in the
+// along direction, into the tiles one step across. Straight crossings come
+// in runs of open pairs, wide runs get an entrance at both ends and narrow
+// ones one in the middle. A diagonal squeezing between two walls is the only
+// way across at that spot so it gets a crossing of its own.
+fn border_transitions(
+    ctx: &SearchContext,
+    transitions: &mut Vec<(usize, usize)>,
+    first: (i32, i32),
+    along: (i32, i32),
+    across: (i32, i32),
+    len: i32,
+) {
+    let inside = |i: i32| ctx.walkable_id_at(first.0 + along.0 * i, first.1 + along.1 * i);
+    let outside = |i: i32| {
+        ctx.walkable_id_at(
+            first.0 + along.0 * i + across.0,
+            first.1 + along.1 * i + across.1,
+        )
+    };
+    let mut run_start = None;
+    for i in 0..=len {
+        let open = i < len && inside(i).is_some() && outside(i).is_some();
+        match (open, run_start) {
+            (true, None) => run_start = Some(i),
+            (false, Some(start)) => {
+                let width = i - start;
+                let picks = if width >= WIDE_OPENING {
+                    vec![start, i - 1]
+                } else {
+                    vec![start + (width - 1) / 2]
+                };
+                for pick in picks {
+                    transitions.push((inside(pick).unwrap(), outside(pick).unwrap()));
+                }
+                run_start = None;
+            }
+            _ => {}
+        }
+        if i == len {
+            break;
+        }
+        for d in [-1, 1] {
+            if i + d < 0 || i + d >= len || outside(i).is_some() || inside(i + d).is_some() {
+                continue;
+            }
+            if let (Some(a), Some(b)) = (inside(i), outside(i + d)) {
+                if is_linked(ctx, a, b) {
+                    transitions.push((a, b));
+                }
+            }
+        }
+    }
+}
+
+// Diagonal crossing through a cluster corner. Only needed when both tiles
+// beside it are walls, otherwise the border crossings already get around it.
+fn corner_transition(
+    ctx: &SearchContext,
+    transitions: &mut Vec<(usize, usize)>,
+    corner: (i32, i32),
+    dir: (i32, i32),
+) {
+    let (x, y) = corner;
+    if ctx.walkable_id_at(x + dir.0, y).is_some() || ctx.walkable_id_at(x, y + dir.1).is_some() {
+        return;
+    }
+    if let (Some(a), Some(b)) = (
+        ctx.walkable_id_at(x, y),
+        ctx.walkable_id_at(x + dir.0, y + dir.1),
+    ) {
+        if is_linked(ctx, a, b) {
+            transitions.push((a, b));
+        }
+    }
+}
+
+fn is_linked(ctx: &SearchContext, from_id: usize, to_id: usize) -> bool {
+    ctx.tiles[from_id].side_ids().contains(&(to_id as i32))
+}
+
+// Dijkstra, or A* when there is a target, from from_id without leaving region.
+// With reverse the costs are for paths into from_id instead of out of it,
+// which only works from an open tile since side links between open tiles
+// go both ways.
+fn search_region<R: Region>(
+    ctx: &SearchContext,
+    region: &R,
+    from_id: usize,
+    target_id: Option<usize>,
+    reverse: bool,
+) -> RegionSearch {
+    let h = |id: usize| target_id.map_or(0, |target_id| ctx.calc_h(id, target_id));
+    let mut search = RegionSearch {
+        g: vec![INF; region.num_slots()],
+        parent: vec![usize::MAX; region.num_slots()],
+        nodes_expanded: 0,
+    };
+    let mut closed = vec![false; region.num_slots()];
+    let mut open_nodes = BinaryHeap::new();
+    search.g[region.slot(&ctx.tiles[from_id]).unwrap()] = 0;
+    open_nodes.push(OpenNode {
+        f: h(from_id),
+        h: h(from_id),
+        id: from_id,
+    });
+    while let Some(open_node) = open_nodes.pop() {
+        let current = open_node.id;
+        let index = region.slot(&ctx.tiles[current]).unwrap();
+        if closed[index] {
+            continue;
+        }
+        closed[index] = true;
+        search.nodes_expanded += 1;
+        if Some(current) == target_id {
+            break;
+        }
+        for s in ctx.tiles[current].side_ids().iter().filter(|s| **s >= 0) {
+            let side_id = *s as usize;
+            let Some(side_index) = region.slot(&ctx.tiles[side_id]) else {
+                continue;
+            };
+            if closed[side_index] {
+                continue;
+            }
+            let step = if reverse {
+                ctx.move_cost(side_id, current)
+            } else {
+                ctx.move_cost(current, side_id)
+            };
+            let new_g = search.g[index] + step;
+            if new_g < search.g[side_index] {
+                search.g[side_index] = new_g;
+                search.parent[side_index] = current;
+                open_nodes.push(OpenNode {
+                    f: new_g + h(side_id),
+                    h: h(side_id),
+                    id: side_id,
+                });
+            }
+        }
+    }
+    search
+}
+
+// Writes the parent_id chain from ctx.end_id back to ctx.start_id found by
+// a search from the start, false without touching the tiles when the search
+// didn't reach the end.
+fn write_path<R: Region>(ctx: &mut SearchContext, region: &R, search: &RegionSearch) -> bool {
+    let (start_id, end_id) = (ctx.start_id, ctx.end_id);
+    match region.slot(&ctx.tiles[end_id]) {
+        Some(slot) if search.g[slot] < INF => {}
+        _ => return false,
+    }
+    let mut id = end_id;
+    while id != start_id {
+        let parent_id = search.parent[region.slot(&ctx.tiles[id]).unwrap()];
+        ctx.tiles[id].parent_id = parent_id as i32;
+        id = parent_id;
+    }
+    true
+}
+
+// Cost of the parent_id chain from the end back to the start and how many
+// cluster borders it crosses, a diagonal through a cluster corner crossing
+// two. Both 0 when the chain doesn't get back to the start.
+fn path_cost_and_borders(ctx: &SearchContext) -> (u32, u32) {
+    let (mut cost, mut borders) = (0, 0);
+    let mut id = ctx.end_id;
+    while ctx.tiles[id].parent_id >= 0 {
+        let parent_id = ctx.tiles[id].parent_id as usize;
+        let (t, parent) = (&ctx.tiles[id], &ctx.tiles[parent_id]);
+        cost += parent.move_cost_to(t) as u32;
+        borders += u32::from(t.x_id / CLUSTER_SIZE != parent.x_id / CLUSTER_SIZE)
+            + u32::from(t.y_id / CLUSTER_SIZE != parent.y_id / CLUSTER_SIZE);
+        id = parent_id;
+    }
+    if id == ctx.start_id {
+        (cost, borders)
+    } else {
+        (0, 0)
+    }
+}
+
+fn whole_map(ctx: &SearchContext) -> Area {
+    Area {
+        x0: 0,
+        y0: 0,
+        x1: ctx.num_x_tiles,
+        y1: ctx.num_y_tiles,
+    }
+}
+
+// Cost of the cheapest path from start_id to end_id over the whole map, None
+// when there is none. Leaves the tiles and search nodes alone.
+#[cfg(test)]
+pub fn cheapest_cost(ctx: &SearchContext, start_id: usize, end_id: usize) -> Option<i32> {
+    let area = whole_map(ctx);
+    let search = search_region(ctx, &area, start_id, Some(end_id), false);
+    Some(search.g[area.index(&ctx.tiles[end_id])]).filter(|g| *g < INF)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::CLUSTER_SIZE;
+    use crate::world::{Algorithm, Generator, Movement, Terrain, WorldState};
+
+    // Cost of the current path and how many cluster borders it crosses, a
+    // diagonal through a cluster corner crossing two
+    fn path_cost_and_crossings(world: &WorldState) -> (i32, i32) {
+        let (mut cost, mut crossings) = (0, 0);
+        let mut id = world.end_id as usize;
+        while world.tiles[id].parent_id >= 0 {
+            let (t, parent) = (&world.tiles[id], &world.tiles[world.tiles[id].parent_id as usize]);
+            cost += parent.move_cost_to(t);
+            crossings += i32::from(t.x_id / CLUSTER_SIZE != parent.x_id / CLUSTER_SIZE)
+                + i32::from(t.y_id / CLUSTER_SIZE != parent.y_id / CLUSTER_SIZE);
+            id = t.parent_id as usize;
+        }
+        assert_eq!(id, world.start_id as usize, "path doesn't reach the start");
+        (cost, crossings)
+    }
+
+    #[test]
+    fn paths_stay_within_the_detour_bound() {
+        let generators = [Generator::Noise, Generator::Caves, Generator::Rooms];
+        let movements = [Movement::Cardinal, Movement::Diagonal, Movement::DiagonalNoCornerCutting];
+        for seed in 0..30 {
+            let mut world = WorldState::new();
+            world.map_size = Some((64, 48));
+            world.generator = generators[seed as usize % generators.len()];
+            world.load_seeded_map(seed);
+            for movement in movements {
+                world.set_movement(movement);
+                world.algorithm = Algorithm::Dijkstra;
+                world.calc_path();
+                let (cheapest, crossings) = path_cost_and_crossings(&world);
+                world.algorithm = Algorithm::Hierarchical;
+                world.calc_path();
+                let (cost, _) = path_cost_and_crossings(&world);
+                let bound = world.cluster_stats().max_detour_per_border as i32;
+                assert!(
+                    cost <= cheapest + crossings * bound,
+                    "seed {}: {} over {} with {} crossings of {}",
+                    seed,
+                    cost,
+                    cheapest,
+                    crossings,
+                    bound
+                );
+                let stats = world.cluster_stats();
+                assert_eq!(stats.path_cost, cost as u32);
+                assert!(cost <= cheapest + stats.max_path_detour as i32, "seed {}", seed);
+                if cheapest > 0 {
+                    assert_eq!(world.path_suboptimality(), cost as f64 / cheapest as f64);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn stale_graph_falls_back_to_a_flat_search() {
+        let row = vec!["0"; 30].join(",");
+        let text = format!("start 0,5\nend 29,5\n{}\n", vec![row; 30].join("\n"));
+        let mut world = WorldState::new();
+        world.algorithm = Algorithm::Hierarchical;
+        world.load_map_text(&text).unwrap();
+        // Wall off the top two rows of clusters behind the graph's back, the
+        // abstract path still runs straight through them
+        for y in 0..20 {
+            let id = world.get_tile_id_at(15, y);
+            world.set_tile_terrain(id, Terrain::Wall);
+        }
+        world.changed_tiles.clear();
+        world.calc_path();
+        let (cost, _) = path_cost_and_crossings(&world);
+        assert!(cost > 29 * 10);
+    }
+}
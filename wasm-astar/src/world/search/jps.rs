@@ -0,0 +1,369 @@
+use std::collections::BinaryHeap;
+
+use super::astar::AStar;
+use super::{NodeState, OpenNode, SearchAlgorithm, SearchContext};
+use crate::world::Movement;
+
+// Jump Point Search. Only the jump points go on the open list, the tiles in
+// between are filled back into the parent_id chain once the end is reached.
+//
+// Cardinal movement: canonical paths take their vertical moves before their
+// horizontal ones, so horizontal runs only stop at a goal or a forced neighbor
+// (free tile above/below whose counterpart behind us is blocked) and vertical
+// runs stop wherever a horizontal run would find something.
+//
+// Diagonal movement: the usual 8-connected rules, with diagonal runs stopping
+// wherever a straight run would find something. Without corner cutting a
+// diagonal run also ends as soon as either tile beside the next step is blocked.
+//
+// The pruning rules only hold when every tile costs the same to step onto,
+// maps with mixed terrain are searched with plain A* instead (see fallback,
+// the search stats name the algorithm that ran).
+pub struct JumpPoint;
+
+impl SearchAlgorithm for JumpPoint {
+    fn name(&self) -> &'static str {
+        "Jump Point Search"
+    }
+
+    fn fallback(
+        &self,
+        min_move_cost: i32,
+        max_move_cost: i32,
+    ) -> Option<&'static dyn SearchAlgorithm> {
+        if min_move_cost != max_move_cost {
+            Some(&AStar)
+        } else {
+            None
+        }
+    }
+
+    fn search(&self, ctx: &mut SearchContext) -> u32 {
+        if let Some(fallback) = self.fallback(ctx.min_move_cost, ctx.max_move_cost) {
+            return fallback.search(ctx);
+        }
+        let start_id = ctx.start_id;
+        let mut open_nodes = BinaryHeap::new();
+        let mut jump_parent = vec![-1; ctx.tiles.len()];
+        let mut nodes_expanded = 0;
+
+        ctx.nodes.h[start_id] = ctx.calc_h(start_id, ctx.end_id);
+        ctx.nodes.f[start_id] = ctx.nodes.h[start_id];
+        ctx.nodes.state[start_id] = NodeState::Open;
+        open_nodes.push(OpenNode {
+            f: ctx.nodes.f[start_id],
+            h: ctx.nodes.h[start_id],
+            id: start_id,
+        });
+
+        while nodes_expanded < ctx.max_expansions {
+            let current_node = match open_nodes.pop() {
+                Some(open_node) => open_node.id,
+                None => break,
+            };
+            if ctx.nodes.state[current_node] == NodeState::Closed {
+                continue;
+            }
+            ctx.nodes.state[current_node] = NodeState::Closed;
+            nodes_expanded += 1;
+            if current_node == ctx.end_id {
+                break;
+            }
+
+            let x = ctx.tiles[current_node].x_id;
+            let y = ctx.tiles[current_node].y_id;
+            for (dx, dy) in successor_dirs(ctx, current_node, jump_parent[current_node]) {
+                let id = match jump(ctx, x, y, dx, dy) {
+                    Some(id) => id,
+                    None => continue,
+                };
+                if ctx.nodes.state[id] == NodeState::Closed {
+                    continue;
+                }
+                let new_g = ctx.nodes.g[current_node] + run_cost(ctx, current_node, id);
+                if ctx.nodes.state[id] == NodeState::Unvisited {
+                    ctx.nodes.state[id] = NodeState::Open;
+                    ctx.nodes.h[id] = ctx.calc_h(id, ctx.end_id);
+                } else if ctx.nodes.g[id] <= new_g {
+                    continue;
+                }
+                jump_parent[id] = current_node as i32;
+                ctx.nodes.g[id] = new_g;
+                ctx.nodes.f[id] = new_g + ctx.nodes.h[id];
+                open_nodes.push(OpenNode {
+                    f: ctx.nodes.f[id],
+                    h: ctx.nodes.h[id],
+                    id,
+                });
+            }
+        }
+
+        if ctx.nodes.state[ctx.end_id] == NodeState::Closed {
+            fill_parent_chain(ctx, &jump_parent);
+        }
+        nodes_expanded
+    }
+}
+
+// Directions worth jumping in from a node, pruned by the direction we arrived from.
+fn successor_dirs(ctx: &SearchContext, id: usize, parent_id: i32) -> Vec<(i32, i32)> {
+    if parent_id < 0 {
+        let mut dirs = vec![(0, -1), (0, 1), (1, 0), (-1, 0)];
+        if ctx.movement != Movement::Cardinal {
+            dirs.extend_from_slice(&[(1, -1), (-1, -1), (1, 1), (-1, 1)]);
+        }
+        return dirs;
+    }
+    let x = ctx.tiles[id].x_id;
+    let y = ctx.tiles[id].y_id;
+    let dx = (x - ctx.tiles[parent_id as usize].x_id).signum();
+    let dy = (y - ctx.tiles[parent_id as usize].y_id).signum();
+    match ctx.movement {
+        Movement::Cardinal => cardinal_successor_dirs(ctx, x, y, dx, dy),
+        Movement::Diagonal => diagonal_successor_dirs(ctx, x, y, dx, dy),
+        Movement::DiagonalNoCornerCutting => no_corner_successor_dirs(ctx, x, y, dx, dy),
+    }
+}
+
+fn cardinal_successor_dirs(ctx: &SearchContext, x: i32, y: i32, dx: i32, dy: i32) -> Vec<(i32, i32)> {
+    if dx != 0 {
+        let mut dirs = vec![(dx, 0)];
+        // While moving horizontally, the tile above/below can only be reached
+        // canonically through x/y when the tile behind it is blocked.
+        for vy in [-1, 1] {
+            if is_open(ctx, x, y + vy) && !is_open(ctx, x - dx, y + vy) {
+                dirs.push((0, vy));
+            }
+        }
+        dirs
+    } else {
+        vec![(0, dy), (1, 0), (-1, 0)]
+    }
+}
+
+fn diagonal_successor_dirs(ctx: &SearchContext, x: i32, y: i32, dx: i32, dy: i32) -> Vec<(i32, i32)> {
+    if dx != 0 && dy != 0 {
+        let mut dirs = vec![(0, dy), (dx, 0), (dx, dy)];
+        if !is_open(ctx, x - dx, y) {
+            dirs.push((-dx, dy));
+        }
+        if !is_open(ctx, x, y - dy) {
+            dirs.push((dx, -dy));
+        }
+        dirs
+    } else if dx != 0 {
+        let mut dirs = vec![(dx, 0)];
+        for vy in [-1, 1] {
+            if !is_open(ctx, x, y + vy) {
+                dirs.push((dx, vy));
+            }
+        }
+        dirs
+    } else {
+        let mut dirs = vec![(0, dy)];
+        for vx in [-1, 1] {
+            if !is_open(ctx, x + vx, y) {
+                dirs.push((vx, dy));
+            }
+        }
+        dirs
+    }
+}
+
+fn no_corner_successor_dirs(ctx: &SearchContext, x: i32, y: i32, dx: i32, dy: i32) -> Vec<(i32, i32)> {
+    if dx != 0 && dy != 0 {
+        vec![(0, dy), (dx, 0), (dx, dy)]
+    } else if dx != 0 {
+        let mut dirs = vec![(dx, 0)];
+        for vy in [-1, 1] {
+            if is_open(ctx, x, y + vy) {
+                dirs.push((0, vy));
+                dirs.push((dx, vy));
+            }
+        }
+        dirs
+    } else {
+        let mut dirs = vec![(0, dy)];
+        for vx in [-1, 1] {
+            if is_open(ctx, x + vx, y) {
+                dirs.push((vx, 0));
+                dirs.push((vx, dy));
+            }
+        }
+        dirs
+    }
+}
+
+fn is_open(ctx: &SearchContext, x: i32, y: i32) -> bool {
+    ctx.walkable_id_at(x, y).is_some()
+}
+
+// Walks from x/y in one direction until a jump point, a wall or the map edge.
+fn jump(ctx: &SearchContext, x: i32, y: i32, dx: i32, dy: i32) -> Option<usize> {
+    let mut x = x;
+    let mut y = y;
+    loop {
+        if dx != 0
+            && dy != 0
+            && ctx.movement == Movement::DiagonalNoCornerCutting
+            && !(is_open(ctx, x + dx, y) && is_open(ctx, x, y + dy))
+        {
+            return None;
+        }
+        x += dx;
+        y += dy;
+        let id = ctx.walkable_id_at(x, y)?;
+        if id == ctx.end_id {
+            return Some(id);
+        }
+        let is_jump_point = match ctx.movement {
+            Movement::Cardinal => is_cardinal_jump_point(ctx, x, y, dx),
+            Movement::Diagonal => is_diagonal_jump_point(ctx, x, y, dx, dy),
+            Movement::DiagonalNoCornerCutting => is_no_corner_jump_point(ctx, x, y, dx, dy),
+        };
+        if is_jump_point {
+            return Some(id);
+        }
+    }
+}
+
+fn is_cardinal_jump_point(ctx: &SearchContext, x: i32, y: i32, dx: i32) -> bool {
+    if dx != 0 {
+        (is_open(ctx, x, y - 1) && !is_open(ctx, x - dx, y - 1))
+            || (is_open(ctx, x, y + 1) && !is_open(ctx, x - dx, y + 1))
+    } else {
+        jump(ctx, x, y, 1, 0).is_some() || jump(ctx, x, y, -1, 0).is_some()
+    }
+}
+
+fn is_diagonal_jump_point(ctx: &SearchContext, x: i32, y: i32, dx: i32, dy: i32) -> bool {
+    if dx != 0 && dy != 0 {
+        (is_open(ctx, x - dx, y + dy) && !is_open(ctx, x - dx, y))
+            || (is_open(ctx, x + dx, y - dy) && !is_open(ctx, x, y - dy))
+            || jump(ctx, x, y, dx, 0).is_some()
+            || jump(ctx, x, y, 0, dy).is_some()
+    } else if dx != 0 {
+        (is_open(ctx, x + dx, y + 1) && !is_open(ctx, x, y + 1))
+            || (is_open(ctx, x + dx, y - 1) && !is_open(ctx, x, y - 1))
+    } else {
+        (is_open(ctx, x + 1, y + dy) && !is_open(ctx, x + 1, y))
+            || (is_open(ctx, x - 1, y + dy) && !is_open(ctx, x - 1, y))
+    }
+}
+
+fn is_no_corner_jump_point(ctx: &SearchContext, x: i32, y: i32, dx: i32, dy: i32) -> bool {
+    if dx != 0 && dy != 0 {
+        jump(ctx, x, y, dx, 0).is_some() || jump(ctx, x, y, 0, dy).is_some()
+    } else if dx != 0 {
+        (is_open(ctx, x, y - 1) && !is_open(ctx, x - dx, y - 1))
+            || (is_open(ctx, x, y + 1) && !is_open(ctx, x - dx, y + 1))
+    } else {
+        (is_open(ctx, x - 1, y) && !is_open(ctx, x - 1, y - dy))
+            || (is_open(ctx, x + 1, y) && !is_open(ctx, x + 1, y - dy))
+    }
+}
+
+// Cost of the straight or diagonal run between two jump points.
+fn run_cost(ctx: &SearchContext, from_id: usize, to_id: usize) -> i32 {
+    let step = run_step(ctx, from_id, to_id);
+    let mut cost = 0;
+    let mut id = from_id;
+    while id != to_id {
+        let next_id = (id as i32 + step) as usize;
+        cost += ctx.move_cost(id, next_id);
+        id = next_id;
+    }
+    cost
+}
+
+// Tile id offset of one step along the run between two jump points.
+fn run_step(ctx: &SearchContext, from_id: usize, to_id: usize) -> i32 {
+    let dx = (ctx.tiles[to_id].x_id - ctx.tiles[from_id].x_id).signum();
+    let dy = (ctx.tiles[to_id].y_id - ctx.tiles[from_id].y_id).signum();
+    dy * ctx.num_x_tiles + dx
+}
+
+// Jump points are joined by straight or diagonal runs, walk each run to set one parent per tile.
+fn fill_parent_chain(ctx: &mut SearchContext, jump_parent: &[i32]) {
+    let mut id = ctx.end_id;
+    while jump_parent[id] >= 0 {
+        let parent_id = jump_parent[id] as usize;
+        let step = run_step(ctx, id, parent_id);
+        let mut run_id = id;
+        while run_id != parent_id {
+            let next_id = (run_id as i32 + step) as usize;
+            ctx.tiles[run_id].parent_id = next_id as i32;
+            run_id = next_id;
+        }
+        id = parent_id;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::utils::Rng;
+    use crate::world::{Algorithm, Heuristic, Movement, WorldState};
+
+    // A 24x16 map with a quarter of the tiles walls and the rest drawn from
+    // terrain_codes, start and end in opposite corners
+    fn random_map(rng: &mut Rng, terrain_codes: &[&'static str]) -> String {
+        let (num_x_tiles, num_y_tiles) = (24, 16);
+        let mut text = format!("start 0,0\nend {},{}\n", num_x_tiles - 1, num_y_tiles - 1);
+        for y in 0..num_y_tiles {
+            let row: Vec<&str> = (0..num_x_tiles)
+                .map(|x| {
+                    let target = (x, y) == (0, 0) || (x, y) == (num_x_tiles - 1, num_y_tiles - 1);
+                    if !target && rng.random() < 0.25 {
+                        "1"
+                    } else {
+                        terrain_codes[rng.random_range(0, terrain_codes.len() as i32 - 1) as usize]
+                    }
+                })
+                .collect();
+            text.push_str(&row.join(","));
+            text.push('\n');
+        }
+        text
+    }
+
+    // Cost of the current path, None when it doesn't lead back to the start
+    fn path_cost(world: &WorldState) -> Option<i32> {
+        let mut cost = 0;
+        let mut id = world.end_id as usize;
+        while world.tiles[id].parent_id >= 0 {
+            let parent = &world.tiles[world.tiles[id].parent_id as usize];
+            cost += parent.move_cost_to(&world.tiles[id]);
+            id = world.tiles[id].parent_id as usize;
+        }
+        Some(cost).filter(|_| id == world.start_id as usize)
+    }
+
+    // All grass maps get the real jump point search, mixed terrain its A*
+    // fallback. Either way the path has to cost what Dijkstra's does.
+    #[test]
+    fn jps_matches_dijkstra_in_every_movement_mode() {
+        let mut rng = Rng::new(7);
+        let mut world = WorldState::new();
+        // Admissible in every movement mode, unlike the default Manhattan
+        world.heuristic = Heuristic::Octile;
+        let movements = [Movement::Cardinal, Movement::Diagonal, Movement::DiagonalNoCornerCutting];
+        for movement in movements {
+            world.set_movement(movement);
+            for round in 0..60 {
+                let (terrain_codes, expected_name) = if round % 2 == 0 {
+                    (&["0"][..], "Jump Point Search")
+                } else {
+                    (&["0", "2", "3"][..], "A*")
+                };
+                world.load_map_text(&random_map(&mut rng, terrain_codes)).unwrap();
+                world.algorithm = Algorithm::Dijkstra;
+                world.calc_path();
+                let cheapest = path_cost(&world);
+                world.algorithm = Algorithm::JumpPoint;
+                world.calc_path();
+                assert_eq!(path_cost(&world), cheapest, "{} round {}", movement.name(), round);
+                assert_eq!(world.search_stats.searched_with, expected_name);
+            }
+        }
+    }
+}
@@ -0,0 +1,210 @@
+use std::cmp::Ordering;
+
+use super::tile::{Heuristic, Tile, MOVE_COST};
+use super::Movement;
+
+mod astar;
+mod bfs;
+mod bidirectional;
+mod dstar_lite;
+mod hpa;
+mod jps;
+use self::astar::{AStar, Dijkstra, GreedyBestFirst};
+use self::bfs::BreadthFirst;
+use self::bidirectional::BidirectionalAStar;
+use self::dstar_lite::DStarLite;
+pub use self::dstar_lite::Planner;
+use self::hpa::Hierarchical;
+#[cfg(test)]
+pub use self::hpa::cheapest_cost;
+pub use self::hpa::{ClusterGraph, ClusterStats};
+use self::jps::JumpPoint;
+
+// Every algorithm writes the same parent_id chain (end back to start, one tile per step)
+// into the tiles so draw_path and get_path_count don't care which one ran.
+// They also leave their open/closed sets and G/H/F in SearchNodes for drawing.
+pub trait SearchAlgorithm {
+    fn name(&self) -> &'static str;
+    // Returns the number of nodes expanded.
+    fn search(&self, ctx: &mut SearchContext) -> u32;
+    // Algorithm that runs instead on maps this one can't search correctly,
+    // None when this one can.
+    fn fallback(
+        &self,
+        _min_move_cost: i32,
+        _max_move_cost: i32,
+    ) -> Option<&'static dyn SearchAlgorithm> {
+        None
+    }
+}
+
+// Maps to the ids passed to set_search_algorithm on the client side
+#[derive(Clone, Copy, PartialEq)]
+pub enum Algorithm {
+    AStar = 0,
+    Dijkstra = 1,
+    GreedyBestFirst = 2,
+    BreadthFirst = 3,
+    JumpPoint = 4,
+    BidirectionalAStar = 5,
+    // Reuses the previous search between frames, see WorldState::calc_path
+    DStarLite = 6,
+    // Searches a graph of cluster entrances kept between frames
+    Hierarchical = 7,
+}
+
+impl Algorithm {
+    pub fn from_id(id: u32) -> Option<Algorithm> {
+        match id {
+            0 => Some(Algorithm::AStar),
+            1 => Some(Algorithm::Dijkstra),
+            2 => Some(Algorithm::GreedyBestFirst),
+            3 => Some(Algorithm::BreadthFirst),
+            4 => Some(Algorithm::JumpPoint),
+            5 => Some(Algorithm::BidirectionalAStar),
+            6 => Some(Algorithm::DStarLite),
+            7 => Some(Algorithm::Hierarchical),
+            _ => None,
+        }
+    }
+
+    pub fn searcher(self) -> &'static dyn SearchAlgorithm {
+        match self {
+            Algorithm::AStar => &AStar,
+            Algorithm::Dijkstra => &Dijkstra,
+            Algorithm::GreedyBestFirst => &GreedyBestFirst,
+            Algorithm::BreadthFirst => &BreadthFirst,
+            Algorithm::JumpPoint => &JumpPoint,
+            Algorithm::BidirectionalAStar => &BidirectionalAStar,
+            Algorithm::DStarLite => &DStarLite,
+            Algorithm::Hierarchical => &Hierarchical,
+        }
+    }
+}
+
+// Everything an algorithm needs to run over the tile grid.
+// Tiles are expected to have been reset (parent_id = -1) before searching.
+pub struct SearchContext<'a> {
+    pub tiles: &'a mut [Tile],
+    pub nodes: &'a mut SearchNodes,
+    pub start_id: usize,
+    pub end_id: usize,
+    pub num_x_tiles: i32,
+    pub num_y_tiles: i32,
+    pub heuristic: Heuristic,
+    pub movement: Movement,
+    // Cheapest terrain step on the map, heuristics are scaled by it to stay admissible
+    pub min_move_cost: i32,
+    pub max_move_cost: i32,
+    // Stop after this many expansions, used to watch the search step by step
+    pub max_expansions: u32,
+}
+
+impl SearchContext<'_> {
+    pub fn calc_h(&self, id: usize, target_id: usize) -> i32 {
+        let h = self.tiles[id].calc_h(&self.tiles[target_id], self.heuristic);
+        h * self.min_move_cost / MOVE_COST
+    }
+
+    pub fn move_cost(&self, from_id: usize, to_id: usize) -> i32 {
+        self.tiles[from_id].move_cost_to(&self.tiles[to_id])
+    }
+
+    // Tile id at grid position x/y, or None when out of bounds or a wall.
+    pub fn walkable_id_at(&self, x: i32, y: i32) -> Option<usize> {
+        if x < 0 || y < 0 || x >= self.num_x_tiles || y >= self.num_y_tiles {
+            return None;
+        }
+        let id = (y * self.num_x_tiles + x) as usize;
+        if self.tiles[id].is_wall() {
+            None
+        } else {
+            Some(id)
+        }
+    }
+}
+
+// Per-node search state. Indexed by tile node_id so lookups are O(1)
+// instead of the Vec::contains / HashSet checks the first version used.
+#[derive(Clone, Copy, PartialEq)]
+pub enum NodeState {
+    Unvisited,
+    Open,
+    Closed,
+}
+
+pub struct SearchNodes {
+    pub state: Vec<NodeState>,
+    pub g: Vec<i32>,
+    pub h: Vec<i32>,
+    pub f: Vec<i32>,
+}
+
+impl SearchNodes {
+    pub fn new() -> SearchNodes {
+        SearchNodes {
+            state: Vec::new(),
+            g: Vec::new(),
+            h: Vec::new(),
+            f: Vec::new(),
+        }
+    }
+
+    // Reuses the existing allocations, the search runs every frame.
+    pub fn reset(&mut self, num_nodes: usize) {
+        self.state.clear();
+        self.state.resize(num_nodes, NodeState::Unvisited);
+        self.g.clear();
+        self.g.resize(num_nodes, 0);
+        self.h.clear();
+        self.h.resize(num_nodes, 0);
+        self.f.clear();
+        self.f.resize(num_nodes, 0);
+    }
+}
+
+#[derive(Clone, Copy, Default)]
+pub struct SearchStats {
+    pub nodes_expanded: u32,
+    pub elapsed_us: f64,
+    // Name of the algorithm that ran, its fallback's when the selected one had one
+    pub searched_with: &'static str,
+}
+
+// Running totals of how calc_path went about each frame: searched from
+// scratch, repaired the previous D* Lite search or HPA* cluster graph, or
+// had nothing to do.
+#[derive(Clone, Copy, Default)]
+pub struct ReplanStats {
+    pub full: u32,
+    pub incremental: u32,
+    pub skipped: u32,
+}
+
+// Entry in the open set binary heap.
+// Nodes are pushed again when a better G is found instead of being updated
+// in place, stale entries are skipped when popped (their node is already closed).
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct OpenNode {
+    pub f: i32,
+    pub h: i32,
+    pub id: usize,
+}
+
+impl Ord for OpenNode {
+    // BinaryHeap is a max-heap so the comparison is reversed to pop the lowest F first.
+    // Ties go to the node closest to the target, then to the lowest id so results are stable.
+    fn cmp(&self, other: &OpenNode) -> Ordering {
+        other
+            .f
+            .cmp(&self.f)
+            .then_with(|| other.h.cmp(&self.h))
+            .then_with(|| other.id.cmp(&self.id))
+    }
+}
+
+impl PartialOrd for OpenNode {
+    fn partial_cmp(&self, other: &OpenNode) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
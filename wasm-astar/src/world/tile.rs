@@ -1,6 +1,85 @@
+use std::f64::consts::SQRT_2;
+
 use crate::engine::{Color, Transform};
 
 pub const MOVE_COST: i32 = 10;
+// ~MOVE_COST * sqrt(2), kept integral so G/F stay i32
+pub const DIAGONAL_MOVE_COST: i32 = 14;
+
+// What a tile is made of. Step cost is paid when moving onto the tile.
+// Ids are the codes used in map text, 0/1 match the old grass/wall maps.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Terrain {
+    Grass = 0,
+    Wall = 1,
+    Road = 2,
+    Swamp = 3,
+}
+
+impl Terrain {
+    pub fn from_id(id: u32) -> Option<Terrain> {
+        match id {
+            0 => Some(Terrain::Grass),
+            1 => Some(Terrain::Wall),
+            2 => Some(Terrain::Road),
+            3 => Some(Terrain::Swamp),
+            _ => None,
+        }
+    }
+
+    pub fn move_cost(self) -> i32 {
+        match self {
+            Terrain::Road => 5,
+            Terrain::Grass => MOVE_COST,
+            Terrain::Swamp => 40,
+            // Never stepped onto, walls are not linked as sides
+            Terrain::Wall => 0,
+        }
+    }
+
+    // Cheaper terrain is drawn lighter
+    pub fn lightness(self) -> u16 {
+        match self {
+            Terrain::Road => 40,
+            Terrain::Grass => 30,
+            Terrain::Swamp => 20,
+            Terrain::Wall => 10,
+        }
+    }
+}
+
+// Maps to the ids passed to set_heuristic on the client side
+#[derive(Clone, Copy, PartialEq)]
+pub enum Heuristic {
+    Manhattan = 0,
+    Octile = 1,
+    Chebyshev = 2,
+    Euclidean = 3,
+    Zero = 4,
+}
+
+impl Heuristic {
+    pub fn from_id(id: u32) -> Option<Heuristic> {
+        match id {
+            0 => Some(Heuristic::Manhattan),
+            1 => Some(Heuristic::Octile),
+            2 => Some(Heuristic::Chebyshev),
+            3 => Some(Heuristic::Euclidean),
+            4 => Some(Heuristic::Zero),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Heuristic::Manhattan => "Manhattan",
+            Heuristic::Octile => "Octile",
+            Heuristic::Chebyshev => "Chebyshev",
+            Heuristic::Euclidean => "Euclidean",
+            Heuristic::Zero => "Zero",
+        }
+    }
+}
 
 #[derive(Clone)]
 pub struct Tile {
@@ -14,12 +93,12 @@ pub struct Tile {
     pub bottom: i32,
     pub left: i32,
     pub right: i32,
-    pub is_wall: bool,
-    // A* values
-    // TODO: move to a new struct type just for A*
-    pub h: i32,
-    pub g: i32,
-    pub f: i32,
+    // Only set when diagonal movement is enabled
+    pub top_left: i32,
+    pub top_right: i32,
+    pub bottom_left: i32,
+    pub bottom_right: i32,
+    pub terrain: Terrain,
 }
 
 impl Tile {
@@ -35,36 +114,98 @@ impl Tile {
             bottom: -1,
             left: -1,
             right: -1,
-            is_wall: false,
-            h: 0,
-            g: 0,
-            f: 0,
+            top_left: -1,
+            top_right: -1,
+            bottom_left: -1,
+            bottom_right: -1,
+            terrain: Terrain::Grass,
         }
     }
 
-    pub fn reset(&mut self, end_node: &Tile) {
+    pub fn set_terrain(&mut self, terrain: Terrain) {
+        self.terrain = terrain;
+        self.color = Color::new(0, 0, terrain.lightness(), 1_f32);
+    }
+
+    pub fn is_wall(&self) -> bool {
+        self.terrain == Terrain::Wall
+    }
+
+    pub fn reset(&mut self) {
         self.parent_id = -1;
-        self.g = 0;
-        self.f = 0;
-        self.calc_h(end_node);
     }
 
-    fn calc_h(&mut self, end_node: &Tile) {
-        if self.is_wall {
-            return;
+    // Neighbor ids, -1 when there is no side (edge of the map, a wall or diagonals disabled).
+    pub fn side_ids(&self) -> [i32; 8] {
+        [
+            self.top,
+            self.bottom,
+            self.right,
+            self.left,
+            self.top_right,
+            self.top_left,
+            self.bottom_right,
+            self.bottom_left,
+        ]
+    }
+
+    // Cost of stepping from this tile onto a neighboring one.
+    // Diagonal steps scale the terrain cost by the same ~sqrt(2) as on grass.
+    pub fn move_cost_to(&self, other: &Tile) -> i32 {
+        let cost = other.terrain.move_cost();
+        if self.x_id != other.x_id && self.y_id != other.y_id {
+            cost * DIAGONAL_MOVE_COST / MOVE_COST
+        } else {
+            cost
         }
+    }
+
+    pub fn calc_h(&self, end_node: &Tile, heuristic: Heuristic) -> i32 {
         // H: difference between this position and the end target
         // REMINDER TO SELF: the MOVE_COST is very dependant on the x/y diff scale.
         // I was using px,py before by accident which caused diffs to be very large
         // and my MOVE_COST of 10 became useless. Using x/y ids keeps the diffs small
         // enough for MOVE_COST of 10 to work.
-        let x_diff = (self.x_id - end_node.x_id).abs() as i32;
-        let y_diff = (self.y_id - end_node.y_id).abs() as i32;
-        self.h = (x_diff + y_diff) * MOVE_COST;
+        let x_diff = (self.x_id - end_node.x_id).abs();
+        let y_diff = (self.y_id - end_node.y_id).abs();
+        match heuristic {
+            Heuristic::Manhattan => (x_diff + y_diff) * MOVE_COST,
+            // Diagonal steps for the shorter axis, straight steps for the rest
+            Heuristic::Octile => {
+                let diagonal = x_diff.min(y_diff);
+                let straight = x_diff.max(y_diff) - diagonal;
+                diagonal * DIAGONAL_MOVE_COST + straight * MOVE_COST
+            }
+            Heuristic::Chebyshev => x_diff.max(y_diff) * MOVE_COST,
+            // Straight line distance priced like a diagonal step. A diagonal
+            // costs DIAGONAL_MOVE_COST, a bit less than MOVE_COST * sqrt(2),
+            // so scaling by MOVE_COST would overestimate long diagonals.
+            // Rounded down this never exceeds the octile cost.
+            Heuristic::Euclidean => {
+                let dist = ((x_diff * x_diff + y_diff * y_diff) as f64).sqrt();
+                (dist * DIAGONAL_MOVE_COST as f64 / SQRT_2) as i32
+            }
+            Heuristic::Zero => 0,
+        }
     }
+}
 
-    pub fn calc_f_g(&mut self, parent_g: i32) {
-        self.g = parent_g + MOVE_COST;
-        self.f = self.g + self.h;
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn euclidean_never_exceeds_octile() {
+        let origin = Tile::new(0_f64, 0_f64, 1_f64);
+        for x_id in 0..200 {
+            for y_id in 0..200 {
+                let mut t = Tile::new(0_f64, 0_f64, 1_f64);
+                t.x_id = x_id;
+                t.y_id = y_id;
+                let euclidean = t.calc_h(&origin, Heuristic::Euclidean);
+                let octile = t.calc_h(&origin, Heuristic::Octile);
+                assert!(euclidean <= octile, "{},{}: {} > {}", x_id, y_id, euclidean, octile);
+            }
+        }
     }
 }
@@ -1,9 +1,12 @@
-/// A* pathfinding module
+//! A* pathfinding module
 
 use wasm_bindgen::prelude::*;
 use std::collections::{HashMap, HashSet, BinaryHeap};
 use crate::types::AStarNode;
-use crate::hex_utils::{get_hex_neighbors, parse_valid_terrain_json, axial_to_cube, cube_distance, hex_distance};
+use crate::hex_utils::{get_hex_neighbors, axial_to_cube, cube_distance, hex_distance};
+use crate::state::WFC_STATE;
+use crate::json::{parse_hexes, parse_hex_set, parse_tile_costs, hexes_to_json, TileCosts};
+use crate::packed::{unpack_hexes, unpack_hex_set, unpack_tile_costs, pack_hexes};
 
 /// Hex A* pathfinding between two road tiles
 /// Returns path length, or -1 if unreachable
@@ -97,40 +100,52 @@ pub fn hex_astar_path(
 /// - Maintains g_scores as HashMap
 /// - Stores parent pointers for path reconstruction
 /// 
-/// @param start_q - Start q coordinate (axial)
-/// @param start_r - Start r coordinate (axial)
-/// @param goal_q - Goal q coordinate (axial)
-/// @param goal_r - Goal r coordinate (axial)
-/// @param valid_terrain_json - JSON string with array of valid terrain coordinates: [{"q":0,"r":0},...]
-/// @returns JSON string with path array [{"q":0,"r":0},...] or "null" if no path found
-#[wasm_bindgen]
-pub fn hex_astar(
-    start_q: i32,
-    start_r: i32,
-    goal_q: i32,
-    goal_r: i32,
-    valid_terrain_json: String,
-) -> String {
-    // Parse valid terrain from JSON
-    let valid_terrain = parse_valid_terrain_json(&valid_terrain_json);
+/// Returns the path from start to goal (both included), or None if either
+/// is not valid terrain or the goal can't be reached
+pub fn find_hex_path(
+    start: (i32, i32),
+    goal: (i32, i32),
+    valid_terrain: &HashSet<(i32, i32)>,
+) -> Option<Vec<(i32, i32)>> {
+    // Uniform cost of 1 per step
+    find_weighted_hex_path(start, goal, |hex| valid_terrain.contains(&hex).then_some(1), 1)
+}
+
+/// Hex A* pathfinding where entering a hex costs step_cost(hex), None where
+/// it can't be entered. min_step_cost has to be at most every step cost, it
+/// scales the cube distance heuristic so it never overestimates.
+/// 
+/// Returns the cheapest path from start to goal (both included), or None if
+/// either can't be entered or the goal can't be reached
+pub fn find_weighted_hex_path<F>(
+    start: (i32, i32),
+    goal: (i32, i32),
+    step_cost: F,
+    min_step_cost: i32,
+) -> Option<Vec<(i32, i32)>>
+where
+    F: Fn((i32, i32)) -> Option<i32>,
+{
+    let (start_q, start_r) = start;
+    let (goal_q, goal_r) = goal;
     
-    // Check if start and goal are in valid terrain
-    if !valid_terrain.contains(&(start_q, start_r)) || !valid_terrain.contains(&(goal_q, goal_r)) {
-        return "null".to_string();
+    // Check if start and goal can be entered
+    if step_cost(start).is_none() || step_cost(goal).is_none() {
+        return None;
     }
     
     // If start equals goal, return path with single node
-    if start_q == goal_q && start_r == goal_r {
-        return format!(r#"[{{"q":{},"r":{}}}]"#, start_q, start_r);
+    if start == goal {
+        return Some(vec![start]);
     }
     
     // Convert goal to cube for distance calculation (matches TypeScript)
     let goal_cube = axial_to_cube(goal_q, goal_r);
     
-    // Calculate heuristic function (cube distance)
+    // Calculate heuristic function (cube distance at the cheapest step cost)
     let heuristic = |q: i32, r: i32| -> i32 {
         let cube = axial_to_cube(q, r);
-        cube_distance(cube, goal_cube)
+        cube_distance(cube, goal_cube).saturating_mul(min_step_cost)
     };
     
     // Initialize A* data structures
@@ -142,7 +157,7 @@ pub fn hex_astar(
     
     // Start node (parent is itself to mark as root)
     open_set.push(AStarNode::new(start_q, start_r, 0, h_start, start_q, start_r));
-    g_scores.insert((start_q, start_r), 0);
+    g_scores.insert(start, 0);
     
     while let Some(current) = open_set.pop() {
         let current_key = (current.q, current.r);
@@ -155,43 +170,18 @@ pub fn hex_astar(
         closed_set.insert(current_key);
         
         // Check if we reached the goal
-        if current.q == goal_q && current.r == goal_r {
-            // Reconstruct path by following parent pointers
-            let mut path: Vec<(i32, i32)> = Vec::new();
-            let mut node_key = (goal_q, goal_r);
-            
-            // Follow parent pointers from goal to start
-            loop {
-                path.push(node_key);
-                
-                // Get parent for this node
-                if let Some(parent_key) = parents.get(&node_key) {
-                    // If parent is the start, add it and break
-                    if parent_key.0 == start_q && parent_key.1 == start_r {
-                        path.push((start_q, start_r));
-                        break;
-                    }
-                    node_key = *parent_key;
-                } else {
-                    // No parent in map means we're at start (shouldn't happen in normal flow)
-                    // But handle it just in case
-                    if node_key.0 != start_q || node_key.1 != start_r {
-                        path.push((start_q, start_r));
-                    }
-                    break;
-                }
+        if current_key == goal {
+            // Follow parent pointers from goal to start, the start has no parent
+            let mut path = vec![goal];
+            let mut node_key = goal;
+            while let Some(parent_key) = parents.get(&node_key) {
+                path.push(*parent_key);
+                node_key = *parent_key;
             }
             
             // Reverse path to get start-to-goal order
             path.reverse();
-            
-            // Build JSON string
-            let mut json_parts = Vec::new();
-            for (q, r) in path {
-                json_parts.push(format!(r#"{{"q":{},"r":{}}}"#, q, r));
-            }
-            
-            return format!("[{}]", json_parts.join(","));
+            return Some(path);
         }
         
         // Explore neighbors
@@ -199,25 +189,25 @@ pub fn hex_astar(
         for (nq, nr) in neighbors {
             let neighbor_key = (nq, nr);
             
-            // Skip if not in valid terrain
-            if !valid_terrain.contains(&neighbor_key) {
+            // Skip if it can't be entered
+            let Some(cost) = step_cost(neighbor_key) else {
                 continue;
-            }
+            };
             
             // Skip if already closed
             if closed_set.contains(&neighbor_key) {
                 continue;
             }
             
-            // Calculate tentative g score (uniform cost of 1 per step)
-            let tentative_g = current.g + 1;
+            // Calculate tentative g score, saturating so huge costs can't wrap
+            let tentative_g = current.g.saturating_add(cost);
             
             // Check if this is a better path
             let current_g = g_scores.get(&neighbor_key).copied().unwrap_or(i32::MAX);
             if tentative_g < current_g {
                 // This path to neighbor is better - record it
                 g_scores.insert(neighbor_key, tentative_g);
-                parents.insert(neighbor_key, (current.q, current.r));
+                parents.insert(neighbor_key, current_key);
                 let h = heuristic(nq, nr);
                 open_set.push(AStarNode::new(nq, nr, tentative_g, h, current.q, current.r));
             }
@@ -225,7 +215,32 @@ pub fn hex_astar(
     }
     
     // No path found
-    "null".to_string()
+    None
+}
+
+/// Hex A* pathfinding export, see `find_hex_path`
+/// 
+/// @param start_q - Start q coordinate (axial)
+/// @param start_r - Start r coordinate (axial)
+/// @param goal_q - Goal q coordinate (axial)
+/// @param goal_r - Goal r coordinate (axial)
+/// @param valid_terrain_json - JSON string with array of valid terrain coordinates: [{"q":0,"r":0},...]
+/// @returns JSON string with path array [{"q":0,"r":0},...] or "null" if no path found
+/// @throws if valid_terrain_json is malformed
+#[wasm_bindgen]
+pub fn hex_astar(
+    start_q: i32,
+    start_r: i32,
+    goal_q: i32,
+    goal_r: i32,
+    valid_terrain_json: String,
+) -> Result<String, JsError> {
+    let valid_terrain = parse_hex_set("valid_terrain_json", &valid_terrain_json)?;
+    
+    Ok(match find_hex_path((start_q, start_r), (goal_q, goal_r), &valid_terrain) {
+        Some(path) => hexes_to_json(&path),
+        None => "null".to_string(),
+    })
 }
 
 /// Build a path between two road points using A* pathfinding
@@ -238,6 +253,7 @@ pub fn hex_astar(
 /// @param end_r - End r coordinate (axial)
 /// @param valid_terrain_json - JSON string with array of valid terrain coordinates: [{"q":0,"r":0},...]
 /// @returns JSON string with path array excluding start, including end, or "null" if no path found
+/// @throws if valid_terrain_json is malformed
 #[wasm_bindgen]
 pub fn build_path_between_roads(
     start_q: i32,
@@ -245,92 +261,112 @@ pub fn build_path_between_roads(
     end_q: i32,
     end_r: i32,
     valid_terrain_json: String,
-) -> String {
-    // Call hex_astar to get full path
-    let full_path_json = hex_astar(start_q, start_r, end_q, end_r, valid_terrain_json);
-    
-    // If no path, return null
-    if full_path_json == "null" || full_path_json.is_empty() {
-        return "null".to_string();
-    }
+) -> Result<String, JsError> {
+    let valid_terrain = parse_hex_set("valid_terrain_json", &valid_terrain_json)?;
     
-    // Parse the path JSON
-    // Simple parsing: extract all {"q":X,"r":Y} patterns and skip first one
-    let trimmed = full_path_json.trim();
-    if trimmed == "[]" || trimmed.len() < 3 {
-        return "null".to_string();
-    }
+    // A path of a single hex (start equals end) has nothing after the start
+    Ok(match find_hex_path((start_q, start_r), (end_q, end_r), &valid_terrain) {
+        Some(path) if path.len() >= 2 => hexes_to_json(&path[1..]),
+        _ => "null".to_string(),
+    })
+}
+
+/// Packed variant of `hex_astar`
+/// 
+/// @param valid_terrain - Int32Array of valid terrain q,r pairs
+/// @returns Int32Array of path q,r pairs, or undefined if no path found
+/// @throws if valid_terrain has an odd length
+#[wasm_bindgen]
+pub fn hex_astar_packed(
+    start_q: i32,
+    start_r: i32,
+    goal_q: i32,
+    goal_r: i32,
+    valid_terrain: &[i32],
+) -> Result<Option<Vec<i32>>, JsError> {
+    let valid_terrain = unpack_hex_set("valid_terrain", valid_terrain)?;
     
-    // Find all coordinate pairs
-    let mut coords: Vec<(i32, i32)> = Vec::new();
-    let mut i = 0;
-    let chars: Vec<char> = trimmed.chars().collect();
-    while i < chars.len() {
-        if chars[i] == '{' {
-            let mut q_value: Option<i32> = None;
-            let mut r_value: Option<i32> = None;
-            
-            i += 1;
-            while i < chars.len() && chars[i] != '}' {
-                if i + 3 < chars.len() && chars[i] == '"' && chars[i + 1] == 'q' && chars[i + 2] == '"' {
-                    i += 3;
-                    while i < chars.len() && (chars[i] == ':' || chars[i] == ' ' || chars[i] == '\t') {
-                        i += 1;
-                    }
-                    if i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '-') {
-                        let start = i;
-                        i += 1;
-                        while i < chars.len() && chars[i].is_ascii_digit() {
-                            i += 1;
-                        }
-                        let num_str: String = chars[start..i].iter().collect();
-                        if let Ok(num) = num_str.parse::<i32>() {
-                            q_value = Some(num);
-                        }
-                    }
-                } else if i + 3 < chars.len() && chars[i] == '"' && chars[i + 1] == 'r' && chars[i + 2] == '"' {
-                    i += 3;
-                    while i < chars.len() && (chars[i] == ':' || chars[i] == ' ' || chars[i] == '\t') {
-                        i += 1;
-                    }
-                    if i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '-') {
-                        let start = i;
-                        i += 1;
-                        while i < chars.len() && chars[i].is_ascii_digit() {
-                            i += 1;
-                        }
-                        let num_str: String = chars[start..i].iter().collect();
-                        if let Ok(num) = num_str.parse::<i32>() {
-                            r_value = Some(num);
-                        }
-                    }
-                } else {
-                    i += 1;
-                }
-            }
-            
-            if let (Some(q), Some(r)) = (q_value, r_value) {
-                coords.push((q, r));
-            }
-        }
-        i += 1;
-    }
+    Ok(find_hex_path((start_q, start_r), (goal_q, goal_r), &valid_terrain).map(|path| pack_hexes(&path)))
+}
+
+/// Packed variant of `build_path_between_roads`
+/// 
+/// @param valid_terrain - Int32Array of valid terrain q,r pairs
+/// @returns Int32Array of path q,r pairs excluding start, including end, or undefined if no path found
+/// @throws if valid_terrain has an odd length
+#[wasm_bindgen]
+pub fn build_path_between_roads_packed(
+    start_q: i32,
+    start_r: i32,
+    end_q: i32,
+    end_r: i32,
+    valid_terrain: &[i32],
+) -> Result<Option<Vec<i32>>, JsError> {
+    let valid_terrain = unpack_hex_set("valid_terrain", valid_terrain)?;
     
-    // If path has less than 2 nodes, return null
-    if coords.len() < 2 {
-        return "null".to_string();
-    }
+    Ok(match find_hex_path((start_q, start_r), (end_q, end_r), &valid_terrain) {
+        Some(path) if path.len() >= 2 => Some(pack_hexes(&path[1..])),
+        _ => None,
+    })
+}
+
+/// Hex A* pathfinding over the generated layout, weighted by tile type
+/// Entering a hex costs what tile_costs gives for its tile in WFC_STATE, so
+/// with road cheaper than forest paths follow roads and go around forests.
+/// Hexes without a tile can't be entered.
+/// 
+/// @param start_q - Start q coordinate (axial)
+/// @param start_r - Start r coordinate (axial)
+/// @param goal_q - Goal q coordinate (axial)
+/// @param goal_r - Goal r coordinate (axial)
+/// @param tile_costs_json - JSON object with the cost of each tile type: {"grass":2,"road":1,"forest":5,"water":null}, a missing or null type can't be entered
+/// @returns JSON string with the cheapest path [{"q":0,"r":0},...] or "null" if no path found
+/// @throws if tile_costs_json is malformed, has an unknown key or a cost below 1
+#[wasm_bindgen]
+pub fn hex_astar_weighted(
+    start_q: i32,
+    start_r: i32,
+    goal_q: i32,
+    goal_r: i32,
+    tile_costs_json: String,
+) -> Result<String, JsError> {
+    let tile_costs = parse_tile_costs("tile_costs_json", &tile_costs_json)?;
     
-    // Return path excluding start (first element), including end (last element)
-    let path_without_start = &coords[1..];
+    Ok(match find_tile_weighted_path((start_q, start_r), (goal_q, goal_r), tile_costs) {
+        Some(path) => hexes_to_json(&path),
+        None => "null".to_string(),
+    })
+}
+
+/// Packed variant of `hex_astar_weighted`
+/// 
+/// @param tile_costs - Int32Array of 5 costs indexed by tile type id (Grass, Building, Road, Forest, Water), -1 where the type can't be entered
+/// @returns Int32Array of the cheapest path's q,r pairs, or undefined if no path found
+/// @throws if tile_costs doesn't have 5 entries or has a cost below 1 other than -1
+#[wasm_bindgen]
+pub fn hex_astar_weighted_packed(
+    start_q: i32,
+    start_r: i32,
+    goal_q: i32,
+    goal_r: i32,
+    tile_costs: &[i32],
+) -> Result<Option<Vec<i32>>, JsError> {
+    let tile_costs = unpack_tile_costs("tile_costs", tile_costs)?;
     
-    // Build JSON string
-    let mut json_parts = Vec::new();
-    for (q, r) in path_without_start {
-        json_parts.push(format!(r#"{{"q":{},"r":{}}}"#, q, r));
-    }
+    Ok(find_tile_weighted_path((start_q, start_r), (goal_q, goal_r), tile_costs).map(|path| pack_hexes(&path)))
+}
+
+fn find_tile_weighted_path(start: (i32, i32), goal: (i32, i32), tile_costs: TileCosts) -> Option<Vec<(i32, i32)>> {
+    // Nothing can be entered
+    let min_step_cost = tile_costs.min_cost()?;
     
-    format!("[{}]", json_parts.join(","))
+    let state = WFC_STATE.lock().unwrap();
+    find_weighted_hex_path(
+        start,
+        goal,
+        |(q, r)| state.get_tile(q, r).and_then(|tile| tile_costs.cost(tile)),
+        min_step_cost,
+    )
 }
 
 /// Validate that all road tiles are reachable from each other using A* pathfinding
@@ -340,86 +376,26 @@ pub fn build_path_between_roads(
 /// 
 /// @param roads_json - JSON string with array of road coordinates: [{"q":0,"r":0},{"q":1,"r":0},...]
 /// @returns true if all roads are reachable from source, false otherwise
+/// @throws if roads_json is malformed
 #[wasm_bindgen]
-pub fn validate_road_connectivity(roads_json: String) -> bool {
-    // Parse roads from JSON
-    // Simple JSON parsing without serde to keep WASM size small
-    let mut roads: Vec<(i32, i32)> = Vec::new();
-    
-    // Remove whitespace and brackets
-    let trimmed = roads_json.trim();
-    if trimmed.is_empty() || trimmed == "[]" {
-        return true; // Empty roads is trivially connected
-    }
-
-    // Simple JSON parsing: find all {"q":X,"r":Y} patterns
-    // This is a simplified parser that handles the expected format: [{"q":0,"r":0},...]
-    let mut i = 0;
-    let chars: Vec<char> = trimmed.chars().collect();
-    while i < chars.len() {
-        // Look for opening brace
-        if chars[i] == '{' {
-            let mut q_value: Option<i32> = None;
-            let mut r_value: Option<i32> = None;
-            
-            i += 1;
-            while i < chars.len() && chars[i] != '}' {
-                // Look for "q" or "r" followed by colon and number
-                if i + 3 < chars.len() && chars[i] == '"' && chars[i + 1] == 'q' && chars[i + 2] == '"' {
-                    i += 3;
-                    // Skip colon and whitespace
-                    while i < chars.len() && (chars[i] == ':' || chars[i] == ' ' || chars[i] == '\t') {
-                        i += 1;
-                    }
-                    // Parse number
-                    if i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '-') {
-                        let start = i;
-                        i += 1;
-                        while i < chars.len() && chars[i].is_ascii_digit() {
-                            i += 1;
-                        }
-                        let num_str: String = chars[start..i].iter().collect();
-                        if let Ok(num) = num_str.parse::<i32>() {
-                            q_value = Some(num);
-                        }
-                    }
-                } else if i + 3 < chars.len() && chars[i] == '"' && chars[i + 1] == 'r' && chars[i + 2] == '"' {
-                    i += 3;
-                    // Skip colon and whitespace
-                    while i < chars.len() && (chars[i] == ':' || chars[i] == ' ' || chars[i] == '\t') {
-                        i += 1;
-                    }
-                    // Parse number
-                    if i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '-') {
-                        let start = i;
-                        i += 1;
-                        while i < chars.len() && chars[i].is_ascii_digit() {
-                            i += 1;
-                        }
-                        let num_str: String = chars[start..i].iter().collect();
-                        if let Ok(num) = num_str.parse::<i32>() {
-                            r_value = Some(num);
-                        }
-                    }
-                } else {
-                    i += 1;
-                }
-            }
-            
-            if let (Some(q), Some(r)) = (q_value, r_value) {
-                roads.push((q, r));
-            }
-        }
-        i += 1;
-    }
+pub fn validate_road_connectivity(roads_json: String) -> Result<bool, JsError> {
+    Ok(roads_connected(&parse_hexes("roads_json", &roads_json)?))
+}
 
-    if roads.is_empty() {
-        return true;
-    }
+/// Packed variant of `validate_road_connectivity`
+/// 
+/// @param roads - Int32Array of road q,r pairs
+/// @returns true if all roads are reachable from source, false otherwise
+/// @throws if roads has an odd length
+#[wasm_bindgen]
+pub fn validate_road_connectivity_packed(roads: &[i32]) -> Result<bool, JsError> {
+    Ok(roads_connected(&unpack_hexes("roads", roads)?))
+}
 
-    if roads.len() == 1 {
-        // Single road - check if it has at least one road neighbor
-        // For single road, we consider it valid (can't check neighbors without more context)
+/// Whether every road is reachable from the first one
+fn roads_connected(roads: &[(i32, i32)]) -> bool {
+    if roads.len() <= 1 {
+        // No roads or a single road is trivially connected
         return true;
     }
 
@@ -440,3 +416,130 @@ pub fn validate_road_connectivity(roads_json: String) -> bool {
     true // All roads reachable from source
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hex_utils::generate_hex_grid;
+    use crate::packed::IMPASSABLE;
+    use crate::types::TileType;
+
+    const COSTS: &str = r#"{"grass":3,"road":1,"forest":5,"water":null}"#;
+
+    /// A ring-6 grass grid with the tiles given overriding it
+    fn layout(tiles: &[((i32, i32), TileType)]) -> HashMap<(i32, i32), TileType> {
+        let mut layout: HashMap<(i32, i32), TileType> = generate_hex_grid(6, 0, 0)
+            .into_iter()
+            .map(|hex| ((hex.q, hex.r), TileType::Grass))
+            .collect();
+        layout.extend(tiles.iter().copied());
+        layout
+    }
+
+    fn weighted_path(
+        layout: &HashMap<(i32, i32), TileType>,
+        start: (i32, i32),
+        goal: (i32, i32),
+        tile_costs: TileCosts,
+    ) -> Option<Vec<(i32, i32)>> {
+        let cost = |hex| layout.get(&hex).and_then(|&tile| tile_costs.cost(tile));
+        find_weighted_hex_path(start, goal, cost, tile_costs.min_cost().unwrap())
+    }
+
+    fn path_cost(
+        layout: &HashMap<(i32, i32), TileType>,
+        path: &[(i32, i32)],
+        tile_costs: TileCosts,
+    ) -> i32 {
+        for pair in path.windows(2) {
+            assert_eq!(hex_distance(pair[0].0, pair[0].1, pair[1].0, pair[1].1), 1);
+        }
+        path[1..].iter().map(|hex| tile_costs.cost(layout[hex]).unwrap()).sum()
+    }
+
+    #[test]
+    fn paths_prefer_roads_over_forest() {
+        // Forest straight between start and goal, a road one row over and water on the other side
+        let mut tiles: Vec<((i32, i32), TileType)> =
+            (-3..=3).map(|q| ((q, 0), TileType::Forest)).collect();
+        tiles.extend((-4..=3).map(|q| ((q, 1), TileType::Road)));
+        tiles.extend((-6..=6).map(|q| ((q, -1), TileType::Water)));
+        let layout = layout(&tiles);
+        let tile_costs = parse_tile_costs("tile_costs_json", COSTS).unwrap();
+
+        let path = weighted_path(&layout, (-4, 0), (4, 0), tile_costs).unwrap();
+        let mut expected = vec![(-4, 0)];
+        expected.extend((-4..=3).map(|q| (q, 1)));
+        expected.push((4, 0));
+        assert_eq!(path, expected);
+        assert_eq!(path_cost(&layout, &path, tile_costs), 8 + 3);
+
+        // Without the road the grass row is still cheaper than the forest
+        for tile in tiles.iter_mut().filter(|(_, tile)| *tile == TileType::Road) {
+            tile.1 = TileType::Grass;
+        }
+        let layout = self::layout(&tiles);
+        let path = weighted_path(&layout, (-4, 0), (4, 0), tile_costs).unwrap();
+        assert_eq!(path_cost(&layout, &path, tile_costs), 9 * 3);
+        assert!(path.iter().all(|hex| layout[hex] == TileType::Grass));
+    }
+
+    #[test]
+    fn impassable_water_is_never_entered() {
+        // Water across the middle of the grid with one gap at the edge
+        let mut tiles: Vec<((i32, i32), TileType)> =
+            (-6..=5).map(|r| ((0, r), TileType::Water)).collect();
+        let layout = layout(&tiles);
+        let tile_costs = parse_tile_costs("tile_costs_json", COSTS).unwrap();
+        let path = weighted_path(&layout, (-3, 0), (3, 0), tile_costs).unwrap();
+        assert!(path.iter().all(|hex| layout[hex] != TileType::Water));
+        assert!(path.contains(&(0, 6)));
+        // The goal itself being water doesn't let the path in either
+        assert_eq!(weighted_path(&layout, (-3, 0), (0, 2), tile_costs), None);
+
+        // Closing the gap cuts the grid in two
+        tiles.push(((0, 6), TileType::Water));
+        let layout = self::layout(&tiles);
+        assert_eq!(weighted_path(&layout, (-3, 0), (3, 0), tile_costs), None);
+
+        // Unless water is given a cost, then it is crossed once
+        let bridged = parse_tile_costs("tile_costs_json", r#"{"grass":3,"water":20}"#).unwrap();
+        let path = weighted_path(&layout, (-3, 0), (3, 0), bridged).unwrap();
+        assert_eq!(path.iter().filter(|hex| layout[hex] == TileType::Water).count(), 1);
+
+        // The packed costs agree with the JSON ones
+        let packed =
+            unpack_tile_costs("tile_costs", &[3, IMPASSABLE, IMPASSABLE, IMPASSABLE, 20]).unwrap();
+        assert_eq!(weighted_path(&layout, (-3, 0), (3, 0), packed), Some(path));
+    }
+
+    #[test]
+    fn zero_and_negative_costs_are_rejected() {
+        // JSON marks impassable types with null, so -1 is as wrong as any other negative
+        for json in [
+            r#"{"road":0}"#,
+            r#"{"grass":2,"forest":-1}"#,
+            r#"{"water":-5}"#,
+            r#"{"road":-2147483648}"#,
+        ] {
+            let error = parse_tile_costs("tile_costs_json", json).unwrap_err();
+            assert_eq!(error.arg, "tile_costs_json", "{json}");
+        }
+        for costs in [
+            [1, 1, 0, 1, 1],
+            [1, 1, 1, 1, -2],
+            [i32::MIN, 1, 1, 1, 1],
+            [0, IMPASSABLE, 1, 1, 1],
+        ] {
+            let error = unpack_tile_costs("tile_costs", &costs).unwrap_err();
+            assert_eq!(error.arg, "tile_costs", "{costs:?}");
+        }
+        assert!(unpack_tile_costs("tile_costs", &[1, 1, 1, 1]).is_err());
+        assert!(unpack_tile_costs("tile_costs", &[1, 1, 1, 1, 1, 1]).is_err());
+
+        let costs = unpack_tile_costs("tile_costs", &[2, IMPASSABLE, 1, 5, IMPASSABLE]).unwrap();
+        let costs = TileType::ALL.map(|tile| costs.cost(tile));
+        assert_eq!(costs, [Some(2), None, Some(1), Some(5), None]);
+        let costs = unpack_tile_costs("tile_costs", &[IMPASSABLE; 5]).unwrap();
+        assert_eq!(costs.min_cost(), None);
+    }
+}
@@ -1,7 +1,9 @@
-/// Chunk management module
+//! Chunk management module
 
 use wasm_bindgen::prelude::*;
-use crate::hex_utils::{parse_valid_terrain_json, hex_distance};
+use crate::hex_utils::hex_distance;
+use crate::json::{parse_hexes, parse_hex_set, parse_chunk_states, hexes_to_json, ChunkState};
+use crate::packed::{unpack_hexes, unpack_hex_set, unpack_chunk_states, pack_hexes};
 
 /// Calculate chunk radius for distance threshold calculations
 /// The chunk radius is the distance from chunk center to the outer boundary
@@ -26,6 +28,19 @@ pub fn calculate_chunk_radius(rings: i32) -> i32 {
 /// @returns JSON string with array of 6 neighbor coordinates: [{"q":0,"r":0},...]
 #[wasm_bindgen]
 pub fn calculate_chunk_neighbors(center_q: i32, center_r: i32, rings: i32) -> String {
+    hexes_to_json(&chunk_neighbors(center_q, center_r, rings))
+}
+
+/// Packed variant of `calculate_chunk_neighbors`
+/// 
+/// @returns Int32Array of 6 neighbor q,r pairs
+#[wasm_bindgen]
+pub fn calculate_chunk_neighbors_packed(center_q: i32, center_r: i32, rings: i32) -> Vec<i32> {
+    pack_hexes(&chunk_neighbors(center_q, center_r, rings))
+}
+
+/// The 6 neighbor chunk centers, see `calculate_chunk_neighbors`
+fn chunk_neighbors(center_q: i32, center_r: i32, rings: i32) -> Vec<(i32, i32)> {
     let mut neighbors = Vec::new();
     
     // Base offset vector: (rings, rings+1) for rings>0, or (1, 0) for rings=0
@@ -60,13 +75,7 @@ pub fn calculate_chunk_neighbors(center_q: i32, center_r: i32, rings: i32) -> St
         current_r = next_r;
     }
     
-    // Convert to JSON
-    let mut json_parts = Vec::new();
-    for (q, r) in neighbors {
-        json_parts.push(format!(r#"{{"q":{},"r":{}}}"#, q, r));
-    }
-    
-    format!("[{}]", json_parts.join(","))
+    neighbors
 }
 
 /// Find the immediate neighbor chunk of the current chunk that is nearest to the current tile
@@ -79,6 +88,7 @@ pub fn calculate_chunk_neighbors(center_q: i32, center_r: i32, rings: i32) -> St
 /// @param rings - Number of rings per chunk
 /// @param existing_chunks_json - JSON array of existing chunk positions: [{"q":0,"r":0},...]
 /// @returns JSON string with nearest neighbor info: {"neighbor":{"q":0,"r":0},"distance":1.5,"isInstantiated":true} or "null"
+/// @throws if existing_chunks_json is malformed
 #[wasm_bindgen]
 pub fn find_nearest_neighbor_chunk(
     current_chunk_q: i32,
@@ -87,24 +97,68 @@ pub fn find_nearest_neighbor_chunk(
     current_tile_r: i32,
     rings: i32,
     existing_chunks_json: String,
-) -> String {
+) -> Result<String, JsError> {
     // Parse existing chunks
-    let existing_chunks = parse_valid_terrain_json(&existing_chunks_json);
-    
-    // Calculate immediate neighbors
-    let neighbors_json = calculate_chunk_neighbors(current_chunk_q, current_chunk_r, rings);
-    let neighbors = parse_valid_terrain_json(&neighbors_json);
+    let existing_chunks = parse_hex_set("existing_chunks_json", &existing_chunks_json)?;
     
-    if neighbors.is_empty() {
-        return "null".to_string();
+    if let Some((neighbor, distance)) = nearest_neighbor_chunk(
+        (current_chunk_q, current_chunk_r),
+        (current_tile_q, current_tile_r),
+        rings,
+    ) {
+        let is_instantiated = existing_chunks.contains(&neighbor);
+        // Return distance as hex distance (TypeScript will convert to world distance if needed)
+        Ok(format!(
+            r#"{{"neighbor":{{"q":{},"r":{}}},"distance":{},"isInstantiated":{}}}"#,
+            neighbor.0, neighbor.1, distance, is_instantiated
+        ))
+    } else {
+        Ok("null".to_string())
     }
+}
+
+/// Packed variant of `find_nearest_neighbor_chunk`
+/// 
+/// @param existing_chunks - Int32Array of existing chunk q,r pairs
+/// @returns Int32Array [q, r, distance, isInstantiated (0 or 1)] or undefined
+/// @throws if existing_chunks has an odd length
+#[wasm_bindgen]
+pub fn find_nearest_neighbor_chunk_packed(
+    current_chunk_q: i32,
+    current_chunk_r: i32,
+    current_tile_q: i32,
+    current_tile_r: i32,
+    rings: i32,
+    existing_chunks: &[i32],
+) -> Result<Option<Vec<i32>>, JsError> {
+    let existing_chunks = unpack_hex_set("existing_chunks", existing_chunks)?;
+    
+    Ok(nearest_neighbor_chunk(
+        (current_chunk_q, current_chunk_r),
+        (current_tile_q, current_tile_r),
+        rings,
+    )
+    .map(|(neighbor, distance)| {
+        let is_instantiated = existing_chunks.contains(&neighbor);
+        vec![neighbor.0, neighbor.1, distance, is_instantiated as i32]
+    }))
+}
+
+/// The neighbor chunk of current_chunk nearest to current_tile and its hex distance
+fn nearest_neighbor_chunk(
+    current_chunk: (i32, i32),
+    current_tile: (i32, i32),
+    rings: i32,
+) -> Option<((i32, i32), i32)> {
+    // Calculate immediate neighbors
+    let neighbors = chunk_neighbors(current_chunk.0, current_chunk.1, rings);
     
     // Find which of the immediate neighbors is closest to the current tile (in hex distance)
     let mut nearest_neighbor: Option<(i32, i32)> = None;
     let mut min_distance = i32::MAX;
     
     for neighbor_pos in &neighbors {
-        let hex_dist = hex_distance(current_tile_q, current_tile_r, neighbor_pos.0, neighbor_pos.1);
+        let hex_dist = hex_distance(current_tile.0, current_tile.1, neighbor_pos.0, neighbor_pos.1);
         
         if hex_dist < min_distance {
             min_distance = hex_dist;
@@ -112,16 +166,7 @@ pub fn find_nearest_neighbor_chunk(
         }
     }
     
-    if let Some(neighbor) = nearest_neighbor {
-        let is_instantiated = existing_chunks.contains(&neighbor);
-        // Return distance as hex distance (TypeScript will convert to world distance if needed)
-        format!(
-            r#"{{"neighbor":{{"q":{},"r":{}}},"distance":{},"isInstantiated":{}}}"#,
-            neighbor.0, neighbor.1, min_distance, is_instantiated
-        )
-    } else {
-        "null".to_string()
-    }
+    nearest_neighbor.map(|neighbor| (neighbor, min_distance))
 }
 
 /// Disable chunks that are more than max_distance away from the current chunk
@@ -132,130 +177,67 @@ pub fn find_nearest_neighbor_chunk(
 /// @param all_chunks_json - JSON array of all chunk positions with enabled state: [{"q":0,"r":0,"enabled":true},...]
 /// @param max_distance - Maximum hex distance threshold
 /// @returns JSON string with chunks to enable/disable: {"toDisable":[{"q":0,"r":0},...],"toEnable":[{"q":0,"r":0},...]}
+/// @throws if all_chunks_json is malformed
 #[wasm_bindgen]
 pub fn disable_distant_chunks(
     current_chunk_q: i32,
     current_chunk_r: i32,
     all_chunks_json: String,
     max_distance: i32,
-) -> String {
+) -> Result<String, JsError> {
     // Parse chunks with enabled state
-    // Format: [{"q":0,"r":0,"enabled":true},...]
-    let mut chunks: Vec<(i32, i32, bool)> = Vec::new();
+    let chunks = parse_chunk_states("all_chunks_json", &all_chunks_json)?;
     
-    let trimmed = all_chunks_json.trim();
-    if trimmed.is_empty() || trimmed == "[]" {
-        return r#"{"toDisable":[],"toEnable":[]}"#.to_string();
-    }
+    let changes = distant_chunk_changes((current_chunk_q, current_chunk_r), &chunks, max_distance);
+    let to_disable: Vec<(i32, i32)> = changes.iter().filter(|c| !c.enabled).map(|c| (c.q, c.r)).collect();
+    let to_enable: Vec<(i32, i32)> = changes.iter().filter(|c| c.enabled).map(|c| (c.q, c.r)).collect();
     
-    // Simple JSON parsing: find all {"q":X,"r":Y,"enabled":Z} patterns
-    let mut i = 0;
-    let chars: Vec<char> = trimmed.chars().collect();
-    while i < chars.len() {
-        if chars[i] == '{' {
-            let mut q_value: Option<i32> = None;
-            let mut r_value: Option<i32> = None;
-            let mut enabled_value: Option<bool> = None;
-            
-            i += 1;
-            while i < chars.len() && chars[i] != '}' {
-                // Look for "q", "r", or "enabled"
-                if i + 3 < chars.len() && chars[i] == '"' && chars[i + 1] == 'q' && chars[i + 2] == '"' {
-                    i += 3;
-                    while i < chars.len() && (chars[i] == ':' || chars[i] == ' ' || chars[i] == '\t') {
-                        i += 1;
-                    }
-                    if i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '-') {
-                        let start = i;
-                        i += 1;
-                        while i < chars.len() && chars[i].is_ascii_digit() {
-                            i += 1;
-                        }
-                        let num_str: String = chars[start..i].iter().collect();
-                        if let Ok(num) = num_str.parse::<i32>() {
-                            q_value = Some(num);
-                        }
-                    }
-                } else if i + 3 < chars.len() && chars[i] == '"' && chars[i + 1] == 'r' && chars[i + 2] == '"' {
-                    i += 3;
-                    while i < chars.len() && (chars[i] == ':' || chars[i] == ' ' || chars[i] == '\t') {
-                        i += 1;
-                    }
-                    if i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '-') {
-                        let start = i;
-                        i += 1;
-                        while i < chars.len() && chars[i].is_ascii_digit() {
-                            i += 1;
-                        }
-                        let num_str: String = chars[start..i].iter().collect();
-                        if let Ok(num) = num_str.parse::<i32>() {
-                            r_value = Some(num);
-                        }
-                    }
-                } else if i + 9 < chars.len() && chars[i] == '"' && chars[i + 1] == 'e' && chars[i + 2] == 'n' 
-                    && chars[i + 3] == 'a' && chars[i + 4] == 'b' && chars[i + 5] == 'l' 
-                    && chars[i + 6] == 'e' && chars[i + 7] == 'd' && chars[i + 8] == '"' {
-                    i += 9;
-                    while i < chars.len() && (chars[i] == ':' || chars[i] == ' ' || chars[i] == '\t') {
-                        i += 1;
-                    }
-                    if i < chars.len() {
-                        if i + 4 < chars.len() && chars[i] == 't' && chars[i + 1] == 'r' 
-                            && chars[i + 2] == 'u' && chars[i + 3] == 'e' {
-                            enabled_value = Some(true);
-                            i += 4;
-                        } else if i + 5 < chars.len() && chars[i] == 'f' && chars[i + 1] == 'a' 
-                            && chars[i + 2] == 'l' && chars[i + 3] == 's' && chars[i + 4] == 'e' {
-                            enabled_value = Some(false);
-                            i += 5;
-                        }
-                    }
-                } else {
-                    i += 1;
-                }
-            }
-            
-            if let (Some(q), Some(r), Some(enabled)) = (q_value, r_value, enabled_value) {
-                chunks.push((q, r, enabled));
-            }
-        }
-        i += 1;
-    }
+    // Build JSON response
+    Ok(format!(
+        r#"{{"toDisable":{},"toEnable":{}}}"#,
+        hexes_to_json(&to_disable),
+        hexes_to_json(&to_enable)
+    ))
+}
+
+/// Packed variant of `disable_distant_chunks`
+/// 
+/// @param all_chunks - Int32Array of q,r,enabled triples, enabled is 0 or 1
+/// @returns Int32Array of q,r,enabled triples for the chunks to change, in input order, enabled is their new state
+/// @throws if all_chunks isn't whole triples or an enabled value isn't 0 or 1
+#[wasm_bindgen]
+pub fn disable_distant_chunks_packed(
+    current_chunk_q: i32,
+    current_chunk_r: i32,
+    all_chunks: &[i32],
+    max_distance: i32,
+) -> Result<Vec<i32>, JsError> {
+    let chunks = unpack_chunk_states("all_chunks", all_chunks)?;
     
-    // Calculate which chunks to disable/enable
-    let mut to_disable: Vec<(i32, i32)> = Vec::new();
-    let mut to_enable: Vec<(i32, i32)> = Vec::new();
+    let changes = distant_chunk_changes((current_chunk_q, current_chunk_r), &chunks, max_distance);
     
-    for (chunk_q, chunk_r, currently_enabled) in chunks {
-        let distance = hex_distance(current_chunk_q, current_chunk_r, chunk_q, chunk_r);
+    Ok(changes.iter().flat_map(|c| [c.q, c.r, c.enabled as i32]).collect())
+}
+
+/// Chunks whose enabled state has to change, with their new state, in input order
+fn distant_chunk_changes(
+    current_chunk: (i32, i32),
+    chunks: &[ChunkState],
+    max_distance: i32,
+) -> Vec<ChunkState> {
+    let mut changes: Vec<ChunkState> = Vec::new();
+    
+    for chunk in chunks {
+        let distance = hex_distance(current_chunk.0, current_chunk.1, chunk.q, chunk.r);
         
-        if distance > max_distance {
-            if currently_enabled {
-                to_disable.push((chunk_q, chunk_r));
-            }
-        } else {
-            if !currently_enabled {
-                to_enable.push((chunk_q, chunk_r));
-            }
+        // Enabled exactly when within the distance threshold
+        let should_enable = distance <= max_distance;
+        if chunk.enabled != should_enable {
+            changes.push(ChunkState { enabled: should_enable, ..*chunk });
         }
     }
     
-    // Build JSON response
-    let mut disable_parts = Vec::new();
-    for (q, r) in &to_disable {
-        disable_parts.push(format!(r#"{{"q":{},"r":{}}}"#, q, r));
-    }
-    
-    let mut enable_parts = Vec::new();
-    for (q, r) in &to_enable {
-        enable_parts.push(format!(r#"{{"q":{},"r":{}}}"#, q, r));
-    }
-    
-    format!(
-        r#"{{"toDisable":[{}],"toEnable":[{}]}}"#,
-        disable_parts.join(","),
-        enable_parts.join(",")
-    )
+    changes
 }
 
 /// Calculate which chunk contains a given tile
@@ -266,30 +248,52 @@ pub fn disable_distant_chunks(
 /// @param rings - Number of rings per chunk
 /// @param chunk_positions_json - JSON array of chunk positions: [{"q":0,"r":0},...]
 /// @returns JSON string with chunk position: {"q":0,"r":0} or "null"
+/// @throws if chunk_positions_json is malformed
 #[wasm_bindgen]
 pub fn calculate_chunk_for_tile(
     tile_q: i32,
     tile_r: i32,
     rings: i32,
     chunk_positions_json: String,
-) -> String {
+) -> Result<String, JsError> {
     // Parse chunk positions
-    let chunk_positions = parse_valid_terrain_json(&chunk_positions_json);
+    let chunk_positions = parse_hexes("chunk_positions_json", &chunk_positions_json)?;
     
-    if chunk_positions.is_empty() {
-        return "null".to_string();
-    }
+    Ok(match chunk_for_tile((tile_q, tile_r), rings, &chunk_positions) {
+        Some(chunk) => format!(r#"{{"q":{},"r":{}}}"#, chunk.0, chunk.1),
+        None => "null".to_string(),
+    })
+}
+
+/// Packed variant of `calculate_chunk_for_tile`
+/// 
+/// @param chunk_positions - Int32Array of chunk q,r pairs
+/// @returns Int32Array [q, r] of the chunk, or undefined if not found
+/// @throws if chunk_positions has an odd length
+#[wasm_bindgen]
+pub fn calculate_chunk_for_tile_packed(
+    tile_q: i32,
+    tile_r: i32,
+    rings: i32,
+    chunk_positions: &[i32],
+) -> Result<Option<Vec<i32>>, JsError> {
+    let chunk_positions = unpack_hexes("chunk_positions", chunk_positions)?;
     
+    Ok(chunk_for_tile((tile_q, tile_r), rings, &chunk_positions).map(|chunk| pack_hexes(&[chunk])))
+}
+
+/// The chunk whose center is nearest to tile, among those within rings of it
+fn chunk_for_tile(tile: (i32, i32), rings: i32, chunk_positions: &[(i32, i32)]) -> Option<(i32, i32)> {
     let mut closest_chunk: Option<(i32, i32)> = None;
     let mut min_distance = i32::MAX;
     
     // Find chunk whose center is closest to the tile and within the chunk's boundary
-    for chunk_pos in &chunk_positions {
-        let distance = hex_distance(tile_q, tile_r, chunk_pos.0, chunk_pos.1);
+    for chunk_pos in chunk_positions {
+        let distance = hex_distance(tile.0, tile.1, chunk_pos.0, chunk_pos.1);
         
         // If tile is exactly at chunk center, return immediately
         if distance == 0 {
-            return format!(r#"{{"q":{},"r":{}}}"#, chunk_pos.0, chunk_pos.1);
+            return Some(*chunk_pos);
         }
         
         // Check if tile is within this chunk's boundary (distance <= rings)
@@ -302,10 +306,5 @@ pub fn calculate_chunk_for_tile(
         }
     }
     
-    if let Some(chunk) = closest_chunk {
-        format!(r#"{{"q":{},"r":{}}}"#, chunk.0, chunk.1)
-    } else {
-        "null".to_string()
-    }
+    closest_chunk
 }
-
@@ -1,4 +1,4 @@
-/// Hex coordinate utilities module
+//! Hex coordinate utilities module
 
 use std::collections::HashSet;
 use crate::types::{HexCoord, CubeCoord};
@@ -130,149 +130,3 @@ pub fn generate_hex_grid(max_layer: i32, center_q: i32, center_r: i32) -> Vec<He
     
     grid
 }
-
-/// Parse valid terrain JSON string into HashSet
-/// Format: [{"q":0,"r":0},{"q":1,"r":0},...]
-/// Returns empty HashSet if parsing fails
-pub fn parse_valid_terrain_json(valid_terrain_json: &str) -> HashSet<(i32, i32)> {
-    let mut valid_terrain = HashSet::new();
-    
-    let trimmed = valid_terrain_json.trim();
-    if trimmed.is_empty() || trimmed == "[]" {
-        return valid_terrain;
-    }
-    
-    // Simple JSON parsing: find all {"q":X,"r":Y} patterns
-    let mut i = 0;
-    let chars: Vec<char> = trimmed.chars().collect();
-    while i < chars.len() {
-        // Look for opening brace
-        if chars[i] == '{' {
-            let mut q_value: Option<i32> = None;
-            let mut r_value: Option<i32> = None;
-            
-            i += 1;
-            while i < chars.len() && chars[i] != '}' {
-                // Look for "q" or "r" followed by colon and number
-                if i + 3 < chars.len() && chars[i] == '"' && chars[i + 1] == 'q' && chars[i + 2] == '"' {
-                    i += 3;
-                    // Skip colon and whitespace
-                    while i < chars.len() && (chars[i] == ':' || chars[i] == ' ' || chars[i] == '\t') {
-                        i += 1;
-                    }
-                    // Parse number
-                    if i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '-') {
-                        let start = i;
-                        i += 1;
-                        while i < chars.len() && chars[i].is_ascii_digit() {
-                            i += 1;
-                        }
-                        let num_str: String = chars[start..i].iter().collect();
-                        if let Ok(num) = num_str.parse::<i32>() {
-                            q_value = Some(num);
-                        }
-                    }
-                } else if i + 3 < chars.len() && chars[i] == '"' && chars[i + 1] == 'r' && chars[i + 2] == '"' {
-                    i += 3;
-                    // Skip colon and whitespace
-                    while i < chars.len() && (chars[i] == ':' || chars[i] == ' ' || chars[i] == '\t') {
-                        i += 1;
-                    }
-                    // Parse number
-                    if i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '-') {
-                        let start = i;
-                        i += 1;
-                        while i < chars.len() && chars[i].is_ascii_digit() {
-                            i += 1;
-                        }
-                        let num_str: String = chars[start..i].iter().collect();
-                        if let Ok(num) = num_str.parse::<i32>() {
-                            r_value = Some(num);
-                        }
-                    }
-                } else {
-                    i += 1;
-                }
-            }
-            
-            if let (Some(q), Some(r)) = (q_value, r_value) {
-                valid_terrain.insert((q, r));
-            }
-        }
-        i += 1;
-    }
-    
-    valid_terrain
-}
-
-/// Parse path JSON and return vector of coordinates
-/// Format: [{"q":0,"r":0},{"q":1,"r":0},...]
-pub fn parse_path_json(path_json: &str) -> Vec<(i32, i32)> {
-    let mut path = Vec::new();
-    
-    if path_json == "null" || path_json.is_empty() {
-        return path;
-    }
-    
-    let trimmed = path_json.trim();
-    if trimmed == "[]" || trimmed.len() < 3 {
-        return path;
-    }
-    
-    // Simple JSON parsing: find all {"q":X,"r":Y} patterns
-    let mut i = 0;
-    let chars: Vec<char> = trimmed.chars().collect();
-    while i < chars.len() {
-        if chars[i] == '{' {
-            let mut q_value: Option<i32> = None;
-            let mut r_value: Option<i32> = None;
-            
-            i += 1;
-            while i < chars.len() && chars[i] != '}' {
-                if i + 3 < chars.len() && chars[i] == '"' && chars[i + 1] == 'q' && chars[i + 2] == '"' {
-                    i += 3;
-                    while i < chars.len() && (chars[i] == ':' || chars[i] == ' ' || chars[i] == '\t') {
-                        i += 1;
-                    }
-                    if i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '-') {
-                        let start = i;
-                        i += 1;
-                        while i < chars.len() && chars[i].is_ascii_digit() {
-                            i += 1;
-                        }
-                        let num_str: String = chars[start..i].iter().collect();
-                        if let Ok(num) = num_str.parse::<i32>() {
-                            q_value = Some(num);
-                        }
-                    }
-                } else if i + 3 < chars.len() && chars[i] == '"' && chars[i + 1] == 'r' && chars[i + 2] == '"' {
-                    i += 3;
-                    while i < chars.len() && (chars[i] == ':' || chars[i] == ' ' || chars[i] == '\t') {
-                        i += 1;
-                    }
-                    if i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '-') {
-                        let start = i;
-                        i += 1;
-                        while i < chars.len() && chars[i].is_ascii_digit() {
-                            i += 1;
-                        }
-                        let num_str: String = chars[start..i].iter().collect();
-                        if let Ok(num) = num_str.parse::<i32>() {
-                            r_value = Some(num);
-                        }
-                    }
-                } else {
-                    i += 1;
-                }
-            }
-            
-            if let (Some(q), Some(r)) = (q_value, r_value) {
-                path.push((q, r));
-            }
-        }
-        i += 1;
-    }
-    
-    path
-}
-
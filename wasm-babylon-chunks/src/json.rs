@@ -0,0 +1,242 @@
+//! Typed JSON input and output for the exports
+//!
+//! Every export that takes a JSON argument parses it here with serde. Input
+//! that isn't what the export expects (not JSON, a missing key, a decimal or
+//! exponent where a whole number belongs) fails the whole call with an
+//! `InputError` naming the argument. It reaches JS as a thrown `Error`, rather
+//! than the entry being dropped and an empty result coming back.
+
+use std::collections::HashSet;
+use std::fmt;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use crate::types::{HexCoord, TileType};
+
+/// A malformed argument to one of the exports
+#[derive(Debug)]
+pub struct InputError {
+    /// Name of the export parameter, e.g. `valid_terrain_json`
+    pub arg: &'static str,
+    pub message: String,
+}
+
+impl InputError {
+    pub fn new(arg: &'static str, message: String) -> Self {
+        InputError { arg, message }
+    }
+}
+
+impl fmt::Display for InputError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.arg, self.message)
+    }
+}
+
+/// Lets the exports return `Result<_, JsError>` and use `?` on parse results
+impl std::error::Error for InputError {}
+
+/// Chunk position and whether it is currently enabled
+/// Format: {"q":0,"r":0,"enabled":true}
+#[derive(Clone, Copy, Debug, Deserialize)]
+pub struct ChunkState {
+    pub q: i32,
+    pub r: i32,
+    pub enabled: bool,
+}
+
+/// Rules for building placement
+/// Format: {"minAdjacentRoads":1}, every key optional
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BuildingRules {
+    #[serde(default = "default_min_adjacent_roads")]
+    pub min_adjacent_roads: i32,
+}
+
+fn default_min_adjacent_roads() -> i32 {
+    1
+}
+
+fn parse<T: DeserializeOwned>(arg: &'static str, json: &str) -> Result<T, InputError> {
+    serde_json::from_str(json).map_err(|e| InputError::new(arg, e.to_string()))
+}
+
+/// Parse a hex list in input order, duplicates kept
+/// Format: [{"q":0,"r":0},{"q":1,"r":0},...], other keys are ignored
+pub fn parse_hexes(arg: &'static str, json: &str) -> Result<Vec<(i32, i32)>, InputError> {
+    let hexes: Vec<HexCoord> = parse(arg, json)?;
+    Ok(hexes.into_iter().map(|hex| (hex.q, hex.r)).collect())
+}
+
+/// Parse a hex list into a set for membership checks
+pub fn parse_hex_set(arg: &'static str, json: &str) -> Result<HashSet<(i32, i32)>, InputError> {
+    Ok(parse_hexes(arg, json)?.into_iter().collect())
+}
+
+/// Parse a chunk list
+/// Format: [{"q":0,"r":0,"enabled":true},...]
+pub fn parse_chunk_states(arg: &'static str, json: &str) -> Result<Vec<ChunkState>, InputError> {
+    parse(arg, json)
+}
+
+impl BuildingRules {
+    /// minAdjacentRoads has to be 0-6 (a hex has 6 neighbors)
+    pub fn check(self, arg: &'static str) -> Result<Self, InputError> {
+        if !(0..=6).contains(&self.min_adjacent_roads) {
+            return Err(InputError::new(
+                arg,
+                format!("minAdjacentRoads must be between 0 and 6, got {}", self.min_adjacent_roads),
+            ));
+        }
+        Ok(self)
+    }
+}
+
+/// Cost of moving onto each tile type, a missing or null type can't be entered
+/// Format: {"grass":2,"road":1,"forest":5,"water":null}, keys as in get_stats
+#[derive(Clone, Copy, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TileCosts {
+    #[serde(default)]
+    pub grass: Option<i32>,
+    #[serde(default)]
+    pub building: Option<i32>,
+    #[serde(default)]
+    pub road: Option<i32>,
+    #[serde(default)]
+    pub forest: Option<i32>,
+    #[serde(default)]
+    pub water: Option<i32>,
+}
+
+impl TileCosts {
+    pub fn cost(&self, tile: TileType) -> Option<i32> {
+        match tile {
+            TileType::Grass => self.grass,
+            TileType::Building => self.building,
+            TileType::Road => self.road,
+            TileType::Forest => self.forest,
+            TileType::Water => self.water,
+        }
+    }
+
+    /// Cheapest cost of any tile type that can be entered
+    pub fn min_cost(&self) -> Option<i32> {
+        [self.grass, self.building, self.road, self.forest, self.water].into_iter().flatten().min()
+    }
+
+    /// Costs have to be at least 1 so hex distance stays a lower bound for A*
+    pub fn check(self, arg: &'static str) -> Result<Self, InputError> {
+        if let Some(cost) = self.min_cost().filter(|&cost| cost < 1) {
+            return Err(InputError::new(arg, format!("costs must be at least 1, got {}", cost)));
+        }
+        Ok(self)
+    }
+}
+
+/// Parse tile costs, see `TileCosts::check`
+pub fn parse_tile_costs(arg: &'static str, json: &str) -> Result<TileCosts, InputError> {
+    parse::<TileCosts>(arg, json)?.check(arg)
+}
+
+/// Parse building rules, see `BuildingRules::check`
+pub fn parse_building_rules(arg: &'static str, json: &str) -> Result<BuildingRules, InputError> {
+    parse::<BuildingRules>(arg, json)?.check(arg)
+}
+
+/// Serialize hexes as [{"q":0,"r":0},...]
+pub fn hexes_to_json(hexes: &[(i32, i32)]) -> String {
+    let hexes: Vec<HexCoord> = hexes.iter().map(|&(q, r)| HexCoord { q, r }).collect();
+    serde_json::to_string(&hexes).unwrap_or_else(|_| String::from("[]"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn malformed_hexes_are_rejected() {
+        for json in [
+            r#"[{"q":1.5,"r":0}]"#,
+            r#"[{"q":1.0,"r":0}]"#,
+            r#"[{"q":0,"r":2e1}]"#,
+            r#"[{"q":1}]"#,
+            r#"[{"r":1}]"#,
+            r#"[{"q":"1","r":0}]"#,
+            r#"[{"q":null,"r":0}]"#,
+            r#"[{"q":3000000000,"r":0}]"#,
+            r#"{"q":0,"r":0}"#,
+            r#"[{"q":0,"r":0},]"#,
+            "",
+        ] {
+            let error = parse_hexes("seeds_json", json).unwrap_err();
+            assert_eq!(error.arg, "seeds_json", "{json}");
+            assert!(error.to_string().starts_with("seeds_json: "), "{json}");
+        }
+    }
+
+    #[test]
+    fn malformed_chunk_states_are_rejected() {
+        for json in [
+            r#"[{"q":0,"r":0,"enabled":1}]"#,
+            r#"[{"q":0,"r":0,"enabled":"true"}]"#,
+            r#"[{"q":0,"r":0,"enabled":null}]"#,
+            r#"[{"q":0,"r":0}]"#,
+            r#"[{"q":0.5,"r":0,"enabled":true}]"#,
+        ] {
+            assert!(parse_chunk_states("all_chunks_json", json).is_err(), "{json}");
+        }
+    }
+
+    #[test]
+    fn malformed_rules_and_costs_are_rejected() {
+        for json in [
+            r#"{"minAdjacentRoads":7}"#,
+            r#"{"minAdjacentRoads":-1}"#,
+            r#"{"minAdjacentRoads":1.5}"#,
+        ] {
+            assert!(parse_building_rules("building_rules_json", json).is_err(), "{json}");
+        }
+        for json in [r#"{"grass":1,"lava":3}"#, r#"{"road":0}"#, r#"{"road":2.5}"#] {
+            assert!(parse_tile_costs("tile_costs_json", json).is_err(), "{json}");
+        }
+    }
+
+    #[test]
+    fn valid_input_round_trips() {
+        let hexes = parse_hexes(
+            "hex_coords_json",
+            concat!(
+                r#"[{"q":-3,"r":7},{"q":0,"r":0,"x":1.5},"#,
+                r#"{"r":-2147483648,"q":2147483647},{"q":-3,"r":7}]"#,
+            ),
+        )
+        .unwrap();
+        assert_eq!(hexes, [(-3, 7), (0, 0), (i32::MAX, i32::MIN), (-3, 7)]);
+        let json = hexes_to_json(&hexes);
+        assert_eq!(
+            json,
+            r#"[{"q":-3,"r":7},{"q":0,"r":0},{"q":2147483647,"r":-2147483648},{"q":-3,"r":7}]"#
+        );
+        assert_eq!(parse_hexes("hex_coords_json", &json).unwrap(), hexes);
+        assert_eq!(parse_hex_set("hex_coords_json", &json).unwrap().len(), 3);
+        assert_eq!(hexes_to_json(&[]), "[]");
+
+        let chunks = parse_chunk_states(
+            "all_chunks_json",
+            r#"[{"q":1,"r":-1,"enabled":true},{"q":0,"r":4,"enabled":false}]"#,
+        )
+        .unwrap();
+        let chunks: Vec<(i32, i32, bool)> = chunks.iter().map(|c| (c.q, c.r, c.enabled)).collect();
+        assert_eq!(chunks, [(1, -1, true), (0, 4, false)]);
+
+        let rules = parse_building_rules("building_rules_json", "{}").unwrap();
+        assert_eq!(rules.min_adjacent_roads, 1);
+        let costs =
+            parse_tile_costs("tile_costs_json", r#"{"grass":2,"road":1,"water":null}"#).unwrap();
+        assert_eq!(
+            TileType::ALL.map(|tile| costs.cost(tile)),
+            [Some(2), None, Some(1), None, None]
+        );
+    }
+}
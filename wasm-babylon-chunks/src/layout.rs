@@ -1,8 +1,11 @@
-/// WFC layout generation module
+//! WFC layout generation module
 
 use wasm_bindgen::prelude::*;
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap, HashSet};
 use crate::state::WFC_STATE;
 use crate::types::TileType;
+use crate::hex_utils::{generate_hex_grid, get_hex_neighbors};
 
 /// Initialize the WASM module
 #[wasm_bindgen(start)]
@@ -16,29 +19,44 @@ pub fn init() {
 /// Update this version when making significant changes to help debug caching issues.
 #[wasm_bindgen]
 pub fn get_wasm_version() -> String {
-    "1.1.0-20250102-performance".to_string()
+    "1.2.0-20261016-wfc".to_string()
 }
 
-/// Generate a simplified layout using pre-constraints
+/// Generate a layout with wave function collapse
 /// 
-/// **Learning Point**: This implements a simple algorithm:
-/// 1. Apply pre-constraints to grid (all tile types set by TypeScript)
-/// 2. Fill any remaining empty cells with grass (shouldn't happen if pre-constraints are complete)
+/// **Learning Point**: Every cell starts as a superposition of all tile types.
+/// The cell with the lowest entropy (fewest, least even options) collapses to
+/// one tile picked by weight, and propagation removes from its neighbors every
+/// tile the adjacency rules don't allow next to what is left. When a cell ends
+/// up with no options the last choice is undone and ruled out (backtracking).
+/// 
+/// The cells are the layout region (set_layout_region) plus every
+/// pre-constraint. Pre-constraints are fixed cells that are never changed, even
+/// where two of them break the adjacency rules. Without a region the layout is
+/// just the pre-constraints. If the search gives up, the remaining cells fall
+/// back to grass where their neighbors allow it.
 #[wasm_bindgen]
 pub fn generate_layout() {
     let mut state = WFC_STATE.lock().unwrap();
     state.clear();
     
-    // Step 1: Apply pre-constraints to grid
-    // Pre-constraints take absolute precedence - TypeScript sets all tiles
-    // Collect pre-constraints into a vector first to avoid borrow checker issues
-    let pre_constraints: Vec<((i32, i32), TileType)> = state.pre_constraints().collect();
-    for ((q, r), tile_type) in pre_constraints {
-        state.insert_tile(q, r, tile_type);
+    // Collect pre-constraints into a map first to avoid borrow checker issues
+    let fixed: HashMap<(i32, i32), TileType> = state.pre_constraints().collect();
+    let mut cells: HashSet<(i32, i32)> = fixed.keys().copied().collect();
+    if let Some((center_q, center_r, rings)) = state.layout_region() {
+        cells.extend(generate_hex_grid(rings, center_q, center_r).into_iter().map(|hex| (hex.q, hex.r)));
     }
+    // Sorted so a seed always gives the same layout
+    let mut cells: Vec<(i32, i32)> = cells.into_iter().collect();
+    cells.sort();
     
-    // Step 2: Fill any remaining empty cells with grass (shouldn't be needed if pre-constraints are complete)
-    // This is a safety fallback
+    let mut wfc = Wfc::new(cells, &fixed, &DEFAULT_RULES, state.next_seed());
+    if !wfc.run() {
+        wfc.fall_back();
+    }
+    for ((q, r), tile_type) in wfc.tiles() {
+        state.insert_tile(q, r, tile_type);
+    }
 }
 
 /// Get tile type at a specific hex grid position
@@ -105,12 +123,51 @@ pub fn clear_pre_constraints() {
     state.clear_pre_constraints();
 }
 
+/// Largest layout region, 100 rings is about 30k cells
+const MAX_LAYOUT_RINGS: i32 = 100;
+
+/// Set the hexagon of cells generate_layout fills
+/// 
+/// **Learning Point**: Pre-constraints inside or outside the region are kept as
+/// fixed cells, the rest of the region is generated around them.
+/// 
+/// @param center_q - Center q coordinate (axial)
+/// @param center_r - Center r coordinate (axial)
+/// @param rings - Rings around the center, 0 is just the center (0-100)
+/// @returns true if the region was set, false if rings is out of range
+#[wasm_bindgen]
+pub fn set_layout_region(center_q: i32, center_r: i32, rings: i32) -> bool {
+    if !(0..=MAX_LAYOUT_RINGS).contains(&rings) {
+        return false;
+    }
+    let mut state = WFC_STATE.lock().unwrap();
+    state.set_layout_region(center_q, center_r, rings);
+    true
+}
+
+/// Clear the layout region so generate_layout only places the pre-constraints
+#[wasm_bindgen]
+pub fn clear_layout_region() {
+    let mut state = WFC_STATE.lock().unwrap();
+    state.clear_layout_region();
+}
+
+/// Seed the layout random number generator
+/// 
+/// **Learning Point**: Each generate_layout call moves on to a new seed, so
+/// setting the seed makes the whole sequence of layouts reproducible.
+/// 
+/// @param seed - Any 32 bit value
+#[wasm_bindgen]
+pub fn set_layout_seed(seed: u32) {
+    let mut state = WFC_STATE.lock().unwrap();
+    state.set_seed(seed as u64);
+}
+
 /// Get statistics about the current grid
 /// 
 /// **Learning Point**: This function iterates over the hash map to count all tile types.
 /// Returns a JSON string with counts for each tile type.
-/// Follows the pattern from wasm-agent-tools - builds JSON manually without serde
-/// to keep WASM size small.
 /// 
 /// @returns JSON string with tile counts: {"grass":X,"building":Y,"road":Z,"forest":A,"water":B,"total":C}
 #[wasm_bindgen]
@@ -141,3 +198,439 @@ pub fn get_stats() -> String {
     )
 }
 
+/// Adjacency rules and tile frequencies for the solver
+struct WfcRules {
+    /// Tile types allowed next to each tile type, as bit masks over TileType
+    /// values. Has to be symmetric: a allows b exactly when b allows a.
+    adjacency: [u8; 5],
+    /// Relative frequency of each tile type when a cell collapses
+    weights: [u32; 5],
+}
+
+/// Buildings stay out of forests and water and roads out of water, grass
+/// goes anywhere
+const DEFAULT_RULES: WfcRules = WfcRules {
+    adjacency: [
+        0b11111, // Grass: anything
+        0b00111, // Building: grass, building, road
+        0b01111, // Road: grass, building, road, forest
+        0b11101, // Forest: grass, road, forest, water
+        0b11001, // Water: grass, forest, water
+    ],
+    weights: [12, 2, 3, 5, 3],
+};
+
+/// A superposition of every tile type
+const ALL_TILES: u8 = 0b11111;
+
+const GRASS: u8 = 1 << TileType::Grass as u8;
+
+/// Undone choices before the search gives up and falls back
+const MAX_BACKTRACKS: usize = 10_000;
+
+/// Heap entry for an uncollapsed cell, stale once the cell's options change
+struct EntropyEntry {
+    entropy: f64,
+    cell: usize,
+    options: u8,
+}
+
+impl PartialEq for EntropyEntry {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for EntropyEntry {}
+
+impl Ord for EntropyEntry {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // Reverse order for min-heap (lowest entropy first)
+        other.entropy.total_cmp(&self.entropy)
+    }
+}
+
+impl PartialOrd for EntropyEntry {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Wave function collapse over a set of hex cells
+/// 
+/// Each cell's remaining options are a bit mask over TileType values. Every
+/// change to an option set is recorded on the trail, so backtracking restores
+/// the cells a choice affected instead of copying the whole grid per choice.
+struct Wfc<'a> {
+    rules: &'a WfcRules,
+    cells: Vec<(i32, i32)>,
+    neighbors: Vec<Vec<usize>>,
+    options: Vec<u8>,
+    /// Pre-constraints, and cells the pre-constraints leave no option for
+    fixed: Vec<bool>,
+    /// (cell, options before the change), newest last
+    trail: Vec<(usize, u8)>,
+    heap: BinaryHeap<EntropyEntry>,
+    /// Shannon entropy of every option set
+    entropy: [f64; 32],
+    /// Union of the adjacency masks of every option set
+    allowed: [u8; 32],
+    rng: u64,
+    backtracks: usize,
+}
+
+impl<'a> Wfc<'a> {
+    fn new(
+        cells: Vec<(i32, i32)>,
+        fixed: &HashMap<(i32, i32), TileType>,
+        rules: &'a WfcRules,
+        seed: u64,
+    ) -> Wfc<'a> {
+        let index: HashMap<(i32, i32), usize> = cells.iter().enumerate().map(|(i, &cell)| (cell, i)).collect();
+        let neighbors = cells
+            .iter()
+            .map(|&(q, r)| get_hex_neighbors(q, r).iter().filter_map(|n| index.get(n).copied()).collect())
+            .collect();
+        let options = cells
+            .iter()
+            .map(|cell| fixed.get(cell).map_or(ALL_TILES, |&tile| 1 << tile as u8))
+            .collect();
+        let is_fixed = cells.iter().map(|cell| fixed.contains_key(cell)).collect();
+        
+        let mut entropy = [0.0; 32];
+        let mut allowed = [0; 32];
+        for mask in 1..32u8 {
+            let weights: Vec<f64> = tile_bits(mask).map(|t| rules.weights[t] as f64).collect();
+            let total: f64 = weights.iter().sum();
+            entropy[mask as usize] = total.ln() - weights.iter().map(|w| w * w.ln()).sum::<f64>() / total;
+            allowed[mask as usize] = tile_bits(mask).fold(0, |acc, t| acc | rules.adjacency[t]);
+        }
+        
+        let mut wfc = Wfc {
+            rules,
+            cells,
+            neighbors,
+            options,
+            fixed: is_fixed,
+            trail: Vec::new(),
+            heap: BinaryHeap::new(),
+            entropy,
+            allowed,
+            // Xorshift state has to be non-zero
+            rng: seed ^ 0x2545_f491_4f6c_dd1d | 1,
+            backtracks: 0,
+        };
+        
+        // Narrow the cells around the fixed ones, this is never undone
+        let fixed_cells: Vec<usize> = (0..wfc.cells.len()).filter(|&i| wfc.fixed[i]).collect();
+        wfc.propagate(fixed_cells, true);
+        wfc.trail.clear();
+        for cell in 0..wfc.cells.len() {
+            wfc.push_entry(cell);
+        }
+        wfc
+    }
+    
+    /// Collapse every cell, backtracking on contradictions
+    /// Returns false if the rules can't be met or it took too many backtracks
+    fn run(&mut self) -> bool {
+        // (cell, chosen tile bit, trail length before the choice)
+        let mut choices: Vec<(usize, u8, usize)> = Vec::new();
+        while let Some(cell) = self.lowest_entropy_cell() {
+            let tile = self.pick(self.options[cell]);
+            choices.push((cell, tile, self.trail.len()));
+            self.set_options(cell, tile);
+            let mut consistent = self.propagate(vec![cell], false);
+            
+            // Undo the latest choice and rule its tile out until that holds
+            while !consistent {
+                let Some((cell, tile, trail_len)) = choices.pop() else {
+                    return false;
+                };
+                self.backtracks += 1;
+                if self.backtracks > MAX_BACKTRACKS {
+                    return false;
+                }
+                self.undo(trail_len);
+                // The cell had more than one option when it was chosen
+                self.set_options(cell, self.options[cell] & !tile);
+                consistent = self.propagate(vec![cell], false);
+            }
+        }
+        true
+    }
+    
+    /// Collapse whatever is left without the rules: grass if a cell still
+    /// allows it, otherwise its most frequent option
+    fn fall_back(&mut self) {
+        self.undo(0);
+        for cell in 0..self.cells.len() {
+            let options = self.options[cell];
+            if options.count_ones() > 1 {
+                self.options[cell] = if options & GRASS != 0 {
+                    GRASS
+                } else {
+                    let best = tile_bits(options).max_by_key(|&t| self.rules.weights[t]).unwrap();
+                    1 << best
+                };
+            }
+        }
+    }
+    
+    /// Cell positions and their tiles, call after run or fall_back
+    fn tiles(&self) -> impl Iterator<Item = ((i32, i32), TileType)> + '_ {
+        self.cells.iter().zip(&self.options).map(|(&cell, &options)| {
+            (cell, TileType::ALL[options.trailing_zeros() as usize])
+        })
+    }
+    
+    /// Remove options the neighbors don't allow, spreading out from the given
+    /// cells. Returns false when a cell runs out of options, unless force is
+    /// set, then that cell becomes fixed grass.
+    fn propagate(&mut self, mut stack: Vec<usize>, force: bool) -> bool {
+        while let Some(cell) = stack.pop() {
+            let allowed = self.allowed[self.options[cell] as usize];
+            for i in 0..self.neighbors[cell].len() {
+                let neighbor = self.neighbors[cell][i];
+                if self.fixed[neighbor] {
+                    continue;
+                }
+                let narrowed = self.options[neighbor] & allowed;
+                if narrowed == self.options[neighbor] {
+                    continue;
+                }
+                if narrowed != 0 {
+                    self.set_options(neighbor, narrowed);
+                } else if force {
+                    self.set_options(neighbor, GRASS);
+                    self.fixed[neighbor] = true;
+                } else {
+                    return false;
+                }
+                stack.push(neighbor);
+            }
+        }
+        true
+    }
+    
+    fn set_options(&mut self, cell: usize, options: u8) {
+        self.trail.push((cell, self.options[cell]));
+        self.options[cell] = options;
+        self.push_entry(cell);
+    }
+    
+    /// Restore every option set changed after the trail had trail_len entries
+    fn undo(&mut self, trail_len: usize) {
+        while self.trail.len() > trail_len {
+            let (cell, options) = self.trail.pop().unwrap();
+            self.options[cell] = options;
+            self.push_entry(cell);
+        }
+    }
+    
+    fn push_entry(&mut self, cell: usize) {
+        let options = self.options[cell];
+        if self.fixed[cell] || options.count_ones() < 2 {
+            return;
+        }
+        // A little noise so ties don't always go to the same corner of the grid
+        let noise = (self.next_random() >> 11) as f64 / (1u64 << 53) as f64 * 1e-6;
+        self.heap.push(EntropyEntry {
+            entropy: self.entropy[options as usize] + noise,
+            cell,
+            options,
+        });
+    }
+    
+    fn lowest_entropy_cell(&mut self) -> Option<usize> {
+        while let Some(entry) = self.heap.pop() {
+            // Skip entries whose cell changed since they were pushed
+            if self.options[entry.cell] == entry.options {
+                return Some(entry.cell);
+            }
+        }
+        None
+    }
+    
+    /// One option of the set picked by tile weight, as a single bit
+    fn pick(&mut self, options: u8) -> u8 {
+        let total: u64 = tile_bits(options).map(|t| self.rules.weights[t] as u64).sum();
+        let mut roll = self.next_random() % total;
+        for t in tile_bits(options) {
+            let weight = self.rules.weights[t] as u64;
+            if roll < weight {
+                return 1 << t;
+            }
+            roll -= weight;
+        }
+        unreachable!("roll is below the total weight")
+    }
+    
+    /// Xorshift64*
+    fn next_random(&mut self) -> u64 {
+        self.rng ^= self.rng >> 12;
+        self.rng ^= self.rng << 25;
+        self.rng ^= self.rng >> 27;
+        self.rng.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+}
+
+/// TileType values in an option set
+fn tile_bits(options: u8) -> impl Iterator<Item = usize> {
+    (0..5).filter(move |t| options & (1 << t) != 0)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Proper four-coloring over the first four tile types, the fifth fits
+    /// next to nothing. Hex cells meet in threes, so random pre-constraints
+    /// regularly leave a cell nothing to be.
+    const FOUR_COLORS: WfcRules = WfcRules {
+        adjacency: [0b01110, 0b01101, 0b01011, 0b00111, 0],
+        weights: [5, 4, 3, 2, 1],
+    };
+
+    fn region(rings: i32, center_q: i32, center_r: i32) -> Vec<(i32, i32)> {
+        generate_hex_grid(rings, center_q, center_r).into_iter().map(|hex| (hex.q, hex.r)).collect()
+    }
+
+    /// Roughly one cell in eight pre-constrained to one of the four colors
+    fn random_pre_constraints(cells: &[(i32, i32)], seed: u64) -> HashMap<(i32, i32), TileType> {
+        let mut x = seed.wrapping_mul(7919).wrapping_add(1);
+        let mut fixed = HashMap::new();
+        for cell in cells {
+            x = x.wrapping_mul(6_364_136_223_846_793_005).wrapping_add(1_442_695_040_888_963_407);
+            if (x >> 33).is_multiple_of(8) {
+                fixed.insert(*cell, TileType::ALL[((x >> 40) % 4) as usize]);
+            }
+        }
+        fixed
+    }
+
+    fn allows(rules: &WfcRules, a: TileType, b: TileType) -> bool {
+        rules.adjacency[a as usize] & (1 << b as u8) != 0
+    }
+
+    /// Pre-constraints come out as they went in and every cell has one tile
+    fn assert_pre_constraints(wfc: &Wfc, fixed: &HashMap<(i32, i32), TileType>) {
+        let tiles: HashMap<(i32, i32), TileType> = wfc.tiles().collect();
+        for (cell, tile) in fixed {
+            assert_eq!(tiles[cell], *tile, "pre-constraint at {:?}", cell);
+        }
+        assert!(wfc.options.iter().all(|options| options.count_ones() == 1));
+    }
+
+    /// Every pair of neighbors follows the rules unless both are fixed
+    fn assert_layout(wfc: &Wfc, rules: &WfcRules, fixed: &HashMap<(i32, i32), TileType>) {
+        assert_pre_constraints(wfc, fixed);
+        let tiles: HashMap<(i32, i32), TileType> = wfc.tiles().collect();
+        for (i, neighbors) in wfc.neighbors.iter().enumerate() {
+            for &j in neighbors.iter().filter(|&&j| !(wfc.fixed[i] && wfc.fixed[j])) {
+                let (a, b) = (tiles[&wfc.cells[i]], tiles[&wfc.cells[j]]);
+                assert!(allows(rules, a, b), "{:?} next to {:?}", a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn default_rules_are_symmetric() {
+        for a in TileType::ALL {
+            for b in TileType::ALL {
+                assert_eq!(allows(&DEFAULT_RULES, a, b), allows(&DEFAULT_RULES, b, a), "{:?} {:?}", a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn default_rules_keep_pre_constraints() {
+        let mut cells = region(8, 0, 0);
+        cells.sort();
+        // A building next to water breaks the rules, both stay as they are
+        let fixed = HashMap::from([
+            ((0, 0), TileType::Building),
+            ((1, 0), TileType::Water),
+            ((3, -2), TileType::Road),
+            ((-4, 4), TileType::Forest),
+        ]);
+        for seed in 0..10 {
+            let mut wfc = Wfc::new(cells.clone(), &fixed, &DEFAULT_RULES, seed);
+            assert!(wfc.run(), "seed {}", seed);
+            assert_layout(&wfc, &DEFAULT_RULES, &fixed);
+        }
+    }
+
+    #[test]
+    fn backtracking_recovers_from_contradictions() {
+        let mut cells = region(4, 0, 0);
+        cells.sort();
+        let (mut recovered, mut forced) = (0, 0);
+        for seed in 0..40 {
+            let fixed = random_pre_constraints(&cells, seed);
+            let mut wfc = Wfc::new(cells.clone(), &fixed, &FOUR_COLORS, seed);
+            // Cells the pre-constraints leave nothing for become fixed grass
+            for (i, cell) in wfc.cells.iter().enumerate() {
+                if wfc.fixed[i] && !fixed.contains_key(cell) {
+                    assert_eq!(wfc.options[i], GRASS);
+                    forced += 1;
+                }
+            }
+            // Some pre-constraints can't be met at all, those fall back
+            if wfc.run() {
+                assert_layout(&wfc, &FOUR_COLORS, &fixed);
+                recovered += usize::from(wfc.backtracks > 0);
+            } else {
+                wfc.fall_back();
+                assert_pre_constraints(&wfc, &fixed);
+            }
+        }
+        assert!(recovered > 0);
+        assert!(forced > 0);
+    }
+
+    #[test]
+    fn unsatisfiable_rules_run_out_of_choices() {
+        // Two tiles that only go next to each other can't fill a triangle
+        let rules = WfcRules {
+            adjacency: [0b00010, 0b00001, 0, 0, 0],
+            weights: [1, 1, 1, 1, 1],
+        };
+        let cells = vec![(0, 0), (0, 1), (1, 0)];
+        let mut wfc = Wfc::new(cells, &HashMap::new(), &rules, 1);
+        assert!(!wfc.run());
+        assert!(wfc.backtracks > 0 && wfc.backtracks <= MAX_BACKTRACKS);
+        wfc.fall_back();
+        assert_pre_constraints(&wfc, &HashMap::new());
+    }
+
+    #[test]
+    fn gives_up_after_max_backtracks() {
+        // Tiles 0 and 1 go next to each other, 2 only next to 3 and 4, and 3
+        // and 4 next to 2 and each other. The fixed 2s leave a triangle 3s
+        // and 4s can't fill, but every pair in it still has an option that
+        // fits, so nothing shows until the triangle is collapsed. The 0/1
+        // cells away from it have lower entropy and go first, and undoing
+        // them one at a time tries every combination of them before the
+        // triangle.
+        let rules = WfcRules {
+            adjacency: [0b00011, 0b00011, 0b11000, 0b10100, 0b01100],
+            weights: [1000, 1, 1, 1, 1],
+        };
+        let fixed = HashMap::from([
+            ((-1, 0), TileType::Road),
+            ((2, 0), TileType::Road),
+            ((0, 2), TileType::Road),
+        ]);
+        let mut cells = region(2, 20, 20);
+        cells.extend([(0, 0), (1, 0), (0, 1)]);
+        cells.extend(fixed.keys());
+        cells.sort();
+        let mut wfc = Wfc::new(cells, &fixed, &rules, 7);
+        assert!(!wfc.run());
+        assert_eq!(wfc.backtracks, MAX_BACKTRACKS + 1);
+        wfc.fall_back();
+        assert_pre_constraints(&wfc, &fixed);
+    }
+}
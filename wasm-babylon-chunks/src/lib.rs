@@ -1,21 +1,27 @@
-/// Main library entry point for wasm-babylon-chunks
-/// 
-/// This module organizes the WASM crate into logical sub-modules:
-/// - types: Core type definitions
-/// - state: WFC state management
-/// - hex_utils: Hex coordinate utilities
-/// - astar: A* pathfinding algorithms
-/// - voronoi: Voronoi region generation
-/// - layout: WFC layout generation
-/// - roads: Road network generation
-/// - chunks: Chunk management
-/// - utils: Utility functions
+//! Main library entry point for wasm-babylon-chunks
+//! 
+//! This module organizes the WASM crate into logical sub-modules:
+//! - types: Core type definitions
+//! - json: Typed JSON inputs and outputs for the exports
+//! - packed: Typed-array inputs and outputs for the *_packed exports
+//! - state: WFC state management
+//! - hex_utils: Hex coordinate utilities
+//! - astar: A* pathfinding algorithms
+//! - terrain_graph: Persistent terrain handle for repeated path queries
+//! - voronoi: Voronoi region generation
+//! - layout: WFC layout generation
+//! - roads: Road network generation
+//! - chunks: Chunk management
+//! - utils: Utility functions
 
 // Module declarations
 mod types;
+mod json;
+mod packed;
 mod state;
 mod hex_utils;
 mod astar;
+mod terrain_graph;
 mod voronoi;
 mod layout;
 mod roads;
@@ -26,19 +32,22 @@ mod utils;
 // This maintains the same public API as before the refactoring
 
 // From layout module
-pub use layout::{init, get_wasm_version, generate_layout, get_tile_at, clear_layout, set_pre_constraint, clear_pre_constraints, get_stats};
+pub use layout::{init, get_wasm_version, generate_layout, get_tile_at, clear_layout, set_pre_constraint, clear_pre_constraints, get_stats, set_layout_region, clear_layout_region, set_layout_seed};
 
 // From astar module
-pub use astar::{hex_astar, build_path_between_roads, validate_road_connectivity};
+pub use astar::{hex_astar, build_path_between_roads, validate_road_connectivity, hex_astar_packed, build_path_between_roads_packed, validate_road_connectivity_packed, hex_astar_weighted, hex_astar_weighted_packed};
+
+// From terrain_graph module
+pub use terrain_graph::TerrainGraph;
 
 // From voronoi module
 pub use voronoi::generate_voronoi_regions;
 
 // From roads module
-pub use roads::generate_road_network_growing_tree;
+pub use roads::{generate_road_network_growing_tree, generate_road_network_growing_tree_packed};
 
 // From chunks module
-pub use chunks::{calculate_chunk_radius, calculate_chunk_neighbors, find_nearest_neighbor_chunk, disable_distant_chunks, calculate_chunk_for_tile};
+pub use chunks::{calculate_chunk_radius, calculate_chunk_neighbors, find_nearest_neighbor_chunk, disable_distant_chunks, calculate_chunk_for_tile, calculate_chunk_neighbors_packed, find_nearest_neighbor_chunk_packed, disable_distant_chunks_packed, calculate_chunk_for_tile_packed};
 
 // From utils module
-pub use utils::{batch_get_tile_types, shuffle_array, count_adjacent_roads, get_adjacent_valid_terrain, generate_building_placement, batch_hex_to_world};
+pub use utils::{batch_get_tile_types, shuffle_array, count_adjacent_roads, get_adjacent_valid_terrain, generate_building_placement, batch_hex_to_world, batch_get_tile_types_packed, shuffle_array_packed, count_adjacent_roads_packed, get_adjacent_valid_terrain_packed, generate_building_placement_packed, batch_hex_to_world_packed};
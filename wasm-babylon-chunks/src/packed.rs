@@ -0,0 +1,211 @@
+//! Packed typed-array input and output for the exports
+//!
+//! The `*_packed` exports mirror the JSON ones but take and return flat
+//! buffers, so large hex lists skip string formatting and parsing on both
+//! sides. Hexes are `Int32Array` q,r pairs, world positions `Float32Array`
+//! q,r,x,z tuples. A buffer whose length doesn't divide into whole entries
+//! fails the call with an `InputError` naming the argument.
+
+use std::collections::HashSet;
+use crate::json::{ChunkState, InputError, TileCosts};
+
+/// Packed tile cost of a type that can't be entered
+pub const IMPASSABLE: i32 = -1;
+
+fn check_stride(arg: &'static str, data: &[i32], stride: usize, entry: &str) -> Result<(), InputError> {
+    if !data.len().is_multiple_of(stride) {
+        return Err(InputError::new(
+            arg,
+            format!("length {} is not a multiple of {} ({})", data.len(), stride, entry),
+        ));
+    }
+    Ok(())
+}
+
+/// Unpack q,r pairs in input order, duplicates kept
+pub fn unpack_hexes(arg: &'static str, data: &[i32]) -> Result<Vec<(i32, i32)>, InputError> {
+    check_stride(arg, data, 2, "q,r pairs")?;
+    Ok(data.chunks_exact(2).map(|hex| (hex[0], hex[1])).collect())
+}
+
+/// Unpack q,r pairs into a set for membership checks
+pub fn unpack_hex_set(arg: &'static str, data: &[i32]) -> Result<HashSet<(i32, i32)>, InputError> {
+    Ok(unpack_hexes(arg, data)?.into_iter().collect())
+}
+
+/// Unpack q,r,enabled triples, enabled is 0 or 1
+pub fn unpack_chunk_states(arg: &'static str, data: &[i32]) -> Result<Vec<ChunkState>, InputError> {
+    check_stride(arg, data, 3, "q,r,enabled triples")?;
+    data.chunks_exact(3)
+        .map(|chunk| match chunk[2] {
+            0 | 1 => Ok(ChunkState { q: chunk[0], r: chunk[1], enabled: chunk[2] == 1 }),
+            other => Err(InputError::new(arg, format!("enabled must be 0 or 1, got {}", other))),
+        })
+        .collect()
+}
+
+/// Unpack tile costs indexed by tile type id (Grass, Building, Road, Forest,
+/// Water), `IMPASSABLE` means the type can't be entered and any other cost
+/// has to be at least 1
+pub fn unpack_tile_costs(arg: &'static str, data: &[i32]) -> Result<TileCosts, InputError> {
+    let &[grass, building, road, forest, water] = data else {
+        return Err(InputError::new(arg, format!("expected 5 costs, one per tile type, got {}", data.len())));
+    };
+    let passable = |cost: i32| (cost != IMPASSABLE).then_some(cost);
+    TileCosts {
+        grass: passable(grass),
+        building: passable(building),
+        road: passable(road),
+        forest: passable(forest),
+        water: passable(water),
+    }
+    .check(arg)
+}
+
+/// Pack hexes as q,r pairs
+pub fn pack_hexes(hexes: &[(i32, i32)]) -> Vec<i32> {
+    hexes.iter().flat_map(|&(q, r)| [q, r]).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::astar::{
+        build_path_between_roads, build_path_between_roads_packed, hex_astar, hex_astar_packed,
+    };
+    use crate::chunks::{disable_distant_chunks, disable_distant_chunks_packed};
+    use crate::hex_utils::generate_hex_grid;
+    use crate::json::{hexes_to_json, parse_hexes};
+    use crate::roads::{
+        generate_road_network_growing_tree, generate_road_network_growing_tree_packed,
+    };
+    use crate::utils::{batch_hex_to_world, batch_hex_to_world_packed};
+
+    /// A ring-8 grid with a wall across it that paths have to go around
+    fn terrain() -> Vec<(i32, i32)> {
+        generate_hex_grid(8, 0, 0)
+            .into_iter()
+            .map(|hex| (hex.q, hex.r))
+            .filter(|&(q, r)| q != 1 || r > 5)
+            .collect()
+    }
+
+    #[test]
+    fn short_buffers_are_rejected() {
+        assert_eq!(unpack_hexes("hexes", &[1, 2, 3]).unwrap_err().arg, "hexes");
+        assert_eq!(unpack_hex_set("roads", &[1]).unwrap_err().arg, "roads");
+        assert_eq!(unpack_hexes("hexes", &[]).unwrap(), []);
+        for all_chunks in [&[0, 0][..], &[0, 0, 1, 5], &[0, 0, 1, 5, 6]] {
+            let error = unpack_chunk_states("all_chunks", all_chunks).unwrap_err();
+            assert_eq!(error.arg, "all_chunks");
+        }
+        assert!(unpack_chunk_states("all_chunks", &[0, 0, 2]).is_err());
+        assert!(unpack_chunk_states("all_chunks", &[0, 0, -1]).is_err());
+        let chunks = unpack_chunk_states("all_chunks", &[1, 2, 1, 3, 4, 0]).unwrap();
+        let chunks: Vec<(i32, i32, bool)> = chunks.iter().map(|c| (c.q, c.r, c.enabled)).collect();
+        assert_eq!(chunks, [(1, 2, true), (3, 4, false)]);
+    }
+
+    fn assert_same_path(json: String, packed: Option<Vec<i32>>) {
+        match packed {
+            Some(packed) => {
+                let path = unpack_hexes("path", &packed).unwrap();
+                assert_eq!(parse_hexes("path", &json).unwrap(), path);
+            }
+            None => assert_eq!(json, "null"),
+        }
+    }
+
+    #[test]
+    fn paths_match_the_json_exports() {
+        let terrain = terrain();
+        let terrain_json = hexes_to_json(&terrain);
+        let terrain_packed = pack_hexes(&terrain);
+        let pairs = [((-4, 0), (5, -2)), ((0, 0), (0, 0)), ((-4, 0), (20, 0)), ((3, 3), (-6, 2))];
+        for ((start_q, start_r), (goal_q, goal_r)) in pairs {
+            assert_same_path(
+                hex_astar(start_q, start_r, goal_q, goal_r, terrain_json.clone()).unwrap(),
+                hex_astar_packed(start_q, start_r, goal_q, goal_r, &terrain_packed).unwrap(),
+            );
+            assert_same_path(
+                build_path_between_roads(start_q, start_r, goal_q, goal_r, terrain_json.clone())
+                    .unwrap(),
+                build_path_between_roads_packed(start_q, start_r, goal_q, goal_r, &terrain_packed)
+                    .unwrap(),
+            );
+        }
+    }
+
+    #[test]
+    fn road_networks_match_the_json_export() {
+        let terrain = terrain();
+        let seeds = [(-4, 0), (5, -2), (0, 7), (20, 20)];
+        let occupied = [(-3, 0), (-2, -1), (4, 4)];
+        for target_count in [0, 10, 80, 1000] {
+            let json = generate_road_network_growing_tree(
+                hexes_to_json(&seeds),
+                hexes_to_json(&terrain),
+                hexes_to_json(&occupied),
+                target_count,
+            )
+            .unwrap();
+            let packed = generate_road_network_growing_tree_packed(
+                &pack_hexes(&seeds),
+                &pack_hexes(&terrain),
+                &pack_hexes(&occupied),
+                target_count,
+            )
+            .unwrap();
+            let roads = unpack_hexes("roads", &packed).unwrap();
+            assert_eq!(parse_hexes("roads", &json).unwrap(), roads);
+        }
+    }
+
+    #[test]
+    fn distant_chunks_match_the_json_export() {
+        let chunks: Vec<ChunkState> = generate_hex_grid(3, 0, 0)
+            .into_iter()
+            .enumerate()
+            .map(|(i, hex)| ChunkState { q: hex.q * 5, r: hex.r * 5, enabled: i % 3 != 0 })
+            .collect();
+        let chunks_json = serde_json::to_string(
+            &chunks
+                .iter()
+                .map(|c| serde_json::json!({"q": c.q, "r": c.r, "enabled": c.enabled}))
+                .collect::<Vec<_>>(),
+        )
+        .unwrap();
+        let chunks_packed: Vec<i32> =
+            chunks.iter().flat_map(|c| [c.q, c.r, c.enabled as i32]).collect();
+
+        for ((q, r), max_distance) in [((0, 0), 5), ((5, -5), 10), ((-15, 0), 0), ((0, 0), 100)] {
+            let json = disable_distant_chunks(q, r, chunks_json.clone(), max_distance).unwrap();
+            let packed = disable_distant_chunks_packed(q, r, &chunks_packed, max_distance).unwrap();
+            let packed = unpack_chunk_states("changes", &packed).unwrap();
+
+            let json: serde_json::Value = serde_json::from_str(&json).unwrap();
+            let to_disable = parse_hexes("toDisable", &json["toDisable"].to_string()).unwrap();
+            let to_enable = parse_hexes("toEnable", &json["toEnable"].to_string()).unwrap();
+            let changed = |enabled: bool| -> Vec<(i32, i32)> {
+                packed.iter().filter(|c| c.enabled == enabled).map(|c| (c.q, c.r)).collect()
+            };
+            assert_eq!(to_disable, changed(false));
+            assert_eq!(to_enable, changed(true));
+        }
+    }
+
+    #[test]
+    fn world_positions_match_the_json_export() {
+        let hexes = [(0, 0), (3, -7), (-12, 4), (3, -7), (100, 100)];
+        for hex_size in [1.0, 2.5, 0.0] {
+            let json = batch_hex_to_world(hexes_to_json(&hexes), hex_size).unwrap();
+            let packed = batch_hex_to_world_packed(&pack_hexes(&hexes), hex_size).unwrap();
+            let json: Vec<serde_json::Value> = serde_json::from_str(&json).unwrap();
+            assert_eq!(packed.len(), 4 * json.len());
+            for (position, tuple) in json.iter().zip(packed.chunks_exact(4)) {
+                let value = |key: &str| position[key].as_f64().unwrap() as f32;
+                assert_eq!([value("q"), value("r"), value("x"), value("z")], tuple);
+            }
+        }
+    }
+}
@@ -1,41 +1,19 @@
-/// Road network generation module
+//! Road network generation module
 
 use wasm_bindgen::prelude::*;
-use std::collections::HashSet;
-use crate::astar::hex_astar;
-use crate::hex_utils::{parse_valid_terrain_json, parse_path_json, hex_distance};
-
-/// Find nearest point in connected set to a given point
-/// Returns the nearest point and its distance
-fn find_nearest_in_set(
-    point: (i32, i32),
-    connected_set: &HashSet<(i32, i32)>,
-) -> Option<((i32, i32), i32)> {
-    if connected_set.is_empty() {
-        return None;
-    }
-    
-    let mut nearest: Option<(i32, i32)> = None;
-    let mut min_distance = i32::MAX;
-    
-    for &connected_point in connected_set {
-        let dist = hex_distance(point.0, point.1, connected_point.0, connected_point.1);
-        if dist < min_distance {
-            min_distance = dist;
-            nearest = Some(connected_point);
-        }
-    }
-    
-    nearest.map(|n| (n, min_distance))
-}
+use std::collections::{HashSet, VecDeque};
+use crate::hex_utils::get_hex_neighbors;
+use crate::json::{parse_hexes, parse_hex_set, hexes_to_json};
+use crate::packed::{unpack_hexes, unpack_hex_set, pack_hexes};
+use crate::terrain_graph::TerrainGraph;
 
 /// Generate road network using true growing tree algorithm
 /// 
 /// Algorithm:
 /// 1. Start with first seed point
 /// 2. For each remaining seed: find nearest connected road, build A* path, add path
-/// 3. For expansion: repeatedly find nearest unconnected valid terrain to any connected road,
-///    build A* path, add path. Continue until target count reached.
+/// 3. For expansion: repeatedly add the nearest unconnected valid terrain to any connected road,
+///    which is always next to one, until target count reached.
 /// 
 /// This creates a true tree structure where every road is connected via a path,
 /// not just adjacent (which would be flood fill).
@@ -45,139 +23,126 @@ fn find_nearest_in_set(
 /// @param occupied_json - JSON array of occupied hexes: [{"q":0,"r":0},...]
 /// @param target_count - Target number of roads to generate
 /// @returns JSON array of road coordinates: [{"q":0,"r":0},...]
+/// @throws if any JSON argument is malformed
 #[wasm_bindgen]
 pub fn generate_road_network_growing_tree(
     seeds_json: String,
     valid_terrain_json: String,
     occupied_json: String,
     target_count: i32,
-) -> String {
-    // Parse inputs
-    let seeds = parse_valid_terrain_json(&seeds_json);
-    let valid_terrain = parse_valid_terrain_json(&valid_terrain_json);
-    let occupied = parse_valid_terrain_json(&occupied_json);
+) -> Result<String, JsError> {
+    // Parse inputs, seeds keep their order so the first seed is the root
+    let seeds = parse_hexes("seeds_json", &seeds_json)?;
+    let valid_terrain = parse_hex_set("valid_terrain_json", &valid_terrain_json)?;
+    let occupied = parse_hex_set("occupied_json", &occupied_json)?;
     
-    // Build valid terrain set (valid terrain minus occupied)
-    let mut valid_terrain_set = HashSet::new();
-    for &hex in &valid_terrain {
-        if !occupied.contains(&hex) {
-            valid_terrain_set.insert(hex);
-        }
-    }
-    
-    // Convert valid terrain to JSON for hex_astar calls
-    let mut valid_terrain_vec: Vec<(i32, i32)> = valid_terrain_set.iter().cloned().collect();
-    valid_terrain_vec.sort();
-    let mut valid_terrain_json_parts = Vec::new();
-    for (q, r) in &valid_terrain_vec {
-        valid_terrain_json_parts.push(format!(r#"{{"q":{},"r":{}}}"#, q, r));
-    }
-    let valid_terrain_json_for_astar = format!("[{}]", valid_terrain_json_parts.join(","));
-    
-    // Connected set: roads in the network
-    let mut connected: HashSet<(i32, i32)> = HashSet::new();
+    Ok(hexes_to_json(&grow_road_network(&seeds, &valid_terrain, &occupied, target_count)))
+}
+
+/// Packed variant of `generate_road_network_growing_tree`
+/// 
+/// @param seeds - Int32Array of seed q,r pairs
+/// @param valid_terrain - Int32Array of valid terrain q,r pairs
+/// @param occupied - Int32Array of occupied q,r pairs
+/// @param target_count - Target number of roads to generate
+/// @returns Int32Array of road q,r pairs
+/// @throws if any array has an odd length
+#[wasm_bindgen]
+pub fn generate_road_network_growing_tree_packed(
+    seeds: &[i32],
+    valid_terrain: &[i32],
+    occupied: &[i32],
+    target_count: i32,
+) -> Result<Vec<i32>, JsError> {
+    let seeds = unpack_hexes("seeds", seeds)?;
+    let valid_terrain = unpack_hex_set("valid_terrain", valid_terrain)?;
+    let occupied = unpack_hex_set("occupied", occupied)?;
     
-    // Unconnected set: valid terrain not yet roads
-    let mut unconnected: HashSet<(i32, i32)> = valid_terrain_set.clone();
+    Ok(pack_hexes(&grow_road_network(&seeds, &valid_terrain, &occupied, target_count)))
+}
+
+/// The growing tree itself, returns the roads sorted
+fn grow_road_network(
+    seeds: &[(i32, i32)],
+    valid_terrain: &HashSet<(i32, i32)>,
+    occupied: &HashSet<(i32, i32)>,
+    target_count: i32,
+) -> Vec<(i32, i32)> {
+    // Roads grow over valid terrain minus occupied
+    let walkable = valid_terrain.difference(occupied).copied().collect();
+    let mut graph = TerrainGraph::with_sets(walkable, HashSet::new());
     
-    // Phase 1: Connect seed points
-    if !seeds.is_empty() {
-        let first_seed = seeds.iter().next().copied();
-        if let Some(seed) = first_seed {
-            if valid_terrain_set.contains(&seed) {
-                connected.insert(seed);
-                unconnected.remove(&seed);
-            }
+    // Phase 1: the first valid seed is the root, the others join the nearest road
+    for &(q, r) in seeds {
+        if !graph.is_walkable(q, r) {
+            continue;
         }
-        
-        // Connect remaining seeds
-        for seed in seeds.iter().skip(1) {
-            if !valid_terrain_set.contains(seed) {
-                continue;
-            }
-            
-            if connected.is_empty() {
-                // No connected roads yet, add seed directly
-                connected.insert(*seed);
-                unconnected.remove(seed);
-                continue;
-            }
-            
-            // Find nearest connected road
-            if let Some((nearest_road, _)) = find_nearest_in_set(*seed, &connected) {
-                // Build path from nearest road to seed
-                let path_json = hex_astar(
-                    nearest_road.0,
-                    nearest_road.1,
-                    seed.0,
-                    seed.1,
-                    valid_terrain_json_for_astar.clone(),
-                );
-                
-                if path_json != "null" && !path_json.is_empty() {
-                    let path = parse_path_json(&path_json);
-                    // Add all path hexes to connected
-                    for path_hex in path {
-                        connected.insert(path_hex);
-                        unconnected.remove(&path_hex);
-                    }
-                }
+        let Some((nearest_road, _)) = graph.nearest_road_to((q, r)) else {
+            graph.set_road(q, r, true);
+            continue;
+        };
+        // Seeds with no path to that road are left out
+        if let Some(path) = graph.path(nearest_road, (q, r)) {
+            for (path_q, path_r) in path {
+                graph.set_road(path_q, path_r, true);
             }
         }
     }
     
-    // Phase 2: Expand to target density using growing tree
-    while (connected.len() as i32) < target_count && !unconnected.is_empty() {
-        let mut best_unconnected: Option<(i32, i32)> = None;
-        let mut best_connected: Option<(i32, i32)> = None;
-        let mut min_distance = i32::MAX;
-        
-        // Find nearest unconnected point to any connected road
-        for &unconnected_point in &unconnected {
-            if let Some((nearest_road, distance)) = find_nearest_in_set(unconnected_point, &connected) {
-                if distance < min_distance {
-                    min_distance = distance;
-                    best_unconnected = Some(unconnected_point);
-                    best_connected = Some(nearest_road);
-                }
-            }
-        }
-        
-        // Build path and add to network
-        if let (Some(unconnected_point), Some(connected_road)) = (best_unconnected, best_connected) {
-            let path_json = hex_astar(
-                connected_road.0,
-                connected_road.1,
-                unconnected_point.0,
-                unconnected_point.1,
-                valid_terrain_json_for_astar.clone(),
-            );
-            
-            if path_json != "null" && !path_json.is_empty() {
-                let path = parse_path_json(&path_json);
-                // Add all path hexes to connected
-                for path_hex in path {
-                    connected.insert(path_hex);
-                    unconnected.remove(&path_hex);
-                }
-            } else {
-                // Can't reach this point, remove it from unconnected
-                unconnected.remove(&unconnected_point);
-            }
-        } else {
+    // Phase 2: the nearest unconnected hex is always next to a road, so growing to
+    // the target is a breadth-first search out from every road at once
+    let mut queue: VecDeque<(i32, i32)> = graph.roads_sorted().into();
+    while (graph.road_count() as i32) < target_count {
+        let Some((q, r)) = queue.pop_front() else {
             // No more reachable points
             break;
+        };
+        for (neighbor_q, neighbor_r) in get_hex_neighbors(q, r) {
+            if (graph.road_count() as i32) >= target_count {
+                break;
+            }
+            // set_road is false for hexes that are already roads
+            if graph.is_walkable(neighbor_q, neighbor_r)
+                && graph.set_road(neighbor_q, neighbor_r, true)
+            {
+                queue.push_back((neighbor_q, neighbor_r));
+            }
         }
     }
     
-    // Convert connected set to JSON array
-    let mut road_vec: Vec<(i32, i32)> = connected.iter().cloned().collect();
-    road_vec.sort();
-    let mut json_parts = Vec::new();
-    for (q, r) in road_vec {
-        json_parts.push(format!(r#"{{"q":{},"r":{}}}"#, q, r));
-    }
-    
-    format!("[{}]", json_parts.join(","))
+    graph.roads_sorted()
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hex_utils::generate_hex_grid;
+
+    #[test]
+    fn growing_tree_is_connected_and_stops_at_target() {
+        let valid_terrain: HashSet<(i32, i32)> =
+            generate_hex_grid(12, 0, 0).into_iter().map(|hex| (hex.q, hex.r)).collect();
+        // A wall across the grid with a gap at the top, and a seed sealed off by it
+        let mut occupied: HashSet<(i32, i32)> = (-12..=8).map(|r| (2, r)).collect();
+        occupied.extend(get_hex_neighbors(-3, 10));
+        let sealed_seed = (-3, 10);
+        let seeds = [(-6, 0), (8, -4), sealed_seed, (0, -12), (-6, 0)];
+
+        let network = grow_road_network(&seeds, &valid_terrain, &occupied, 0);
+        let roads: HashSet<(i32, i32)> = network.iter().copied().collect();
+        assert!(roads.contains(&(-6, 0)) && roads.contains(&(8, -4)) && roads.contains(&(0, -12)));
+        assert!(!roads.contains(&sealed_seed));
+        assert!(TerrainGraph::with_sets(HashSet::new(), roads).roads_connected());
+
+        for target_count in [60, 200] {
+            let network = grow_road_network(&seeds, &valid_terrain, &occupied, target_count);
+            assert_eq!(network.len(), target_count as usize);
+            assert!(network.windows(2).all(|pair| pair[0] < pair[1]));
+            assert!(network
+                .iter()
+                .all(|hex| valid_terrain.contains(hex) && !occupied.contains(hex)));
+            let roads: HashSet<(i32, i32)> = network.iter().copied().collect();
+            assert!(TerrainGraph::with_sets(HashSet::new(), roads).roads_connected());
+        }
+    }
+}
@@ -1,4 +1,4 @@
-/// WFC state management module
+//! WFC state management module
 
 use std::sync::{LazyLock, Mutex};
 use std::collections::HashMap;
@@ -11,19 +11,58 @@ use crate::types::TileType;
 pub struct WfcState {
     grid: HashMap<(i32, i32), TileType>,
     pre_constraints: HashMap<(i32, i32), TileType>,
+    /// Center q, center r and rings of the hexagon generate_layout fills
+    layout_region: Option<(i32, i32, i32)>,
+    seed: u64,
 }
 
+/// Seed used until set_seed is called
+const DEFAULT_SEED: u64 = 0x5eed;
+
 impl WfcState {
     pub fn new() -> Self {
         WfcState {
             grid: HashMap::new(),
             pre_constraints: HashMap::new(),
+            layout_region: None,
+            seed: DEFAULT_SEED,
         }
     }
     
     pub fn clear(&mut self) {
         self.grid.clear();
-        // DO NOT clear pre_constraints - they must persist
+        // DO NOT clear pre_constraints, the region or the seed - they must persist
+    }
+    
+    /// Set the hexagon of cells generate_layout fills around the pre-constraints
+    pub fn set_layout_region(&mut self, center_q: i32, center_r: i32, rings: i32) {
+        self.layout_region = Some((center_q, center_r, rings));
+    }
+    
+    /// Go back to only laying out the pre-constrained cells
+    pub fn clear_layout_region(&mut self) {
+        self.layout_region = None;
+    }
+    
+    pub fn layout_region(&self) -> Option<(i32, i32, i32)> {
+        self.layout_region
+    }
+    
+    pub fn set_seed(&mut self, seed: u64) {
+        self.seed = seed;
+    }
+    
+    /// Seed for the next layout, each call gets a different one so repeated
+    /// layouts differ but the sequence is the same after set_seed
+    pub fn next_seed(&mut self) -> u64 {
+        let seed = self.seed;
+        // SplitMix64 step
+        self.seed = self.seed.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.seed;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        self.seed = z ^ (z >> 31);
+        seed
     }
     
     /// Set a pre-constraint at a specific hex position (q, r)
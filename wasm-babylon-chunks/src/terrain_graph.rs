@@ -0,0 +1,389 @@
+//! Persistent terrain graph module
+//!
+//! `TerrainGraph` is a JS-owned handle that keeps walkable hexes and roads in
+//! memory between calls, so path queries don't parse and rebuild the terrain
+//! set each time. Connected components of the walkable hexes are cached and
+//! kept up to date as tiles are added; removing a tile that had walkable
+//! neighbors drops the cache, which is rebuilt on the next query that needs it.
+//! Roads are also bucketed by position so nearest-road queries only look at
+//! the buckets around the query hex.
+
+use wasm_bindgen::prelude::*;
+use std::collections::{HashMap, HashSet, VecDeque};
+use crate::astar::find_hex_path;
+use crate::hex_utils::{get_hex_neighbors, hex_distance};
+use crate::json::{parse_hex_set, hexes_to_json};
+use crate::packed::{unpack_hexes, unpack_hex_set, pack_hexes};
+
+/// Side of the square axial buckets roads are indexed by
+const ROAD_BUCKET: i32 = 16;
+
+/// Walkable hexes and roads answering path, nearest-road and connectivity queries
+#[wasm_bindgen]
+pub struct TerrainGraph {
+    walkable: HashSet<(i32, i32)>,
+    roads: HashSet<(i32, i32)>,
+    /// Roads by `road_bucket`, kept in step with `roads`
+    road_buckets: HashMap<(i32, i32), Vec<(i32, i32)>>,
+    /// Component id of every walkable hex, None until a query needs it
+    components: Option<HashMap<(i32, i32), u32>>,
+    next_component: u32,
+}
+
+#[wasm_bindgen]
+impl TerrainGraph {
+    /// Create an empty graph
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> TerrainGraph {
+        TerrainGraph::with_sets(HashSet::new(), HashSet::new())
+    }
+
+    /// Build a graph from packed hexes
+    ///
+    /// @param walkable - Int32Array of q,r pairs paths may use
+    /// @param roads - Int32Array of road q,r pairs
+    /// @throws if either array has an odd length
+    pub fn from_packed(walkable: &[i32], roads: &[i32]) -> Result<TerrainGraph, JsError> {
+        Ok(TerrainGraph::with_sets(
+            unpack_hex_set("walkable", walkable)?,
+            unpack_hex_set("roads", roads)?,
+        ))
+    }
+
+    /// Build a graph from JSON hex lists
+    ///
+    /// @param walkable_json - JSON array of hexes paths may use: [{"q":0,"r":0},...]
+    /// @param roads_json - JSON array of road coordinates: [{"q":0,"r":0},...]
+    /// @throws if either JSON argument is malformed
+    pub fn from_json(walkable_json: String, roads_json: String) -> Result<TerrainGraph, JsError> {
+        Ok(TerrainGraph::with_sets(
+            parse_hex_set("walkable_json", &walkable_json)?,
+            parse_hex_set("roads_json", &roads_json)?,
+        ))
+    }
+
+    pub fn walkable_count(&self) -> u32 {
+        self.walkable.len() as u32
+    }
+
+    pub fn road_count(&self) -> u32 {
+        self.roads.len() as u32
+    }
+
+    pub fn is_walkable(&self, q: i32, r: i32) -> bool {
+        self.walkable.contains(&(q, r))
+    }
+
+    pub fn is_road(&self, q: i32, r: i32) -> bool {
+        self.roads.contains(&(q, r))
+    }
+
+    /// Make a hex walkable or not
+    /// @returns true if that changed anything
+    pub fn set_walkable(&mut self, q: i32, r: i32, walkable: bool) -> bool {
+        if walkable {
+            self.add_walkable((q, r))
+        } else {
+            self.remove_walkable((q, r))
+        }
+    }
+
+    /// set_walkable for every hex of a packed q,r list
+    /// @returns Number of hexes that changed
+    /// @throws if hexes has an odd length
+    pub fn set_walkable_packed(&mut self, hexes: &[i32], walkable: bool) -> Result<u32, JsError> {
+        let hexes = unpack_hexes("hexes", hexes)?;
+        Ok(hexes.into_iter().filter(|&(q, r)| self.set_walkable(q, r, walkable)).count() as u32)
+    }
+
+    /// Make a hex a road or not, roads don't have to be walkable
+    /// @returns true if that changed anything
+    pub fn set_road(&mut self, q: i32, r: i32, road: bool) -> bool {
+        let changed = if road {
+            self.roads.insert((q, r))
+        } else {
+            self.roads.remove(&(q, r))
+        };
+        if changed {
+            let bucket = self.road_buckets.entry(road_bucket((q, r))).or_default();
+            if road {
+                bucket.push((q, r));
+            } else {
+                bucket.retain(|&hex| hex != (q, r));
+                if bucket.is_empty() {
+                    self.road_buckets.remove(&road_bucket((q, r)));
+                }
+            }
+        }
+        changed
+    }
+
+    /// set_road for every hex of a packed q,r list
+    /// @returns Number of hexes that changed
+    /// @throws if hexes has an odd length
+    pub fn set_roads_packed(&mut self, hexes: &[i32], road: bool) -> Result<u32, JsError> {
+        let hexes = unpack_hexes("hexes", hexes)?;
+        Ok(hexes.into_iter().filter(|&(q, r)| self.set_road(q, r, road)).count() as u32)
+    }
+
+    /// Shortest path over walkable hexes, see `find_hex_path`
+    /// @returns Int32Array of path q,r pairs (start and goal included), or undefined if no path found
+    pub fn find_path(&mut self, start_q: i32, start_r: i32, goal_q: i32, goal_r: i32) -> Option<Vec<i32>> {
+        self.path((start_q, start_r), (goal_q, goal_r)).map(|path| pack_hexes(&path))
+    }
+
+    /// JSON variant of `find_path`
+    /// @returns JSON string with path array [{"q":0,"r":0},...] or "null" if no path found
+    pub fn find_path_json(&mut self, start_q: i32, start_r: i32, goal_q: i32, goal_r: i32) -> String {
+        match self.path((start_q, start_r), (goal_q, goal_r)) {
+            Some(path) => hexes_to_json(&path),
+            None => "null".to_string(),
+        }
+    }
+
+    /// Whether a path over walkable hexes joins the two hexes
+    pub fn is_connected(&mut self, a_q: i32, a_r: i32, b_q: i32, b_r: i32) -> bool {
+        let components = self.components();
+        match (components.get(&(a_q, a_r)), components.get(&(b_q, b_r))) {
+            (Some(a), Some(b)) => a == b,
+            _ => false,
+        }
+    }
+
+    /// Number of separate walkable areas
+    pub fn component_count(&mut self) -> u32 {
+        let components = self.components();
+        components.values().collect::<HashSet<_>>().len() as u32
+    }
+
+    /// Road nearest to a hex by hex distance, ties go to the smallest (q, r)
+    /// @returns Int32Array [q, r, distance] or undefined if there are no roads
+    pub fn nearest_road(&self, q: i32, r: i32) -> Option<Vec<i32>> {
+        self.nearest_road_to((q, r))
+            .map(|((road_q, road_r), distance)| vec![road_q, road_r, distance])
+    }
+
+    /// Whether every road is reachable from every other one moving only over
+    /// roads, like `validate_road_connectivity`
+    pub fn roads_connected(&self) -> bool {
+        let Some(&source) = self.roads.iter().next() else {
+            // No roads is trivially connected
+            return true;
+        };
+        flood(source, &self.roads).len() == self.roads.len()
+    }
+}
+
+impl Default for TerrainGraph {
+    fn default() -> Self {
+        TerrainGraph::new()
+    }
+}
+
+impl TerrainGraph {
+    pub(crate) fn with_sets(
+        walkable: HashSet<(i32, i32)>,
+        roads: HashSet<(i32, i32)>,
+    ) -> TerrainGraph {
+        let mut road_buckets: HashMap<(i32, i32), Vec<(i32, i32)>> = HashMap::new();
+        for &road in &roads {
+            road_buckets.entry(road_bucket(road)).or_default().push(road);
+        }
+        TerrainGraph {
+            walkable,
+            roads,
+            road_buckets,
+            components: None,
+            next_component: 0,
+        }
+    }
+
+    /// Nearest road and its hex distance, ties go to the smallest (q, r)
+    ///
+    /// Walks the buckets ring by ring around the hex's bucket. Every hex of a
+    /// bucket `ring` buckets away is at least `(ring - 1) * ROAD_BUCKET + 1`
+    /// away on q or r, so the walk stops once that passes the best distance.
+    pub(crate) fn nearest_road_to(&self, (q, r): (i32, i32)) -> Option<((i32, i32), i32)> {
+        let (center_q, center_r) = road_bucket((q, r));
+        let mut best: Option<(i32, (i32, i32))> = None;
+        let consider = |best: &mut Option<(i32, (i32, i32))>, roads: &[(i32, i32)]| {
+            for &road in roads {
+                let candidate = (hex_distance(q, r, road.0, road.1), road);
+                if best.is_none_or(|best| candidate < best) {
+                    *best = Some(candidate);
+                }
+            }
+        };
+        for ring in 0.. {
+            if best.is_some_and(|(distance, _)| (ring - 1) * ROAD_BUCKET + 1 > distance) {
+                break;
+            }
+            let ring_size = if ring == 0 { 1 } else { 8 * ring as usize };
+            if ring_size >= self.road_buckets.len() {
+                // Fewer buckets left than the ring has, look at all of them instead
+                for (&(bucket_q, bucket_r), roads) in &self.road_buckets {
+                    if (bucket_q - center_q).abs().max((bucket_r - center_r).abs()) >= ring {
+                        consider(&mut best, roads);
+                    }
+                }
+                break;
+            }
+            for bucket_q in center_q - ring..=center_q + ring {
+                // The first and last columns lie on the ring, the others only at their ends
+                let step = if (bucket_q - center_q).abs() == ring { 1 } else { (2 * ring).max(1) };
+                let mut bucket_r = center_r - ring;
+                while bucket_r <= center_r + ring {
+                    if let Some(roads) = self.road_buckets.get(&(bucket_q, bucket_r)) {
+                        consider(&mut best, roads);
+                    }
+                    bucket_r += step;
+                }
+            }
+        }
+        best.map(|(distance, road)| (road, distance))
+    }
+
+    /// Roads sorted so the order doesn't depend on the set's iteration order
+    pub(crate) fn roads_sorted(&self) -> Vec<(i32, i32)> {
+        let mut roads: Vec<(i32, i32)> = self.roads.iter().copied().collect();
+        roads.sort();
+        roads
+    }
+
+    pub(crate) fn path(&mut self, start: (i32, i32), goal: (i32, i32)) -> Option<Vec<(i32, i32)>> {
+        // Without this, a goal in another area makes A* flood the whole start area
+        if !self.is_connected(start.0, start.1, goal.0, goal.1) {
+            return None;
+        }
+        find_hex_path(start, goal, &self.walkable)
+    }
+
+    fn add_walkable(&mut self, hex: (i32, i32)) -> bool {
+        if !self.walkable.insert(hex) {
+            return false;
+        }
+        if let Some(components) = &mut self.components {
+            let joined: HashSet<u32> = get_hex_neighbors(hex.0, hex.1)
+                .iter()
+                .filter_map(|neighbor| components.get(neighbor).copied())
+                .collect();
+            match joined.len() {
+                0 => {
+                    components.insert(hex, self.next_component);
+                    self.next_component += 1;
+                }
+                1 => {
+                    components.insert(hex, *joined.iter().next().unwrap());
+                }
+                // Merges areas, relabel on the next query
+                _ => self.components = None,
+            }
+        }
+        true
+    }
+
+    fn remove_walkable(&mut self, hex: (i32, i32)) -> bool {
+        if !self.walkable.remove(&hex) {
+            return false;
+        }
+        let has_neighbors = get_hex_neighbors(hex.0, hex.1)
+            .iter()
+            .any(|neighbor| self.walkable.contains(neighbor));
+        if has_neighbors {
+            // May split an area, relabel on the next query
+            self.components = None;
+        } else if let Some(components) = &mut self.components {
+            components.remove(&hex);
+        }
+        true
+    }
+
+    fn components(&mut self) -> &HashMap<(i32, i32), u32> {
+        if self.components.is_none() {
+            let mut components = HashMap::with_capacity(self.walkable.len());
+            let mut next_component = 0;
+            for &hex in &self.walkable {
+                if components.contains_key(&hex) {
+                    continue;
+                }
+                for member in flood(hex, &self.walkable) {
+                    components.insert(member, next_component);
+                }
+                next_component += 1;
+            }
+            self.components = Some(components);
+            self.next_component = next_component;
+        }
+        self.components.as_ref().unwrap()
+    }
+}
+
+/// Bucket of the road index a hex falls in
+fn road_bucket((q, r): (i32, i32)) -> (i32, i32) {
+    (q.div_euclid(ROAD_BUCKET), r.div_euclid(ROAD_BUCKET))
+}
+
+/// All hexes of the set reachable from start moving between neighbors in the set
+fn flood(start: (i32, i32), hexes: &HashSet<(i32, i32)>) -> HashSet<(i32, i32)> {
+    let mut reached = HashSet::from([start]);
+    let mut queue = VecDeque::from([start]);
+    while let Some((q, r)) = queue.pop_front() {
+        for neighbor in get_hex_neighbors(q, r) {
+            if hexes.contains(&neighbor) && reached.insert(neighbor) {
+                queue.push_back(neighbor);
+            }
+        }
+    }
+    reached
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn nearest_by_scan(
+        roads: &HashSet<(i32, i32)>,
+        (q, r): (i32, i32),
+    ) -> Option<((i32, i32), i32)> {
+        roads
+            .iter()
+            .map(|&road| (hex_distance(q, r, road.0, road.1), road))
+            .min()
+            .map(|(distance, road)| (road, distance))
+    }
+
+    #[test]
+    fn nearest_road_matches_a_linear_scan() {
+        let mut graph = TerrainGraph::new();
+        assert_eq!(graph.nearest_road_to((0, 0)), None);
+
+        let mut x: u64 = 7;
+        let mut next = |range: i32| {
+            x = x.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            ((x >> 33) % (2 * range as u64 + 1)) as i32 - range
+        };
+        let mut roads = Vec::new();
+        for _ in 0..300 {
+            roads.push((next(100), next(100)));
+        }
+        // A few far off roads so some queries cross many empty buckets
+        roads.extend([(5000, -3000), (-4000, 10), (37, 9000)]);
+        for &(q, r) in &roads {
+            graph.set_road(q, r, true);
+        }
+        for &(q, r) in roads.iter().step_by(3) {
+            graph.set_road(q, r, false);
+        }
+
+        for _ in 0..500 {
+            let hex = (next(6000), next(6000));
+            let expected = nearest_by_scan(&graph.roads, hex);
+            assert_eq!(graph.nearest_road_to(hex), expected, "from {hex:?}");
+        }
+        for _ in 0..500 {
+            let hex = (next(120), next(120));
+            let expected = nearest_by_scan(&graph.roads, hex);
+            assert_eq!(graph.nearest_road_to(hex), expected, "from {hex:?}");
+        }
+    }
+}
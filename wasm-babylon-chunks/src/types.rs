@@ -1,4 +1,6 @@
-/// Core type definitions for the WASM module
+//! Core type definitions for the WASM module
+
+use serde::{Deserialize, Serialize};
 
 /// Tile type enumeration for 5 simple tile types
 /// 
@@ -14,8 +16,20 @@ pub enum TileType {
     Water = 4,
 }
 
-/// Hex coordinate structure for Voronoi generation
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+impl TileType {
+    /// Every tile type, indexed by its i32 value
+    pub const ALL: [TileType; 5] = [
+        TileType::Grass,
+        TileType::Building,
+        TileType::Road,
+        TileType::Forest,
+        TileType::Water,
+    ];
+}
+
+/// Hex coordinate structure for Voronoi generation and the `{"q":0,"r":0}`
+/// entries of the JSON exports
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct HexCoord {
     pub q: i32,
     pub r: i32,
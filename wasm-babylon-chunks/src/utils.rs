@@ -1,108 +1,64 @@
-/// Utility functions module
+//! Utility functions module
 
 use wasm_bindgen::prelude::*;
 use std::collections::HashSet;
 use crate::state::WFC_STATE;
-use crate::hex_utils::{parse_valid_terrain_json, get_hex_neighbors};
+use crate::hex_utils::get_hex_neighbors;
+use crate::types::TileType;
+use crate::json::{parse_hexes, parse_hex_set, parse_building_rules, hexes_to_json, BuildingRules, InputError};
+use crate::packed::{unpack_hexes, unpack_hex_set, pack_hexes};
 
 /// Batch query tile types for multiple hex coordinates
 /// Returns JSON array with tile types: [{"q":0,"r":0,"tileType":1},...]
 /// 
 /// @param hex_coords_json - JSON array of hex coordinates: [{"q":0,"r":0},...]
-/// @returns JSON array with tile types for each coordinate
+/// @returns JSON array with tile types for each coordinate, in input order
+/// @throws if hex_coords_json is malformed
 #[wasm_bindgen]
-pub fn batch_get_tile_types(hex_coords_json: String) -> String {
-    let state = WFC_STATE.lock().unwrap();
-    
+pub fn batch_get_tile_types(hex_coords_json: String) -> Result<String, JsError> {
     // Parse hex coordinates
-    let hex_coords = parse_valid_terrain_json(&hex_coords_json);
+    let hex_coords = parse_hexes("hex_coords_json", &hex_coords_json)?;
     
     let mut json_parts = Vec::new();
-    for (q, r) in hex_coords {
-        if let Some(tile) = state.get_tile(q, r) {
-            json_parts.push(format!(
-                r#"{{"q":{},"r":{},"tileType":{}}}"#,
-                q, r, tile as i32
-            ));
-        }
+    for ((q, r), tile) in tile_types(&hex_coords) {
+        json_parts.push(format!(
+            r#"{{"q":{},"r":{},"tileType":{}}}"#,
+            q, r, tile as i32
+        ));
     }
     
-    format!("[{}]", json_parts.join(","))
+    Ok(format!("[{}]", json_parts.join(",")))
 }
 
-/// Shuffle array in WASM using Fisher-Yates algorithm
-/// Returns shuffled JSON array
+/// Packed variant of `batch_get_tile_types`
 /// 
-/// @param array_json - JSON array to shuffle: [{"q":0,"r":0},...]
-/// @returns Shuffled JSON array
+/// @param hex_coords - Int32Array of q,r pairs
+/// @returns Int32Array of q,r,tileType triples, in input order
+/// @throws if hex_coords has an odd length
 #[wasm_bindgen]
-pub fn shuffle_array(array_json: String) -> String {
-    // Parse array
-    let mut coords: Vec<(i32, i32)> = Vec::new();
-    
-    let trimmed = array_json.trim();
-    if trimmed.is_empty() || trimmed == "[]" {
-        return "[]".to_string();
-    }
-    
-    // Simple JSON parsing: find all {"q":X,"r":Y} patterns
-    let mut i = 0;
-    let chars: Vec<char> = trimmed.chars().collect();
-    while i < chars.len() {
-        if chars[i] == '{' {
-            let mut q_value: Option<i32> = None;
-            let mut r_value: Option<i32> = None;
-            
-            i += 1;
-            while i < chars.len() && chars[i] != '}' {
-                if i + 3 < chars.len() && chars[i] == '"' && chars[i + 1] == 'q' && chars[i + 2] == '"' {
-                    i += 3;
-                    while i < chars.len() && (chars[i] == ':' || chars[i] == ' ' || chars[i] == '\t') {
-                        i += 1;
-                    }
-                    if i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '-') {
-                        let start = i;
-                        i += 1;
-                        while i < chars.len() && chars[i].is_ascii_digit() {
-                            i += 1;
-                        }
-                        let num_str: String = chars[start..i].iter().collect();
-                        if let Ok(num) = num_str.parse::<i32>() {
-                            q_value = Some(num);
-                        }
-                    }
-                } else if i + 3 < chars.len() && chars[i] == '"' && chars[i + 1] == 'r' && chars[i + 2] == '"' {
-                    i += 3;
-                    while i < chars.len() && (chars[i] == ':' || chars[i] == ' ' || chars[i] == '\t') {
-                        i += 1;
-                    }
-                    if i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '-') {
-                        let start = i;
-                        i += 1;
-                        while i < chars.len() && chars[i].is_ascii_digit() {
-                            i += 1;
-                        }
-                        let num_str: String = chars[start..i].iter().collect();
-                        if let Ok(num) = num_str.parse::<i32>() {
-                            r_value = Some(num);
-                        }
-                    }
-                } else {
-                    i += 1;
-                }
-            }
-            
-            if let (Some(q), Some(r)) = (q_value, r_value) {
-                coords.push((q, r));
-            }
-        }
-        i += 1;
-    }
+pub fn batch_get_tile_types_packed(hex_coords: &[i32]) -> Result<Vec<i32>, JsError> {
+    let hex_coords = unpack_hexes("hex_coords", hex_coords)?;
     
-    // Fisher-Yates shuffle using a simple PRNG
-    // Use a deterministic seed based on array content for reproducibility
+    Ok(tile_types(&hex_coords)
+        .into_iter()
+        .flat_map(|((q, r), tile)| [q, r, tile as i32])
+        .collect())
+}
+
+/// Tile types of the hexes that have one, in input order
+fn tile_types(hex_coords: &[(i32, i32)]) -> Vec<((i32, i32), TileType)> {
+    let state = WFC_STATE.lock().unwrap();
+    hex_coords
+        .iter()
+        .filter_map(|&(q, r)| state.get_tile(q, r).map(|tile| ((q, r), tile)))
+        .collect()
+}
+
+/// Fisher-Yates shuffle using a simple PRNG
+/// Uses a deterministic seed based on array content for reproducibility
+fn shuffle_hexes(coords: &mut [(i32, i32)]) {
     let mut seed: u64 = 0;
-    for (q, r) in &coords {
+    for (q, r) in coords.iter() {
         seed = seed.wrapping_mul(31).wrapping_add((*q as u64).wrapping_mul(17).wrapping_add(*r as u64));
     }
     
@@ -116,14 +72,31 @@ pub fn shuffle_array(array_json: String) -> String {
         let j = (rng() % (i as u64 + 1)) as usize;
         coords.swap(i, j);
     }
-    
-    // Convert back to JSON
-    let mut json_parts = Vec::new();
-    for (q, r) in coords {
-        json_parts.push(format!(r#"{{"q":{},"r":{}}}"#, q, r));
-    }
-    
-    format!("[{}]", json_parts.join(","))
+}
+
+/// Shuffle array in WASM using Fisher-Yates algorithm
+/// Returns shuffled JSON array
+/// 
+/// @param array_json - JSON array to shuffle: [{"q":0,"r":0},...]
+/// @returns Shuffled JSON array
+/// @throws if array_json is malformed
+#[wasm_bindgen]
+pub fn shuffle_array(array_json: String) -> Result<String, JsError> {
+    let mut coords = parse_hexes("array_json", &array_json)?;
+    shuffle_hexes(&mut coords);
+    Ok(hexes_to_json(&coords))
+}
+
+/// Packed variant of `shuffle_array`
+/// 
+/// @param array - Int32Array of q,r pairs to shuffle
+/// @returns Shuffled Int32Array of q,r pairs
+/// @throws if array has an odd length
+#[wasm_bindgen]
+pub fn shuffle_array_packed(array: &[i32]) -> Result<Vec<i32>, JsError> {
+    let mut coords = unpack_hexes("array", array)?;
+    shuffle_hexes(&mut coords);
+    Ok(pack_hexes(&coords))
 }
 
 /// Count adjacent roads for a given hex coordinate
@@ -132,12 +105,26 @@ pub fn shuffle_array(array_json: String) -> String {
 /// @param hex_r - Hex r coordinate
 /// @param road_network_json - JSON array of road coordinates: [{"q":0,"r":0},...]
 /// @returns Number of adjacent roads (0-6)
+/// @throws if road_network_json is malformed
 #[wasm_bindgen]
-pub fn count_adjacent_roads(hex_q: i32, hex_r: i32, road_network_json: String) -> i32 {
-    let roads = parse_valid_terrain_json(&road_network_json);
-    let roads_set: HashSet<(i32, i32)> = roads.iter().cloned().collect();
-    
-    let neighbors = get_hex_neighbors(hex_q, hex_r);
+pub fn count_adjacent_roads(hex_q: i32, hex_r: i32, road_network_json: String) -> Result<i32, JsError> {
+    let roads_set = parse_hex_set("road_network_json", &road_network_json)?;
+    Ok(adjacent_road_count((hex_q, hex_r), &roads_set))
+}
+
+/// Packed variant of `count_adjacent_roads`
+/// 
+/// @param road_network - Int32Array of road q,r pairs
+/// @returns Number of adjacent roads (0-6)
+/// @throws if road_network has an odd length
+#[wasm_bindgen]
+pub fn count_adjacent_roads_packed(hex_q: i32, hex_r: i32, road_network: &[i32]) -> Result<i32, JsError> {
+    let roads_set = unpack_hex_set("road_network", road_network)?;
+    Ok(adjacent_road_count((hex_q, hex_r), &roads_set))
+}
+
+fn adjacent_road_count(hex: (i32, i32), roads_set: &HashSet<(i32, i32)>) -> i32 {
+    let neighbors = get_hex_neighbors(hex.0, hex.1);
     let mut count = 0;
     
     for (nq, nr) in neighbors {
@@ -159,24 +146,50 @@ pub fn count_adjacent_roads(hex_q: i32, hex_r: i32, road_network_json: String) -
 /// @param valid_terrain_json - JSON array of valid terrain: [{"q":0,"r":0},...]
 /// @param occupied_json - JSON array of occupied hexes: [{"q":0,"r":0},...]
 /// @returns JSON array of adjacent valid terrain: [{"q":0,"r":0},...]
+/// @throws if any JSON argument is malformed
 #[wasm_bindgen]
 pub fn get_adjacent_valid_terrain(
     road_network_json: String,
     valid_terrain_json: String,
     occupied_json: String,
-) -> String {
-    let roads = parse_valid_terrain_json(&road_network_json);
-    let valid_terrain = parse_valid_terrain_json(&valid_terrain_json);
-    let occupied = parse_valid_terrain_json(&occupied_json);
+) -> Result<String, JsError> {
+    let roads_set = parse_hex_set("road_network_json", &road_network_json)?;
+    let valid_terrain_set = parse_hex_set("valid_terrain_json", &valid_terrain_json)?;
+    let occupied_set = parse_hex_set("occupied_json", &occupied_json)?;
     
-    let roads_set: HashSet<(i32, i32)> = roads.iter().cloned().collect();
-    let valid_terrain_set: HashSet<(i32, i32)> = valid_terrain.iter().cloned().collect();
-    let occupied_set: HashSet<(i32, i32)> = occupied.iter().cloned().collect();
+    Ok(hexes_to_json(&adjacent_valid_terrain(&roads_set, &valid_terrain_set, &occupied_set)))
+}
+
+/// Packed variant of `get_adjacent_valid_terrain`
+/// 
+/// @param road_network - Int32Array of road q,r pairs
+/// @param valid_terrain - Int32Array of valid terrain q,r pairs
+/// @param occupied - Int32Array of occupied q,r pairs
+/// @returns Int32Array of adjacent valid terrain q,r pairs
+/// @throws if any array has an odd length
+#[wasm_bindgen]
+pub fn get_adjacent_valid_terrain_packed(
+    road_network: &[i32],
+    valid_terrain: &[i32],
+    occupied: &[i32],
+) -> Result<Vec<i32>, JsError> {
+    let roads_set = unpack_hex_set("road_network", road_network)?;
+    let valid_terrain_set = unpack_hex_set("valid_terrain", valid_terrain)?;
+    let occupied_set = unpack_hex_set("occupied", occupied)?;
     
+    Ok(pack_hexes(&adjacent_valid_terrain(&roads_set, &valid_terrain_set, &occupied_set)))
+}
+
+/// Free valid terrain next to a road, sorted
+fn adjacent_valid_terrain(
+    roads_set: &HashSet<(i32, i32)>,
+    valid_terrain_set: &HashSet<(i32, i32)>,
+    occupied_set: &HashSet<(i32, i32)>,
+) -> Vec<(i32, i32)> {
     let mut adjacent_hexes: HashSet<(i32, i32)> = HashSet::new();
     
     // For each road, find its neighbors
-    for (road_q, road_r) in roads {
+    for &(road_q, road_r) in roads_set {
         let neighbors = get_hex_neighbors(road_q, road_r);
         for (nq, nr) in neighbors {
             let neighbor_key = (nq, nr);
@@ -198,16 +211,9 @@ pub fn get_adjacent_valid_terrain(
         }
     }
     
-    // Convert to JSON
     let mut adjacent_vec: Vec<(i32, i32)> = adjacent_hexes.iter().cloned().collect();
     adjacent_vec.sort();
-    
-    let mut json_parts = Vec::new();
-    for (q, r) in adjacent_vec {
-        json_parts.push(format!(r#"{{"q":{},"r":{}}}"#, q, r));
-    }
-    
-    format!("[{}]", json_parts.join(","))
+    adjacent_vec
 }
 
 /// Generate building placement on valid terrain adjacent to roads
@@ -218,6 +224,7 @@ pub fn get_adjacent_valid_terrain(
 /// @param building_rules_json - JSON string with building rules: {"minAdjacentRoads":1}
 /// @param target_count - Target number of buildings to place
 /// @returns JSON array of building positions: [{"q":0,"r":0},...]
+/// @throws if any JSON argument is malformed, minAdjacentRoads is outside 0-6 or target_count is negative
 #[wasm_bindgen]
 pub fn generate_building_placement(
     valid_terrain_json: String,
@@ -225,117 +232,133 @@ pub fn generate_building_placement(
     occupied_json: String,
     building_rules_json: String,
     target_count: i32,
-) -> String {
-    let valid_terrain = parse_valid_terrain_json(&valid_terrain_json);
-    let roads = parse_valid_terrain_json(&road_network_json);
-    let occupied = parse_valid_terrain_json(&occupied_json);
-    
-    let roads_set: HashSet<(i32, i32)> = roads.iter().cloned().collect();
-    let occupied_set: HashSet<(i32, i32)> = occupied.iter().cloned().collect();
+) -> Result<String, JsError> {
+    let valid_terrain = parse_hex_set("valid_terrain_json", &valid_terrain_json)?;
+    let roads_set = parse_hex_set("road_network_json", &road_network_json)?;
+    let occupied_set = parse_hex_set("occupied_json", &occupied_json)?;
+    let rules = parse_building_rules("building_rules_json", &building_rules_json)?;
+    let target_count = check_target_count(target_count)?;
     
-    // Parse building rules
-    let mut min_adjacent_roads = 1;
-    let trimmed_rules = building_rules_json.trim();
-    if !trimmed_rules.is_empty() && trimmed_rules != "{}" {
-        // Simple JSON parsing for minAdjacentRoads
-        let chars: Vec<char> = trimmed_rules.chars().collect();
-        let mut i = 0;
-        while i < chars.len() {
-            if i + 18 < chars.len() && chars[i] == '"' && chars[i + 1] == 'm' && chars[i + 2] == 'i' 
-                && chars[i + 3] == 'n' && chars[i + 4] == 'A' && chars[i + 5] == 'd' 
-                && chars[i + 6] == 'j' && chars[i + 7] == 'a' && chars[i + 8] == 'c' 
-                && chars[i + 9] == 'e' && chars[i + 10] == 'n' && chars[i + 11] == 't' 
-                && chars[i + 12] == 'R' && chars[i + 13] == 'o' && chars[i + 14] == 'a' 
-                && chars[i + 15] == 'd' && chars[i + 16] == 's' && chars[i + 17] == '"' {
-                i += 18;
-                while i < chars.len() && (chars[i] == ':' || chars[i] == ' ' || chars[i] == '\t') {
-                    i += 1;
-                }
-                if i < chars.len() && chars[i].is_ascii_digit() {
-                    let start = i;
-                    i += 1;
-                    while i < chars.len() && chars[i].is_ascii_digit() {
-                        i += 1;
-                    }
-                    let num_str: String = chars[start..i].iter().collect();
-                    if let Ok(num) = num_str.parse::<i32>() {
-                        min_adjacent_roads = num;
-                    }
-                }
-                break;
-            }
-            i += 1;
-        }
-    }
+    Ok(hexes_to_json(&building_placement(&valid_terrain, &roads_set, &occupied_set, rules, target_count)))
+}
+
+/// Packed variant of `generate_building_placement`, the rules are passed as numbers
+/// 
+/// @param valid_terrain - Int32Array of valid terrain q,r pairs
+/// @param road_network - Int32Array of road q,r pairs
+/// @param occupied - Int32Array of occupied q,r pairs
+/// @param min_adjacent_roads - Roads a building needs next to it (0-6)
+/// @param target_count - Target number of buildings to place
+/// @returns Int32Array of building q,r pairs
+/// @throws if any array has an odd length, min_adjacent_roads is outside 0-6 or target_count is negative
+#[wasm_bindgen]
+pub fn generate_building_placement_packed(
+    valid_terrain: &[i32],
+    road_network: &[i32],
+    occupied: &[i32],
+    min_adjacent_roads: i32,
+    target_count: i32,
+) -> Result<Vec<i32>, JsError> {
+    let valid_terrain = unpack_hex_set("valid_terrain", valid_terrain)?;
+    let roads_set = unpack_hex_set("road_network", road_network)?;
+    let occupied_set = unpack_hex_set("occupied", occupied)?;
+    let rules = BuildingRules { min_adjacent_roads }.check("min_adjacent_roads")?;
+    let target_count = check_target_count(target_count)?;
     
+    Ok(pack_hexes(&building_placement(&valid_terrain, &roads_set, &occupied_set, rules, target_count)))
+}
+
+fn check_target_count(target_count: i32) -> Result<usize, InputError> {
+    usize::try_from(target_count)
+        .map_err(|_| InputError::new("target_count", format!("must not be negative, got {}", target_count)))
+}
+
+/// Up to target_count free hexes meeting the rules, in a shuffled order
+fn building_placement(
+    valid_terrain: &HashSet<(i32, i32)>,
+    roads_set: &HashSet<(i32, i32)>,
+    occupied_set: &HashSet<(i32, i32)>,
+    rules: BuildingRules,
+    target_count: usize,
+) -> Vec<(i32, i32)> {
     // Find available hexes for buildings
     let mut available_building_hexes: Vec<(i32, i32)> = Vec::new();
     
-    for (terrain_q, terrain_r) in &valid_terrain {
-        let terrain_key = (*terrain_q, *terrain_r);
-        
+    for &terrain_key in valid_terrain {
         // Skip if occupied
         if occupied_set.contains(&terrain_key) {
             continue;
         }
         
-        // Count adjacent roads
-        let neighbors = get_hex_neighbors(*terrain_q, *terrain_r);
-        let mut adjacent_road_count = 0;
-        for (nq, nr) in neighbors {
-            if roads_set.contains(&(nq, nr)) {
-                adjacent_road_count += 1;
-            }
-        }
-        
         // Check if meets minimum adjacent roads requirement
-        if adjacent_road_count >= min_adjacent_roads {
+        if adjacent_road_count(terrain_key, roads_set) >= rules.min_adjacent_roads {
             available_building_hexes.push(terrain_key);
         }
     }
     
-    // Shuffle available building hexes
-    if available_building_hexes.len() > 1 {
-        // Use deterministic seed based on content
-        let mut seed: u64 = 0;
-        for (q, r) in &available_building_hexes {
-            seed = seed.wrapping_mul(31).wrapping_add((*q as u64).wrapping_mul(17).wrapping_add(*r as u64));
-        }
-        
-        let mut rng_state = seed;
-        let mut rng = || {
-            rng_state = rng_state.wrapping_mul(1103515245).wrapping_add(12345);
-            rng_state
-        };
-        
-        for i in (1..available_building_hexes.len()).rev() {
-            let j = (rng() % (i as u64 + 1)) as usize;
-            available_building_hexes.swap(i, j);
-        }
-    }
+    // Shuffle available building hexes, sorted first so the result only
+    // depends on the input and not on the set's iteration order
+    available_building_hexes.sort();
+    shuffle_hexes(&mut available_building_hexes);
     
     // Limit to target count
-    let building_count = target_count.min(available_building_hexes.len() as i32);
-    let selected_buildings = &available_building_hexes[0..(building_count as usize)];
+    available_building_hexes.truncate(target_count);
+    available_building_hexes
+}
+
+/// Batch convert hex coordinates to world positions
+/// 
+/// @param hex_coords_json - JSON array of hex coordinates: [{"q":0,"r":0},...]
+/// @param hex_size - Size of hexagon for coordinate conversion
+/// @returns JSON array with world positions in input order: [{"q":0,"r":0,"x":0.0,"z":0.0},...]
+/// @throws if hex_coords_json is malformed or hex_size isn't a finite number
+#[wasm_bindgen]
+pub fn batch_hex_to_world(hex_coords_json: String, hex_size: f64) -> Result<String, JsError> {
+    let hex_coords = parse_hexes("hex_coords_json", &hex_coords_json)?;
+    let hex_size = check_hex_size(hex_size)?;
     
-    // Convert to JSON
     let mut json_parts = Vec::new();
-    for (q, r) in selected_buildings {
-        json_parts.push(format!(r#"{{"q":{},"r":{}}}"#, q, r));
+    for (q, r) in hex_coords {
+        let (x, z) = hex_to_world(q, r, hex_size);
+        json_parts.push(format!(
+            r#"{{"q":{},"r":{},"x":{},"z":{}}}"#,
+            q, r, x, z
+        ));
     }
     
-    format!("[{}]", json_parts.join(","))
+    Ok(format!("[{}]", json_parts.join(",")))
 }
 
-/// Batch convert hex coordinates to world positions
+/// Packed variant of `batch_hex_to_world`
 /// 
-/// @param hex_coords_json - JSON array of hex coordinates: [{"q":0,"r":0},...]
+/// @param hex_coords - Int32Array of q,r pairs
 /// @param hex_size - Size of hexagon for coordinate conversion
-/// @returns JSON array with world positions: [{"q":0,"r":0,"x":0.0,"z":0.0},...]
+/// @returns Float32Array of q,r,x,z tuples, in input order
+/// @throws if hex_coords has an odd length or hex_size isn't a finite number
 #[wasm_bindgen]
-pub fn batch_hex_to_world(hex_coords_json: String, hex_size: f64) -> String {
-    let hex_coords = parse_valid_terrain_json(&hex_coords_json);
+pub fn batch_hex_to_world_packed(hex_coords: &[i32], hex_size: f64) -> Result<Vec<f32>, JsError> {
+    let hex_coords = unpack_hexes("hex_coords", hex_coords)?;
+    let hex_size = check_hex_size(hex_size)?;
     
+    Ok(hex_coords
+        .into_iter()
+        .flat_map(|(q, r)| {
+            let (x, z) = hex_to_world(q, r, hex_size);
+            [q as f32, r as f32, x as f32, z as f32]
+        })
+        .collect())
+}
+
+/// NaN or infinite positions would not be valid JSON
+fn check_hex_size(hex_size: f64) -> Result<f64, InputError> {
+    if !hex_size.is_finite() {
+        return Err(InputError::new("hex_size", format!("must be a finite number, got {}", hex_size)));
+    }
+    Ok(hex_size)
+}
+
+/// World x,z of a hex center
+fn hex_to_world(q: i32, r: i32, hex_size: f64) -> (f64, f64) {
     // Formula for pointy-top hexagons:
     // x = size * (√3 * q + √3/2 * r)
     // z = size * (3/2 * r)
@@ -343,19 +366,62 @@ pub fn batch_hex_to_world(hex_coords_json: String, hex_size: f64) -> String {
     let adjusted_hex_size = hex_size / 1.34;
     let sqrt3 = 3.0_f64.sqrt();
     
-    let mut json_parts = Vec::new();
-    for (q, r) in hex_coords {
-        let q_f = q as f64;
-        let r_f = r as f64;
-        let x = adjusted_hex_size * (sqrt3 * 2.0 * q_f + sqrt3 * r_f);
-        let z = adjusted_hex_size * (3.0 * r_f);
-        
-        json_parts.push(format!(
-            r#"{{"q":{},"r":{},"x":{},"z":{}}}"#,
-            q, r, x, z
-        ));
-    }
-    
-    format!("[{}]", json_parts.join(","))
+    let q_f = q as f64;
+    let r_f = r as f64;
+    let x = adjusted_hex_size * (sqrt3 * 2.0 * q_f + sqrt3 * r_f);
+    let z = adjusted_hex_size * (3.0 * r_f);
+    (x, z)
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hex_utils::generate_hex_grid;
+
+    #[test]
+    fn building_placement_only_depends_on_the_input() {
+        let grid: Vec<(i32, i32)> =
+            generate_hex_grid(6, 0, 0).into_iter().map(|hex| (hex.q, hex.r)).collect();
+        let roads: HashSet<(i32, i32)> = (-6..=6).map(|q| (q, 0)).collect();
+        // Callers pass the roads as occupied too
+        let mut occupied = roads.clone();
+        occupied.extend([(0, 1), (1, -1), (-2, 1)]);
+        let rules = BuildingRules { min_adjacent_roads: 2 };
+
+        let forward: HashSet<(i32, i32)> = grid.iter().copied().collect();
+        let mut backward = HashSet::new();
+        for &hex in grid.iter().rev() {
+            backward.insert(hex);
+        }
+        let placed = building_placement(&forward, &roads, &occupied, rules, usize::MAX);
+        assert_eq!(placed, building_placement(&backward, &roads, &occupied, rules, usize::MAX));
+        assert!(placed.iter().all(|hex| !occupied.contains(hex)));
+        assert!(placed.iter().all(|&hex| adjacent_road_count(hex, &roads) >= 2));
+
+        // Every free hex on the rows either side of the road has two road neighbors
+        let mut expected: Vec<(i32, i32)> = grid
+            .iter()
+            .copied()
+            .filter(|&(_, r)| r == 1 || r == -1)
+            .filter(|hex| !occupied.contains(hex) && adjacent_road_count(*hex, &roads) >= 2)
+            .collect();
+        expected.sort();
+        let mut sorted = placed.clone();
+        sorted.sort();
+        assert_eq!(sorted, expected);
+
+        // A smaller target keeps a prefix of the same shuffle
+        let few = building_placement(&backward, &roads, &occupied, rules, 5);
+        assert_eq!(few, placed[..5]);
+    }
+
+    #[test]
+    fn out_of_range_numbers_are_rejected() {
+        assert_eq!(check_target_count(0).unwrap(), 0);
+        assert_eq!(check_target_count(-1).unwrap_err().arg, "target_count");
+        assert_eq!(check_hex_size(1.5).unwrap(), 1.5);
+        for hex_size in [f64::NAN, f64::INFINITY, f64::NEG_INFINITY] {
+            assert_eq!(check_hex_size(hex_size).unwrap_err().arg, "hex_size");
+        }
+    }
+}
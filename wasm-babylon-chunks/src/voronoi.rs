@@ -1,4 +1,4 @@
-/// Voronoi region generation module
+//! Voronoi region generation module
 
 use wasm_bindgen::prelude::*;
 use crate::types::{TileType, VoronoiSeed};
@@ -39,12 +39,9 @@ pub fn generate_voronoi_regions(
     };
     
     let hex_count = hex_vec.len();
-    match hex_count {
-        0 => {
-            // If hex_vec is empty, return at least one default entry
-            return r#"[{"q":0,"r":0,"tileType":0}]"#.to_string();
-        },
-        _ => {},
+    if hex_count == 0 {
+        // If hex_vec is empty, return at least one default entry
+        return r#"[{"q":0,"r":0,"tileType":0}]"#.to_string();
     }
     
     // Generate seed points by sampling from actual hex grid coordinates
@@ -104,20 +101,17 @@ pub fn generate_voronoi_regions(
     
     // CRITICAL: If no seeds were generated, force generation of at least one grass seed
     // This should never happen with positive seed counts, but ensures function always works
-    match seeds.as_slice() {
-        [] => {
-            match hex_vec.first() {
-                Some(&(q, r)) => {
-                    seeds.push(VoronoiSeed {
-                        q,
-                        r,
-                        tile_type: TileType::Grass,
-                    });
-                },
-                None => return r#"[{"q":0,"r":0,"tileType":0}]"#.to_string(),
-            }
-        },
-        _ => {},
+    if seeds.is_empty() {
+        match hex_vec.first() {
+            Some(&(q, r)) => {
+                seeds.push(VoronoiSeed {
+                    q,
+                    r,
+                    tile_type: TileType::Grass,
+                });
+            },
+            None => return r#"[{"q":0,"r":0,"tileType":0}]"#.to_string(),
+        }
     }
     
     // Assign each hex to nearest seed and build JSON
@@ -132,14 +126,11 @@ pub fn generate_voronoi_regions(
         let nearest_seed = seeds_ref.iter()
             .min_by_key(|seed| hex_distance(hex.q, hex.r, seed.q, seed.r));
         
-        match nearest_seed {
-            Some(seed) => {
-                json_parts.push(format!(
-                    r#"{{"q":{},"r":{},"tileType":{}}}"#,
-                    hex.q, hex.r, seed.tile_type as i32
-                ));
-            },
-            None => {},
+        if let Some(seed) = nearest_seed {
+            json_parts.push(format!(
+                r#"{{"q":{},"r":{},"tileType":{}}}"#,
+                hex.q, hex.r, seed.tile_type as i32
+            ));
         }
     }
     
@@ -162,9 +153,8 @@ pub fn generate_voronoi_regions(
     };
     
     // Final safety check - ensure we never return empty array
-    match json_parts.as_slice() {
-        [] => return r#"[{"q":555,"r":555,"tileType":0}]"#.to_string(),
-        _ => {},
+    if json_parts.is_empty() {
+        return r#"[{"q":555,"r":555,"tileType":0}]"#.to_string();
     }
     
     let result = format!("[{}]", json_parts.join(","));
@@ -442,6 +442,7 @@ pub fn generate_lsystem(width: u32, height: u32) -> Vec<u8> {
     image_data
 }
 
+#[allow(clippy::too_many_arguments)]
 fn draw_line(image_data: &mut [u8], width: u32, height: u32, x1: f64, y1: f64, x2: f64, y2: f64, color_scheme: u32) {
     let steps = ((x2 - x1).abs().max((y2 - y1).abs()) as u32).max(1);
     for i in 0..=steps {
@@ -140,7 +140,7 @@ pub fn get_text_stats(text: &str) -> String {
     let character_count = text.chars().count() as u32;
     let character_count_no_spaces = text.chars().filter(|c| !c.is_whitespace()).count() as u32;
     
-    let sentence_count = text.split(|c: char| c == '.' || c == '!' || c == '?')
+    let sentence_count = text.split(['.', '!', '?'])
         .filter(|s| !s.trim().is_empty())
         .count() as u32;
     